@@ -1,11 +1,12 @@
 use std::path::PathBuf;
 
 use clap::{ Parser };
+use tokio::{sync::oneshot, task::JoinHandle};
 
 use crate::{
     commands::{Command, serve},
     config::Config,
-    error::Result,
+    error::{Error, Result},
 };
 
 #[derive(Parser)]
@@ -19,6 +20,11 @@ pub struct Args {
     #[arg(short, action = clap::ArgAction::Count)]
     pub verbosity: u8,
 
+    /// Server to send commands to, `host[:port]`; defaults to `RKLLM_HOST`,
+    /// then the configured `base_url`
+    #[arg(long, value_name = "HOST")]
+    pub host: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
@@ -31,18 +37,28 @@ impl Args {
                 serve::run(config, &args).await
             }
             Some(command) => {
-                let (_server_handle, shutdown_tx) = crate::server::start_background_server(config).await?;
-                let tx = if matches!(command, Command::Stop(_)) {
-                    Some(shutdown_tx)
-                } else {
-                    None
-                };
-                command.run(config, tx).await
+                let _server = connect(config).await?;
+                command.run(config).await
             }
             None => {
-                let (_server_handle, shutdown_tx) = crate::server::start_background_server(config).await?;
-                crate::commands::run_repl(config, shutdown_tx).await
+                let _server = connect(config).await?;
+                crate::commands::run_repl(config).await
             }
         }
     }
 }
+
+/// Uses the server answering at the client host, or starts one in this
+/// process when none does and the host is local. A started server runs
+/// until the returned sender is dropped.
+async fn connect(config: &Config) -> Result<Option<(JoinHandle<()>, oneshot::Sender<()>)>> {
+    let host = config.client_host();
+    if crate::commands::is_server_running(config, &host).await {
+        tracing::debug!(%host, "using running server");
+        return Ok(None);
+    }
+    if !config.client_host_is_local() {
+        return Err(Error::Network(format!("no server reachable at {}", host)));
+    }
+    crate::server::start_background_server(config).await.map(Some)
+}
@@ -45,7 +45,7 @@ pub async fn run(config: &Config, options: &Args) -> Result<()> {
 
     // Create runtime and client
     let models_path = config.models_path.clone().unwrap_or_else(|| std::path::PathBuf::from("./data"));
-    let runtime = RkllmRuntime::new(models_path);
+    let runtime = RkllmRuntime::new(models_path).with_vision(config.vision.clone());
     let runtime_arc = std::sync::Arc::new(runtime);
     let client = RkllmClient::new(runtime_arc.clone());
 
@@ -1,6 +1,6 @@
 use clap::Parser;
 
-use crate::{ 
+use crate::{
     config::Config,
     error::Result,
     terminal::{color::Colorize, message::write},
@@ -9,9 +9,35 @@ use crate::{
 /// Copy a model
 #[derive(Default, Parser)]
 pub struct Args {
+    /// Existing model, by its full `name[:tag]`
+    #[clap(name = "source")]
+    pub source: String,
+    /// New `name[:tag]`, e.g. `qwen:prod`; replaces a model of that name
+    #[clap(name = "destination")]
+    pub destination: String,
 }
 
-pub async fn run(_config: &Config, _options: &Args) -> Result<()> {
-    write::info("executing copy command...".green())?;
+pub async fn run(config: &Config, options: &Args) -> Result<()> {
+    if options.source.is_empty() || options.destination.is_empty() {
+        write::error("Usage: cp <source> <destination>".red())?;
+        return Ok(());
+    }
+
+    let url = format!("{}/api/copy", config.client_host());
+    let resp = super::http_client(config)
+        .post(&url)
+        .json(&serde_json::json!({ "source": options.source, "destination": options.destination }))
+        .send()
+        .await
+        .map_err(|e| crate::error::Error::Network(e.to_string()))?;
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        write::error(format!("Copy failed ({}): {}", status, body).red())?;
+        return Ok(());
+    }
+
+    write::info(format!("Copied '{}' to '{}'", options.source, options.destination).green())?;
     Ok(())
-}
\ No newline at end of file
+}
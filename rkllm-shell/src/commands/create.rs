@@ -1,17 +1,65 @@
+use std::path::{Path, PathBuf};
+
 use clap::Parser;
 
-use crate::{ 
+use crate::{
     config::Config,
-    error::Result, 
+    error::Result,
+    server::modelfile::Modelfile,
     terminal::{color::Colorize, message::write},
 };
 
 /// Create a model from a Modelfile
 #[derive(Default, Parser)]
 pub struct Args {
+    /// Name of the new model, `name[:tag]`
+    #[clap(name = "model")]
+    pub model: String,
+    /// Path to the Modelfile
+    #[clap(short = 'f', long = "file", default_value = "Modelfile")]
+    pub file: PathBuf,
 }
 
-pub async fn run(_config: &Config, _options: &Args) -> Result<()> {
-    write::info("executing create command...".green())?;
+pub async fn run(config: &Config, options: &Args) -> Result<()> {
+    if options.model.is_empty() {
+        write::error("Usage: create <name[:tag]> [-f Modelfile]".red())?;
+        return Ok(());
+    }
+
+    let text = match std::fs::read_to_string(&options.file) {
+        Ok(text) => text,
+        Err(e) => {
+            write::error(format!("Cannot read {}: {}", options.file.display(), e).red())?;
+            return Ok(());
+        }
+    };
+    let mut modelfile = match Modelfile::parse(&text) {
+        Ok(modelfile) => modelfile,
+        Err(e) => {
+            write::error(format!("{}: {}", options.file.display(), e).red())?;
+            return Ok(());
+        }
+    };
+    // Paths in a Modelfile are relative to it, not to where the server runs.
+    modelfile.resolve_paths(options.file.parent().unwrap_or(Path::new(".")));
+
+    write::info(format!("Creating model '{}'...", options.model).green())?;
+
+    let url = format!("{}/api/create", config.client_host());
+    let body = serde_json::json!({
+        "model": options.model,
+        "modelfile": modelfile.to_string(),
+        "stream": true,
+    });
+    let resp = super::http_client(config)
+        .post(&url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| crate::error::Error::Network(e.to_string()))?;
+
+    if super::pull::show_progress(resp, "Create").await?.is_some() {
+        write::info(format!("Created model '{}'", options.model).green())?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
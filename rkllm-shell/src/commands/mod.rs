@@ -1,4 +1,3 @@
-use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use clap::Subcommand;
@@ -6,7 +5,6 @@ use clap_repl::reedline::{
     DefaultPrompt, DefaultPromptSegment, FileBackedHistory,
 };
 use clap_repl::{ClapEditor, ReadCommandOutput};
-use tokio::sync::oneshot;
 use tokio::time::sleep;
 
 use crate::args::Args;
@@ -18,8 +16,10 @@ pub mod create;
 pub mod show;
 pub mod run;
 pub mod stop;
+pub mod shutdown;
 pub mod quit;
 pub mod pull;
+pub mod prune;
 pub mod push;
 pub mod list;
 pub mod ps;
@@ -52,8 +52,10 @@ pub enum Command {
     Create(create::Args),
     Run(run::Args),
     Stop(stop::Args),
+    Shutdown(shutdown::Args),
     Quit(quit::Args),
     Pull(pull::Args),
+    Prune(prune::Args),
     Push(push::Args),
     List(list::Args),
     Ps(ps::Args),
@@ -63,24 +65,23 @@ pub enum Command {
 }
 
 impl Command {
-    pub async fn run(self, config: &crate::config::Config, shutdown_tx: Option<oneshot::Sender<()>>) -> crate::error::Result<()> {
+    pub async fn run(self, config: &crate::config::Config) -> crate::error::Result<()> {
         match self {
             Command::Serve(args) => serve::run(config, &args).await,
             Command::Info(_) => info::run(config).await,
             Command::Show(args) => show::run(config, &args).await,
             Command::Create(args) => create::run(config, &args).await,
             Command::Run(args) => run::run(config, &args).await,
-            Command::Stop(args) => { 
-                stop::run(config, &args).await?;
-                write::info("Server shutdown initiated")?;
-                if let Some(tx) = shutdown_tx {
-                    let _ = tx.send(());
-                }
+            Command::Stop(args) => stop::run(config, &args).await,
+            Command::Shutdown(args) => {
+                shutdown::run(config, &args).await?;
+                // Let the server finish answering before the process exits.
                 sleep(Duration::from_millis(100)).await;
                 Ok(())
-             },
+            }
             Command::Quit(args) => quit::run(config, &args).await,
             Command::Pull(args) => pull::run(config, &args).await,
+            Command::Prune(args) => prune::run(config, &args).await,
             Command::Push(args) => push::run(config, &args).await,
             Command::List(args) => list::run(config, &args).await,
             Command::Ps(args) => ps::run(config, &args).await,
@@ -91,7 +92,36 @@ impl Command {
     }
 }
 
-pub async fn run_repl(config: &crate::config::Config, shutdown_tx: oneshot::Sender<()>) -> crate::error::Result<()> {
+/// HTTP client for talking to the server, sending the configured API key.
+pub fn http_client(config: &crate::config::Config) -> reqwest::Client {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(key) = config.client_api_key() {
+        match reqwest::header::HeaderValue::from_str(&format!("Bearer {}", key)) {
+            Ok(mut value) => {
+                value.set_sensitive(true);
+                headers.insert(reqwest::header::AUTHORIZATION, value);
+            }
+            Err(_) => tracing::warn!("API key contains invalid header characters, not sending it"),
+        }
+    }
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Whether a server answers at the base URL `url`. Any response counts, as
+/// one that requires a key rejects the health check without it.
+pub async fn is_server_running(config: &crate::config::Config, url: &str) -> bool {
+    http_client(config)
+        .get(format!("{}/healthz", url))
+        .timeout(Duration::from_secs(1))
+        .send()
+        .await
+        .is_ok()
+}
+
+pub async fn run_repl(config: &crate::config::Config) -> crate::error::Result<()> {
     let prompt = DefaultPrompt {
         left_prompt: DefaultPromptSegment::Basic("rkllm-shell".to_owned()),
         ..DefaultPrompt::default()
@@ -107,24 +137,17 @@ pub async fn run_repl(config: &crate::config::Config, shutdown_tx: oneshot::Send
             ))
         })
         .build();
-    let shutdown_tx = Arc::new(Mutex::new(Some(shutdown_tx)));
     loop {
         match rl.read_command() {
             ReadCommandOutput::Command(args) => {
                 if let Some(cmd) = args.command {
-                    let is_stop_command = matches!(cmd, Command::Stop(_));
-                    let is_quit_command = matches!(cmd, Command::Quit(_));
-                    let tx = if is_stop_command {
-                            shutdown_tx.lock().unwrap().take()
-                    } else {
-                        None
-                    };
+                    let is_exit_command = matches!(cmd, Command::Shutdown(_) | Command::Quit(_));
 
-                    if let Err(e) = cmd.run(config, tx).await {
+                    if let Err(e) = cmd.run(config).await {
                         println!("Error executing command: {}", e);
                     }
 
-                    if is_stop_command || is_quit_command {
+                    if is_exit_command {
                         break;
                     }
                 }
@@ -0,0 +1,63 @@
+use clap::Parser;
+
+use crate::{
+    config::Config,
+    error::Result,
+    terminal::{color::Colorize, message::write},
+};
+
+/// Delete abandoned downloads and orphaned Hugging Face cache files
+#[derive(Default, Parser)]
+pub struct Args {
+    /// List what would be deleted without deleting it
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+pub async fn run(config: &Config, options: &Args) -> Result<()> {
+    let url = format!("{}/api/prune", config.client_host());
+    let resp = super::http_client(config)
+        .post(&url)
+        .json(&serde_json::json!({ "dry_run": options.dry_run }))
+        .send()
+        .await
+        .map_err(|e| crate::error::Error::Network(e.to_string()))?;
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        write::error(format!("Prune failed ({}): {}", status, body).red())?;
+        return Ok(());
+    }
+
+    let report: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| crate::error::Error::Network(e.to_string()))?;
+
+    let removed = report
+        .get("removed")
+        .and_then(|r| r.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let bytes = report.get("reclaimed_bytes").and_then(|b| b.as_u64()).unwrap_or(0);
+
+    if removed.is_empty() {
+        write::info("Nothing to prune.".green())?;
+        return Ok(());
+    }
+    for path in &removed {
+        println!("{}", path.as_str().unwrap_or("-"));
+    }
+    let verb = if options.dry_run { "Would reclaim" } else { "Reclaimed" };
+    write::info(
+        format!(
+            "{} {:.2} GB from {} files",
+            verb,
+            bytes as f64 / 1_073_741_824.0,
+            removed.len()
+        )
+        .green(),
+    )?;
+    Ok(())
+}
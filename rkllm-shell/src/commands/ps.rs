@@ -11,8 +11,8 @@ use crate::{
 pub struct Args {}
 
 pub async fn run(config: &Config, _options: &Args) -> Result<()> {
-    let url = format!("http://{}/api/ps", config.base_url);
-    let resp = reqwest::Client::new()
+    let url = format!("{}/api/ps", config.client_host());
+    let resp = super::http_client(config)
         .get(&url)
         .send()
         .await
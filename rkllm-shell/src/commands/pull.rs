@@ -3,53 +3,107 @@ use clap::Parser;
 use crate::{
     config::Config,
     error::Result,
-    terminal::{color::Colorize, message::write},
+    terminal::{self, color::Colorize, message::write},
 };
 
+const BAR_WIDTH: usize = 30;
+
 /// Pull a model from Hugging Face Hub
 #[derive(Default, Parser)]
 pub struct Args {
-    /// Model repository id (e.g. `username/model-name`)
+    /// Model repository id, optionally with a revision and variant
+    /// (e.g. `username/model-name@main:W8A8`)
     #[clap(name = "model")]
     pub model: String,
+    /// Download the files matching this glob instead of picking a variant; repeatable
+    #[clap(long = "file", value_name = "GLOB")]
+    pub files: Vec<String>,
 }
 
 pub async fn run(config: &Config, options: &Args) -> Result<()> {
     if options.model.is_empty() {
-        write::error("Usage: pull <repo-id>".red())?;
+        write::error("Usage: pull <owner/repo[@revision][:variant]> [--file <glob>]...".red())?;
         return Ok(());
     }
 
     write::info(format!("Pulling model '{}'...", options.model).green())?;
 
-    let url = format!("http://{}/api/pull", config.base_url);
+    let url = format!("{}/api/pull", config.client_host());
     // Server expects "name" field (Ollama API compatibility)
-    let body = serde_json::json!({ "name": options.model });
+    let body = serde_json::json!({ "name": options.model, "stream": true, "files": options.files });
 
-    let resp = reqwest::Client::new()
+    let resp = super::http_client(config)
         .post(&url)
         .json(&body)
         .send()
         .await
         .map_err(|e| crate::error::Error::Network(e.to_string()))?;
 
+    if let Some(last_status) = show_progress(resp, "Pull").await? {
+        write::info(format!("Pull status: {}", last_status).green())?;
+    }
+    Ok(())
+}
+
+/// Draws the NDJSON progress of a pull or create as it arrives. Returns the
+/// last status, or None when `action` failed, after printing why.
+pub async fn show_progress(mut resp: reqwest::Response, action: &str) -> Result<Option<String>> {
     if !resp.status().is_success() {
         let status = resp.status();
         let text = resp.text().await.unwrap_or_default();
-        write::error(format!("Pull failed ({}): {}", status, text).red())?;
-        return Ok(());
+        write::error(format!("{} failed ({}): {}", action, status, text).red())?;
+        return Ok(None);
     }
 
-    let result: serde_json::Value = resp
-        .json()
+    // One JSON object per line; a line may span several chunks.
+    let mut buffer = Vec::new();
+    let mut last_status = String::new();
+    while let Some(chunk) = resp
+        .chunk()
         .await
-        .map_err(|e| crate::error::Error::Network(e.to_string()))?;
-
-    let status = result
-        .get("status")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
+        .map_err(|e| crate::error::Error::Network(e.to_string()))?
+    {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let Ok(event) = serde_json::from_slice::<serde_json::Value>(&line) else {
+                continue;
+            };
+            if let Some(error) = event.get("error").and_then(|v| v.as_str()) {
+                terminal::writeln("")?;
+                write::error(format!("{} failed: {}", action, error).red())?;
+                return Ok(None);
+            }
+            let status = event.get("status").and_then(|v| v.as_str()).unwrap_or("unknown");
+            let total = event.get("total").and_then(|v| v.as_u64());
+            let completed = event.get("completed").and_then(|v| v.as_u64());
+            // Each status gets its own line; progress redraws it in place.
+            if status != last_status && !last_status.is_empty() {
+                terminal::writeln("")?;
+            }
+            terminal::write(format!("\r{}", render_progress(status, total, completed)))?;
+            last_status = status.to_string();
+        }
+    }
+    terminal::writeln("")?;
+    Ok(Some(last_status))
+}
 
-    write::info(format!("Pull status: {}", status).green())?;
-    Ok(())
-}
\ No newline at end of file
+/// A status line, with a bar when the size is known, e.g.
+/// `downloading model.rkllm [#####-----]  50% 1.0/2.0 GB`
+fn render_progress(status: &str, total: Option<u64>, completed: Option<u64>) -> String {
+    let (Some(total), Some(completed)) = (total, completed) else {
+        return status.to_string();
+    };
+    let fraction = if total == 0 { 1.0 } else { completed as f64 / total as f64 };
+    let filled = ((fraction * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+    format!(
+        "{} [{}{}] {:>3.0}% {:.1}/{:.1} GB",
+        status,
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        fraction * 100.0,
+        completed as f64 / 1_073_741_824.0,
+        total as f64 / 1_073_741_824.0
+    )
+}
@@ -12,6 +12,6 @@ pub struct Args {}
 
 pub async fn run(_config: &Config, _options: &Args) -> Result<()> {
     write::info("Exiting shell. Server will continue running in the background.".green())?;
-    write::info("Use 'rkllm-shell stop' to stop the server.".yellow())?;
+    write::info("Use 'rkllm-shell shutdown' to stop the server.".yellow())?;
     Ok(())
 }
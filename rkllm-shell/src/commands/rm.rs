@@ -1,6 +1,6 @@
 use clap::Parser;
 
-use crate::{ 
+use crate::{
     config::Config,
     error::Result,
     terminal::{color::Colorize, message::write},
@@ -9,9 +9,39 @@ use crate::{
 /// Remove a model
 #[derive(Default, Parser)]
 pub struct Args {
+    /// Models to remove, by their full `name[:tag]`
+    #[clap(name = "model", required = true)]
+    pub models: Vec<String>,
+    /// Unload a model that is loaded instead of refusing to remove it
+    #[clap(long)]
+    pub force: bool,
 }
 
-pub async fn run(_config: &Config, _options: &Args) -> Result<()> {
-    write::info("executing rm command...".green())?;
+pub async fn run(config: &Config, options: &Args) -> Result<()> {
+    if options.models.is_empty() {
+        write::error("Usage: rm <model>... [--force]".red())?;
+        return Ok(());
+    }
+
+    let url = format!("{}/api/delete", config.client_host());
+    let client = super::http_client(config);
+    for model in &options.models {
+        let resp = client
+            .delete(&url)
+            .json(&serde_json::json!({ "model": model, "force": options.force }))
+            .send()
+            .await
+            .map_err(|e| crate::error::Error::Network(e.to_string()))?;
+
+        let status = resp.status();
+        if status.is_success() {
+            write::info(format!("Deleted '{}'", model).green())?;
+        } else if status == reqwest::StatusCode::CONFLICT {
+            write::error(format!("'{}' is loaded; stop it first or use --force", model).red())?;
+        } else {
+            let body = resp.text().await.unwrap_or_default();
+            write::error(format!("Failed to delete '{}' ({}): {}", model, status, body).red())?;
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,17 +1,598 @@
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Read};
+use std::path::Path;
+use std::time::Instant;
+
+use base64::{engine::general_purpose, Engine};
 use clap::Parser;
+use clap_repl::reedline::{DefaultPrompt, DefaultPromptSegment, FileBackedHistory, Reedline, Signal};
 
-use crate::{ 
+use crate::{
     config::Config,
-    error::Result,
-    terminal::{color::Colorize, message::write},
+    error::{Error, Result},
+    server::{
+        api_models::{ChatCompletionRequestMessage, ChatCompletionResponse, ContextReport, Role},
+        apis::error::ApiError,
+        modelfile::Modelfile,
+        store::Message,
+    },
+    terminal::{self, color::Colorize, message::write},
 };
 
-/// PRun a model
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif", "bmp"];
+
+const HELP: &str = r#"Available commands:
+  /set parameter <name> <value>  Set a parameter (num_ctx, temperature, top_k, stop, ...)
+  /set system <text>             Set the system message
+  /set verbose | quiet           Show or hide token stats after each response
+  /show [info|modelfile|parameters|system|template|license]
+                                 Show information about the model
+  /clear                         Forget the conversation so far
+  /save <model>                  Save this session as a new model
+  /load <model>                  Switch to another model and start over
+  /bye                           Exit
+
+Use """ to begin and end a multi-line message.
+Paths to images (png, jpg, webp, ...) in a message are attached for vision models;
+quote a path that contains spaces."#;
+
+/// Run a model
 #[derive(Default, Parser)]
 pub struct Args {
+    /// Model name (e.g. `qwen2.5-3b:w8a8`)
+    #[clap(name = "model")]
+    pub model: String,
+    /// Prompt to answer once instead of starting a chat
+    #[clap(name = "prompt")]
+    pub prompt: Vec<String>,
+    /// Show token counts and speed after each response
+    #[clap(long = "verbose")]
+    pub verbose: bool,
+}
+
+pub async fn run(config: &Config, options: &Args) -> Result<()> {
+    if options.model.is_empty() {
+        write::error("Usage: run <model> [prompt] [--verbose]".red())?;
+        return Ok(());
+    }
+
+    let mut session = Session::new(&options.model, options.verbose);
+    let mut prompt = options.prompt.join(" ");
+    if !std::io::stdin().is_terminal() {
+        // Piped input follows the prompt, e.g. `cat notes.txt | run qwen "Summarize:"`.
+        // It is read off the async workers, like chat input.
+        let piped = tokio::task::spawn_blocking(|| {
+            let mut piped = String::new();
+            std::io::stdin().lock().read_to_string(&mut piped).map(|_| piped)
+        })
+        .await
+        .map_err(|e| Error::Server(e.to_string()))??;
+        prompt = [prompt.trim(), piped.trim()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+    if !prompt.is_empty() {
+        return session.send(config, &prompt).await;
+    }
+
+    if session.fetch_show(config).await?.is_none() {
+        return Ok(());
+    }
+    chat(config, session).await
+}
+
+/// Reads messages and slash commands until `/bye` or Ctrl-D.
+async fn chat(config: &Config, mut session: Session) -> Result<()> {
+    let mut editor = Reedline::create();
+    if let Ok(history) = FileBackedHistory::with_file(1000, config.dir.join(".run_history")) {
+        editor = editor.with_history(Box::new(history));
+    }
+    let prompt = DefaultPrompt {
+        left_prompt: DefaultPromptSegment::Basic(">>> ".to_owned()),
+        right_prompt: DefaultPromptSegment::Empty,
+    };
+    let continuation = DefaultPrompt {
+        left_prompt: DefaultPromptSegment::Basic("... ".to_owned()),
+        right_prompt: DefaultPromptSegment::Empty,
+    };
+
+    write::info(format!("Chatting with '{}'. Type /? for help, /bye to leave.", session.model).green())?;
+    // The lines of a `"""` message read so far.
+    let mut pending: Option<String> = None;
+    loop {
+        let shown = if pending.is_some() { continuation.clone() } else { prompt.clone() };
+        let (returned, signal) = read_line(editor, shown).await?;
+        editor = returned;
+        let line = match signal {
+            Signal::Success(line) => line,
+            Signal::CtrlC => {
+                pending = None;
+                continue;
+            }
+            Signal::CtrlD => break,
+        };
+
+        let input = if let Some(text) = pending.as_mut() {
+            let (line, closed) = match line.trim_end().strip_suffix("\"\"\"") {
+                Some(last) => (last, true),
+                None => (line.as_str(), false),
+            };
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(line);
+            if !closed {
+                continue;
+            }
+            pending.take().unwrap_or_default()
+        } else if let Some(rest) = line.trim_start().strip_prefix("\"\"\"") {
+            match rest.trim_end().strip_suffix("\"\"\"") {
+                Some(whole) => whole.to_string(),
+                None => {
+                    pending = Some(rest.to_string());
+                    continue;
+                }
+            }
+        } else {
+            let line = line.trim();
+            if line.starts_with('/') {
+                match session.command(config, line).await {
+                    Ok(Flow::Exit) => break,
+                    Ok(Flow::Continue) => {}
+                    Err(e) => write::error(e.to_string().red())?,
+                }
+                continue;
+            }
+            line.to_string()
+        };
+
+        if input.trim().is_empty() {
+            continue;
+        }
+        if let Err(e) = session.send(config, &input).await {
+            write::error(e.to_string().red())?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a line on a blocking thread, so the async workers, which an
+/// in-process server also runs on, keep going while the user types.
+async fn read_line(mut editor: Reedline, prompt: DefaultPrompt) -> Result<(Reedline, Signal)> {
+    tokio::task::spawn_blocking(move || {
+        let signal = editor.read_line(&prompt)?;
+        Ok((editor, signal))
+    })
+    .await
+    .map_err(|e| Error::Server(e.to_string()))?
+}
+
+enum Flow {
+    Continue,
+    Exit,
+}
+
+/// A conversation with one model, and what `/set` changed for it.
+struct Session {
+    model: String,
+    system: Option<String>,
+    /// `/set parameter` values, as `PARAMETER` lines
+    parameters: Vec<(String, String)>,
+    messages: Vec<ChatCompletionRequestMessage>,
+    verbose: bool,
+}
+
+impl Session {
+    fn new(model: &str, verbose: bool) -> Self {
+        Self {
+            model: model.to_string(),
+            system: None,
+            parameters: Vec::new(),
+            messages: Vec::new(),
+            verbose,
+        }
+    }
+
+    /// The options to send: only those `/set` changed, so the server fills
+    /// in the model's own parameters for the rest.
+    fn options(&self) -> std::result::Result<BTreeMap<String, serde_json::Value>, ApiError> {
+        Modelfile {
+            parameters: self.parameters.clone(),
+            ..Default::default()
+        }
+        .parameter_map()
+    }
+
+    /// Sends `text` as the next user message and streams the reply. The
+    /// turn joins the history only when the model answered.
+    async fn send(&mut self, config: &Config, text: &str) -> Result<()> {
+        let (content, images) = attach_images(text)?;
+        let message = ChatCompletionRequestMessage {
+            role: Role::User,
+            content,
+            thinking: None,
+            images: (!images.is_empty()).then_some(images),
+            videos: None,
+        };
+        let mut messages = Vec::new();
+        if let Some(system) = &self.system {
+            messages.push(ChatCompletionRequestMessage {
+                role: Role::System,
+                content: system.clone(),
+                thinking: None,
+                images: None,
+                videos: None,
+            });
+        }
+        messages.extend(self.messages.iter().cloned());
+        messages.push(message.clone());
+
+        let options = self.options().map_err(|e| Error::Server(e.to_string()))?;
+        let url = format!("{}/api/chat", config.client_host());
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": true,
+            "options": options,
+        });
+
+        let started = Instant::now();
+        let mut resp = super::http_client(config)
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| Error::Network(e.to_string()))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            write::error(format!("Server returned {}: {}", status, text).red())?;
+            return Ok(());
+        }
+
+        let mut lines = SseLines::default();
+        let mut reply = String::new();
+        let mut thinking = false;
+        let mut tokens = 0usize;
+        let mut first_token = None;
+        let mut context = None;
+        while let Some(chunk) = resp.chunk().await.map_err(|e| Error::Network(e.to_string()))? {
+            for data in lines.push(&chunk) {
+                let Ok(event) = serde_json::from_str::<ChatCompletionResponse>(&data) else {
+                    continue;
+                };
+                if event.done {
+                    context = event.context;
+                    continue;
+                }
+                first_token.get_or_insert_with(Instant::now);
+                tokens += 1;
+                if let Some(text) = event.message.thinking.filter(|t| !t.is_empty()) {
+                    thinking = true;
+                    terminal::write(text.bright_black())?;
+                }
+                if !event.message.content.is_empty() {
+                    if thinking {
+                        terminal::write("\n\n")?;
+                        thinking = false;
+                    }
+                    terminal::write(&event.message.content)?;
+                    reply.push_str(&event.message.content);
+                }
+            }
+        }
+        terminal::writeln("\n")?;
+
+        if self.verbose {
+            print_stats(started, first_token, tokens, context.as_ref())?;
+        }
+        if let Some(context) = &context {
+            if context.dropped_messages > 0 {
+                write::info(
+                    format!(
+                        "{} earlier messages no longer fit in the context window",
+                        context.dropped_messages
+                    )
+                    .yellow(),
+                )?;
+            }
+        }
+        if !reply.is_empty() {
+            self.messages.push(message);
+            self.messages.push(ChatCompletionRequestMessage {
+                role: Role::Assistant,
+                content: reply,
+                thinking: None,
+                images: None,
+                videos: None,
+            });
+        }
+        Ok(())
+    }
+
+    async fn command(&mut self, config: &Config, line: &str) -> Result<Flow> {
+        let (name, rest) = line
+            .split_once(char::is_whitespace)
+            .map(|(name, rest)| (name, rest.trim()))
+            .unwrap_or((line, ""));
+        match name {
+            "/bye" | "/exit" => return Ok(Flow::Exit),
+            "/?" | "/help" => terminal::writeln(HELP)?,
+            "/clear" => {
+                self.messages.clear();
+                write::info("Cleared session context".green())?;
+            }
+            "/set" => self.set(rest)?,
+            "/show" => self.show(config, rest).await?,
+            "/save" => self.save(config, rest).await?,
+            "/load" => self.load(config, rest).await?,
+            other => write::error(format!("Unknown command '{}'. Type /? for help", other).red())?,
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn set(&mut self, args: &str) -> Result<()> {
+        let (what, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let value = value.trim();
+        match what {
+            "parameter" => {
+                let Some((key, value)) = value.split_once(char::is_whitespace) else {
+                    write::error("Usage: /set parameter <name> <value>".red())?;
+                    return Ok(());
+                };
+                let previous = self.parameters.clone();
+                set_parameter(&mut self.parameters, key, value);
+                match self.options() {
+                    Ok(_) => write::info(format!("Set parameter '{}' to '{}'", key, value.trim()).green())?,
+                    Err(e) => {
+                        self.parameters = previous;
+                        write::error(e.to_string().red())?;
+                    }
+                }
+            }
+            "system" if !value.is_empty() => {
+                self.system = Some(value.trim_matches('"').to_string());
+                write::info("Set system message".green())?;
+            }
+            "verbose" => {
+                self.verbose = true;
+                write::info("Set 'verbose' mode".green())?;
+            }
+            "quiet" => {
+                self.verbose = false;
+                write::info("Set 'quiet' mode".green())?;
+            }
+            _ => write::error("Usage: /set parameter <name> <value> | system <text> | verbose | quiet".red())?,
+        }
+        Ok(())
+    }
+
+    async fn show(&self, config: &Config, what: &str) -> Result<()> {
+        let key = match what {
+            "" | "info" => "details",
+            "modelfile" | "parameters" | "system" | "template" | "license" => what,
+            _ => {
+                write::error("Usage: /show [info|modelfile|parameters|system|template|license]".red())?;
+                return Ok(());
+            }
+        };
+        if let (Some(system), "system") = (&self.system, key) {
+            terminal::writeln(system)?;
+            return Ok(());
+        }
+        let Some(info) = self.fetch_show(config).await? else {
+            return Ok(());
+        };
+        let text = info.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+        if text.is_empty() && !(key == "parameters" && !self.parameters.is_empty()) {
+            write::info(format!("No {} for '{}'", if what.is_empty() { "info" } else { what }, self.model).yellow())?;
+            return Ok(());
+        }
+        if !text.is_empty() {
+            terminal::writeln(text.trim_end())?;
+        }
+        if key == "parameters" {
+            for (key, value) in &self.parameters {
+                terminal::writeln(format!("{} {} (this session)", key, value))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the session as a model derived from the current one, with its
+    /// system message, parameters and conversation.
+    async fn save(&self, config: &Config, name: &str) -> Result<()> {
+        if name.is_empty() {
+            write::error("Usage: /save <model>".red())?;
+            return Ok(());
+        }
+        let modelfile = Modelfile {
+            from: self.model.clone(),
+            parameters: self.parameters.clone(),
+            system: self.system.clone(),
+            messages: self
+                .messages
+                .iter()
+                .map(|m| Message {
+                    role: m.role.clone(),
+                    content: m.content.clone(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let url = format!("{}/api/create", config.client_host());
+        let body = serde_json::json!({
+            "model": name,
+            "modelfile": modelfile.to_string(),
+            "stream": true,
+        });
+        let resp = super::http_client(config)
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| Error::Network(e.to_string()))?;
+        if super::pull::show_progress(resp, "Save").await?.is_some() {
+            write::info(format!("Created new model '{}'", name).green())?;
+        }
+        Ok(())
+    }
+
+    /// Switches to `name` and starts a new conversation; `/set` values stay.
+    async fn load(&mut self, config: &Config, name: &str) -> Result<()> {
+        if name.is_empty() {
+            write::error("Usage: /load <model>".red())?;
+            return Ok(());
+        }
+        let previous = std::mem::replace(&mut self.model, name.to_string());
+        if self.fetch_show(config).await?.is_none() {
+            self.model = previous;
+            return Ok(());
+        }
+        self.messages.clear();
+        write::info(format!("Loaded model '{}'", name).green())?;
+        Ok(())
+    }
+
+    /// The model's `/api/show` response, or None after printing why there
+    /// is none.
+    async fn fetch_show(&self, config: &Config) -> Result<Option<serde_json::Value>> {
+        let url = format!("{}/api/show", config.client_host());
+        let resp = super::http_client(config)
+            .post(&url)
+            .json(&serde_json::json!({ "model": self.model }))
+            .send()
+            .await
+            .map_err(|e| Error::Network(e.to_string()))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            write::error(format!("Model '{}' is not available ({}): {}", self.model, status, text).red())?;
+            if status == reqwest::StatusCode::NOT_FOUND {
+                write::info("Pull or create it first, e.g. `pull <owner/repo>`".yellow())?;
+            }
+            return Ok(None);
+        }
+        let info = resp.json().await.map_err(|e| Error::Network(e.to_string()))?;
+        Ok(Some(info))
+    }
 }
 
-pub async fn run(_config: &Config, _options: &Args) -> Result<()> {
-    write::info("executing run command...".green())?;
+/// Replaces the values of `key` with `value`. Each word of a `stop` value
+/// is a stop sequence, as in `/set parameter stop <|User|> <|End|>`.
+fn set_parameter(parameters: &mut Vec<(String, String)>, key: &str, value: &str) {
+    parameters.retain(|(k, _)| k != key);
+    if key == "stop" {
+        parameters.extend(value.split_whitespace().map(|stop| (key.to_string(), stop.to_string())));
+    } else {
+        parameters.push((key.to_string(), value.trim().to_string()));
+    }
+}
+
+/// Reassembles the lines of a server-sent event stream, one `data: {json}`
+/// line per token, from chunks that may split them anywhere.
+#[derive(Default)]
+struct SseLines {
+    buffer: Vec<u8>,
+}
+
+impl SseLines {
+    /// Adds `chunk`, returning the `data:` of each line it completes.
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut data = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            if let Some(line) = String::from_utf8_lossy(&line).trim().strip_prefix("data:") {
+                data.push(line.trim().to_string());
+            }
+        }
+        data
+    }
+}
+
+/// The words of `text` with their offsets. A word that starts with a quote
+/// runs to the closing quote, so a quoted path may hold spaces.
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, first)) = chars.next() {
+        if first.is_whitespace() {
+            continue;
+        }
+        let mut end = start + first.len_utf8();
+        if first == '"' || first == '\'' {
+            for (i, c) in chars.by_ref() {
+                end = i + c.len_utf8();
+                if c == first {
+                    break;
+                }
+            }
+        } else {
+            while let Some((i, c)) = chars.next_if(|(_, c)| !c.is_whitespace()) {
+                end = i + c.len_utf8();
+            }
+        }
+        words.push((start, &text[start..end]));
+    }
+    words
+}
+
+/// Takes the paths of image files out of `text`, returning the rest and the
+/// images base64 encoded.
+fn attach_images(text: &str) -> Result<(String, Vec<String>)> {
+    let mut rest = String::new();
+    let mut copied = 0;
+    let mut images = Vec::new();
+    for (start, word) in words(text) {
+        let path = Path::new(word.trim_matches(|c| c == '"' || c == '\''));
+        let is_image = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+        if is_image && path.is_file() {
+            match std::fs::read(path) {
+                Ok(bytes) => {
+                    write::info(format!("Added image '{}'", path.display()).green())?;
+                    images.push(general_purpose::STANDARD.encode(bytes));
+                    rest.push_str(&text[copied..start]);
+                    copied = start + word.len();
+                }
+                Err(e) => write::error(format!("Cannot read {}: {}", path.display(), e).red())?,
+            }
+        }
+    }
+    rest.push_str(&text[copied..]);
+    Ok((rest.trim().to_string(), images))
+}
+
+/// Timings in the style of `ollama run --verbose`.
+fn print_stats(
+    started: Instant,
+    first_token: Option<Instant>,
+    tokens: usize,
+    context: Option<&ContextReport>,
+) -> Result<()> {
+    let total = started.elapsed().as_secs_f64();
+    let prompt_eval = first_token.map_or(total, |t| t.duration_since(started).as_secs_f64());
+    let eval = first_token.map_or(0.0, |t| t.elapsed().as_secs_f64());
+    let mut lines = vec![format!("total duration:       {:.2}s", total)];
+    if let Some(context) = context {
+        let estimated = if context.estimated { " (estimated)" } else { "" };
+        lines.push(format!("prompt eval count:    {} token(s){}", context.prompt_tokens, estimated));
+    }
+    lines.push(format!("prompt eval duration: {:.2}s", prompt_eval));
+    lines.push(format!("eval count:           {} token(s)", tokens));
+    lines.push(format!("eval duration:        {:.2}s", eval));
+    if eval > 0.0 {
+        lines.push(format!("eval rate:            {:.2} tokens/s", tokens as f64 / eval));
+    }
+    terminal::writeln(lines.join("\n").bright_black())?;
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+#[path = "run_test.rs"]
+mod tests;
@@ -1,10 +1,80 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn dummy_test() {
-        // Replace with real tests for run.rs
-        assert_eq!(2 + 2, 4);
-    }
+//! Tests for the interactive `run` session
+
+use super::*;
+
+#[test]
+fn test_sse_lines_span_chunks() {
+    let mut lines = SseLines::default();
+    assert!(lines.push(b"data: {\"a\"").is_empty());
+    assert_eq!(lines.push(b":1}\n\n: keep-alive\ndata: x\nda"), vec!["{\"a\":1}", "x"]);
+    assert_eq!(lines.push(b"ta: y\r\n"), vec!["y"]);
+
+    // A character split between chunks is decoded whole.
+    let event = "data: é\n".as_bytes();
+    assert!(lines.push(&event[..7]).is_empty());
+    assert_eq!(lines.push(&event[7..]), vec!["é"]);
+}
+
+#[test]
+fn test_words_keep_quoted_spaces() {
+    let words: Vec<&str> = words("look at \"my cat.png\" and 'a b' c").into_iter().map(|(_, w)| w).collect();
+    assert_eq!(words, vec!["look", "at", "\"my cat.png\"", "and", "'a b'", "c"]);
+    assert_eq!(words("  two\n words")[1], (7, "words"));
+}
+
+#[test]
+fn test_attach_images() {
+    let dir = tempfile::tempdir().unwrap();
+    let spaced = dir.path().join("my cat.png");
+    let plain = dir.path().join("dog.JPG");
+    std::fs::write(&spaced, b"cat").unwrap();
+    std::fs::write(&plain, b"dog").unwrap();
+    let missing = dir.path().join("missing.png");
+
+    let text = format!(
+        "Compare \"{}\" with {} and {}",
+        spaced.display(),
+        plain.display(),
+        missing.display()
+    );
+    let (rest, images) = attach_images(&text).unwrap();
+    assert_eq!(images, vec![general_purpose::STANDARD.encode(b"cat"), general_purpose::STANDARD.encode(b"dog")]);
+    assert_eq!(rest, format!("Compare  with  and {}", missing.display()));
+
+    let (rest, images) = attach_images("no images here").unwrap();
+    assert_eq!(rest, "no images here");
+    assert!(images.is_empty());
+}
+
+#[test]
+fn test_set_parameter_splits_stops() {
+    let mut parameters = vec![("stop".to_string(), "<|Old|>".to_string())];
+    set_parameter(&mut parameters, "stop", "<|User|>  <|End|>");
+    set_parameter(&mut parameters, "temperature", "0.5 ");
+    assert_eq!(
+        parameters,
+        vec![
+            ("stop".to_string(), "<|User|>".to_string()),
+            ("stop".to_string(), "<|End|>".to_string()),
+            ("temperature".to_string(), "0.5".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_session_options() {
+    let mut session = Session::new("qwen", false);
+    assert!(session.options().unwrap().is_empty());
+
+    session.set("parameter stop <|User|> <|End|>").unwrap();
+    session.set("parameter temperature 0.5").unwrap();
+    let options = session.options().unwrap();
+    assert_eq!(options["stop"], serde_json::json!(["<|User|>", "<|End|>"]));
+    assert_eq!(options["temperature"], serde_json::json!(0.5));
+    // Only what `/set` changed is sent.
+    assert_eq!(options.len(), 2);
+
+    // A value the server would reject leaves the parameters as they were.
+    session.set("parameter top_k high").unwrap();
+    assert_eq!(session.options().unwrap(), options);
 }
@@ -12,36 +12,22 @@ use crate::{
 pub struct Args {
 }
 
-async fn is_server_running(base_url: &str) -> bool {
-    let client = reqwest::Client::new();
-    if let Ok(response) = client
-        .get(&format!("http://{}/healthz", base_url))
-        .timeout(std::time::Duration::from_millis(500))
-        .send()
-        .await
-    {
-        response.status().is_success()
-    } else {
-        false
-    }
-}
-
 pub async fn run(config: &Config, _options: &Args) -> Result<()> {
     let base_url = config.base_url.clone();
     
     // Check if server is already running
-    if is_server_running(&base_url).await {
+    if super::is_server_running(config, &format!("http://{}", base_url)).await {
         write::info(format!(
             "Server is already running on http://{}",
             base_url
         ).green())?;
-        write::info("Use 'rkllm-shell stop' to stop the server, or connect to the existing instance.".yellow())?;
+        write::info("Use 'rkllm-shell shutdown' to stop the server, or connect to the existing instance.".yellow())?;
         return Ok(());
     }
     
     write::info(format!(
-        "Starting server with models path '{:?}'...",
-        config.models_path
+        "Starting server on http://{} with models path '{:?}'...",
+        base_url, config.models_path
     ).yellow())?;
 
     // Keep the shutdown sender alive to prevent immediate shutdown.
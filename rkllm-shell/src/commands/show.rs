@@ -20,7 +20,7 @@ pub async fn run(config: &Config, options: &Args) -> Result<()> {
         return Ok(());
     }
 
-    let url = format!("http://{}/api/show", config.base_url);
+    let url = format!("{}/api/show", config.client_host());
     let body = serde_json::json!({
         "model":   options.model,
         "system":  "",
@@ -28,7 +28,7 @@ pub async fn run(config: &Config, options: &Args) -> Result<()> {
         "options": {}
     });
 
-    let resp = reqwest::Client::new()
+    let resp = super::http_client(config)
         .post(&url)
         .json(&body)
         .send()
@@ -0,0 +1,29 @@
+use clap::Parser;
+
+use crate::{
+    config::Config,
+    error::Result,
+    terminal::{color::Colorize, message::write},
+};
+
+/// Shut down the server
+#[derive(Default, Parser)]
+pub struct Args {}
+
+pub async fn run(config: &Config, _options: &Args) -> Result<()> {
+    let url = format!("{}/api/shutdown", config.client_host());
+    let resp = super::http_client(config)
+        .post(&url)
+        .send()
+        .await
+        .map_err(|e| crate::error::Error::Network(e.to_string()))?;
+
+    let status = resp.status();
+    if status.is_success() {
+        write::info("Server shutdown initiated".green())?;
+    } else {
+        let body = resp.text().await.unwrap_or_default();
+        write::error(format!("Failed to shut down the server ({}): {}", status, body).red())?;
+    }
+    Ok(())
+}
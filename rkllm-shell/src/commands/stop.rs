@@ -1,6 +1,6 @@
 use clap::Parser;
 
-use crate::{ 
+use crate::{
     config::Config,
     error::Result,
     terminal::{color::Colorize, message::write},
@@ -9,9 +9,29 @@ use crate::{
 /// Stop a running model
 #[derive(Default, Parser)]
 pub struct Args {
+    /// Model to unload, as listed by `ps`
+    #[clap(name = "model")]
+    pub model: String,
 }
 
-pub async fn run(_config: &Config, _options: &Args) -> Result<()> {
-    write::info("executing stop command...".green())?;
+pub async fn run(config: &Config, options: &Args) -> Result<()> {
+    let url = format!("{}/api/unload", config.client_host());
+    let resp = super::http_client(config)
+        .post(&url)
+        .json(&serde_json::json!({ "model": options.model }))
+        .send()
+        .await
+        .map_err(|e| crate::error::Error::Network(e.to_string()))?;
+
+    let status = resp.status();
+    if status.is_success() {
+        write::info(format!("Stopped '{}'", options.model).green())?;
+    } else if status == reqwest::StatusCode::NOT_FOUND {
+        let body = resp.text().await.unwrap_or_default();
+        write::error(format!("'{}' is not running: {}", options.model, body).red())?;
+    } else {
+        let body = resp.text().await.unwrap_or_default();
+        write::error(format!("Failed to stop '{}' ({}): {}", options.model, status, body).red())?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
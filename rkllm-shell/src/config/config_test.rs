@@ -1,10 +1,53 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn dummy_test() {
-        // Replace with real tests for mod.rs in config
-        assert_eq!(2 + 2, 4);
+//! Tests for the client host settings
+
+use super::*;
+
+fn config(host: &str) -> Config {
+    Config {
+        base_url: "127.0.0.1:3000".to_string(),
+        // An empty host still takes precedence over RKLLM_HOST.
+        host: Some(host.to_string()),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_client_host() {
+    for (host, url) in [
+        ("", "http://127.0.0.1:3000"),
+        ("board", "http://board:3000"),
+        ("Board:8080/", "http://board:8080"),
+        ("http://board", "http://board:3000"),
+        ("https://board:8443/", "https://board:8443"),
+        ("board:3000/rkllm", "http://board:3000/rkllm"),
+        ("::1", "http://[::1]:3000"),
+        ("[::1]:8080", "http://[::1]:8080"),
+        ("https://[fe80::1]", "https://[fe80::1]:3000"),
+    ] {
+        assert_eq!(config(host).client_host(), url, "{:?}", host);
     }
 }
+
+#[test]
+fn test_client_host_is_local() {
+    for (host, local) in [
+        ("", true),
+        ("localhost", true),
+        ("127.0.0.1:3000", true),
+        ("::1", true),
+        ("[::1]:3000", true),
+        ("http://0.0.0.0:3000", true),
+        ("127.0.0.1:3001", false),
+        ("https://127.0.0.1:3000", false),
+        ("localhost:3000/rkllm", false),
+        ("board", false),
+    ] {
+        assert_eq!(config(host).client_host_is_local(), local, "{:?}", host);
+    }
+
+    let config = Config {
+        host: Some("localhost".to_string()),
+        ..Config::default()
+    };
+    assert!(config.client_host_is_local());
+}
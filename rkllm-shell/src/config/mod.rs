@@ -1,12 +1,18 @@
 use std::{
     fs,
+    net::{IpAddr, Ipv6Addr},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use config::{File, FileFormat};
+use reqwest::Url;
 use serde::Deserialize;
 
 use crate::error::Result;
+use crate::server::api_models::ModelOptions;
+use crate::server::defaults::{default_keep_alive, default_model_options};
+use crate::server::vision::preprocess::ProfileSetting;
 
 const CONFIG_FILE_NAME: &str = "config.yaml";
 
@@ -16,22 +22,514 @@ const DEFAULT_CONFIG_FILE_CONTENT: &str = include_str!("default.yaml");
 pub struct Config {
     pub models_path: Option<PathBuf>,
     pub base_url: String,
+    /// Models loaded by the server at start-up, before it reports ready
+    #[serde(default)]
+    pub preload: Vec<PreloadModel>,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub rate_limits: RateLimitConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub context: ContextConfig,
+    #[serde(default)]
+    pub image_urls: ImageUrlConfig,
+    /// Vision encoders of the multimodal models
+    #[serde(default)]
+    pub vision: Vec<VisionModelConfig>,
+    #[serde(default)]
+    pub video: VideoConfig,
+    #[serde(default)]
+    pub pull: PullConfig,
+    /// Key sent by CLI commands to the server; `RKLLM_API_KEY` overrides it
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Server CLI commands talk to, from `--host`
+    #[serde(skip)]
+    pub host: Option<String>,
 
     #[serde(skip)]
     pub dir: PathBuf,
 }
 
+/// A model to load (and optionally warm up) when the server starts.
+///
+/// The runtime keys loaded models by name *and* options, so requests only
+/// reuse a preloaded model when they ask for the same options.
+#[derive(Deserialize, Clone, Debug)]
+pub struct PreloadModel {
+    pub model: String,
+    #[serde(default = "default_model_options")]
+    pub options: ModelOptions,
+    #[serde(default = "default_keep_alive")]
+    pub keep_alive: Duration,
+    /// Prompt for a throw-away generation that primes the NPU after loading
+    #[serde(default)]
+    pub warmup: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+impl From<LogRotation> for tracing_appender::rolling::Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Hourly => Self::HOURLY,
+            LogRotation::Daily => Self::DAILY,
+            LogRotation::Never => Self::NEVER,
+        }
+    }
+}
+
+/// Where and how the server and CLI write their logs
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Filter directives such as `info` or `rkllm_shell=debug`; `-v` overrides it
+    pub level: Option<String>,
+    pub format: LogFormat,
+    /// Directory for rotated log files; logs go to stderr when unset
+    pub dir: Option<PathBuf>,
+    pub rotation: LogRotation,
+}
+
+/// What an API key is allowed to do
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Generation, chat, embeddings and model listing
+    Inference,
+    /// Managing and unloading models, shutting the server down, plus server
+    /// status and metrics
+    ModelAdmin,
+    /// The rig agent endpoints
+    Agent,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Inference => "inference",
+            Scope::ModelAdmin => "model_admin",
+            Scope::Agent => "agent",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    /// Hex-encoded SHA-256 of the key, so config files never hold the key itself
+    pub sha256: String,
+    pub scopes: Vec<Scope>,
+    /// Generated-token allowance for this key
+    #[serde(default)]
+    pub quota: Option<TokenQuota>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TokenQuota {
+    pub tokens: u64,
+    pub period: QuotaPeriod,
+}
+
+/// A token bucket: `burst` requests at once, refilled at `requests_per_minute`
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BucketConfig {
+    pub requests_per_minute: u32,
+    pub burst: u32,
+}
+
+/// Per-client request rate limits by route group; unset groups are unlimited
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Generation, chat, embedding and agent routes
+    pub inference: Option<BucketConfig>,
+    /// Model management, status and metrics routes
+    pub admin: Option<BucketConfig>,
+}
+
+/// API key authentication; disabled while no keys are configured
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub keys: Vec<ApiKeyConfig>,
+    /// YAML file with a further `keys:` list, read at server start
+    pub keys_file: Option<PathBuf>,
+    /// Leave `/`, `/healthz`, `/health/*`, `/docs` and `/openapi.json` open
+    pub exempt_public: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            keys: Vec::new(),
+            keys_file: None,
+            exempt_public: true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct KeysFile {
+    #[serde(default)]
+    keys: Vec<ApiKeyConfig>,
+}
+
+impl AuthConfig {
+    /// Configured keys plus those in `keys_file`, if any.
+    pub fn load_keys(&self) -> Result<Vec<ApiKeyConfig>> {
+        let mut keys = self.keys.clone();
+        if let Some(path) = &self.keys_file {
+            let file: KeysFile = config::Config::builder()
+                .add_source(File::from(path.as_path()).format(FileFormat::Yaml))
+                .build()?
+                .try_deserialize()?;
+            keys.extend(file.keys);
+        }
+        Ok(keys)
+    }
+}
+
+/// Upper bounds applied to inference requests before they reach the NPU
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Largest accepted request body, images included
+    pub max_body_bytes: usize,
+    pub max_messages: usize,
+    /// Prompt length in characters, summed over all messages
+    pub max_prompt_chars: usize,
+    /// Token ids in one `/api/detokenize` request
+    pub max_detokenize_tokens: usize,
+    /// Images per request
+    pub max_images: usize,
+    /// Decoded size of a single image
+    pub max_image_bytes: usize,
+    /// Videos per request
+    pub max_videos: usize,
+    /// Decoded size of a single MP4/WebM video
+    pub max_video_bytes: usize,
+    /// Largest `num_ctx` a request may ask for
+    pub max_num_ctx: i32,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 32 * 1024 * 1024,
+            max_messages: 256,
+            max_prompt_chars: 128 * 1024,
+            max_detokenize_tokens: 32 * 1024,
+            max_images: 4,
+            max_image_bytes: 8 * 1024 * 1024,
+            max_videos: 1,
+            max_video_bytes: 24 * 1024 * 1024,
+            max_num_ctx: 16384,
+        }
+    }
+}
+
+/// What to do with a chat that no longer fits the context window
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextPolicy {
+    /// Drop the oldest non-system turns until the prompt fits
+    #[default]
+    TruncateOldest,
+    /// Like `truncate_oldest`, replacing the dropped turns with a summary
+    Summarize,
+    /// Reject the request with the token counts
+    Reject,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ContextConfig {
+    pub policy: ContextPolicy,
+    /// Tokens kept free for the reply when the request sets no `num_predict`
+    pub reserve_tokens: usize,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            policy: ContextPolicy::default(),
+            reserve_tokens: 512,
+        }
+    }
+}
+
+/// Where `image_url` parts may point besides inline `data:` URLs. Both lists
+/// are empty by default, so remote and local images are refused.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ImageUrlConfig {
+    /// Hosts `http(s)://` images may be fetched from; `*.example.com` matches subdomains
+    pub allow_hosts: Vec<String>,
+    /// Directories `file://` images may be read from
+    pub allow_paths: Vec<PathBuf>,
+    /// Time limit for fetching one image, in seconds
+    pub fetch_timeout_secs: u64,
+}
+
+impl Default for ImageUrlConfig {
+    fn default() -> Self {
+        Self {
+            allow_hosts: Vec::new(),
+            allow_paths: Vec::new(),
+            fetch_timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VisionEncoderKind {
+    /// ONNX export of the vision tower and projector, run on the CPU
+    #[default]
+    Onnx,
+    /// Deterministic placeholder embeddings, for testing without a vision model
+    Stub,
+}
+
+/// The vision encoder turning a multimodal model's images into embeddings.
+///
+/// Models without an entry reject requests that carry images.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct VisionModelConfig {
+    /// Model name as sent in requests, or the model's file name
+    pub model: String,
+    #[serde(default)]
+    pub encoder: VisionEncoderKind,
+    /// Encoder file, relative to `models_path` unless absolute
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// How images are resized, normalised, tiled and marked in the prompt:
+    /// a built-in profile name or a profile spelled out
+    #[serde(default)]
+    pub profile: ProfileSetting,
+    /// Width and height images are resized to, overriding the profile's
+    #[serde(default)]
+    pub input_size: Option<[u32; 2]>,
+    /// Embedding width; read from the ONNX model when unset
+    #[serde(default)]
+    pub embed_dim: Option<usize>,
+    /// Embeddings per image; read from the ONNX model when unset
+    #[serde(default)]
+    pub num_image_tokens: Option<usize>,
+}
+
+/// How frames are taken from MP4/WebM videos. Frame arrays sent instead of a
+/// video are only thinned out to `max_frames`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct VideoConfig {
+    /// The `ffmpeg` binary decoding videos
+    pub ffmpeg: PathBuf,
+    /// Frames sampled per second of video
+    pub fps: f32,
+    /// Frames kept per video
+    pub max_frames: usize,
+    /// Time limit for decoding one video, in seconds
+    pub timeout_secs: u64,
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        Self {
+            ffmpeg: PathBuf::from("ffmpeg"),
+            fps: 1.0,
+            max_frames: 8,
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// Where `/api/pull` downloads from and which files of a repository it picks.
+///
+/// Without an explicit variant or file globs, the `.rkllm` file built for
+/// `soc` is picked; unset, the SoC is read from `/proc/device-tree/compatible`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PullConfig {
+    /// Target SoC such as `rk3588`
+    pub soc: Option<String>,
+    /// Registry used by names without a `registry://` prefix
+    pub default_registry: String,
+    /// Registries in addition to, or replacing, the built-in `hf` and `modelscope`
+    pub registries: Vec<RegistryConfig>,
+}
+
+impl Default for PullConfig {
+    fn default() -> Self {
+        Self {
+            soc: None,
+            default_registry: "hf".to_string(),
+            registries: Vec::new(),
+        }
+    }
+}
+
+impl PullConfig {
+    /// The registry called `name`: a configured one, or else a built-in.
+    pub fn registry(&self, name: &str) -> Option<RegistryConfig> {
+        if let Some(registry) = self.registries.iter().find(|r| r.name == name) {
+            return Some(registry.clone());
+        }
+        let kind = match name {
+            "hf" => RegistryKind::Hf,
+            "modelscope" => RegistryKind::Modelscope,
+            _ => return None,
+        };
+        Some(RegistryConfig {
+            name: name.to_string(),
+            kind,
+            ..RegistryConfig::default()
+        })
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryKind {
+    /// The Hugging Face Hub API, as served by huggingface.co and its mirrors
+    #[default]
+    Hf,
+    Modelscope,
+    /// A plain HTTP server with an `index.json` listing its models
+    Http,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct RegistryConfig {
+    pub name: String,
+    pub kind: RegistryKind,
+    /// Base URL; defaults to the public service of `kind` (`HF_ENDPOINT` for `hf`)
+    pub endpoint: Option<String>,
+    /// Bearer token; defaults to `HF_TOKEN` for `hf` and `MODELSCOPE_API_TOKEN` for `modelscope`
+    pub token: Option<String>,
+    /// Where unfinished downloads are kept; defaults to next to the model
+    pub cache_dir: Option<PathBuf>,
+    /// Proxy URL for all requests to the registry
+    pub proxy: Option<String>,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             models_path: Some(PathBuf::from("./data")),
             base_url: "0.0.0.0:3000".into(),
+            preload: Vec::new(),
+            logging: LoggingConfig::default(),
+            auth: AuthConfig::default(),
+            rate_limits: RateLimitConfig::default(),
+            limits: LimitsConfig::default(),
+            context: ContextConfig::default(),
+            image_urls: ImageUrlConfig::default(),
+            vision: Vec::new(),
+            video: VideoConfig::default(),
+            pull: PullConfig::default(),
+            api_key: None,
+            host: None,
             dir: PathBuf::from("."),
         }
     }
 }
 
 impl Config {
+    /// The key CLI commands authenticate with, if any.
+    pub fn client_api_key(&self) -> Option<String> {
+        std::env::var("RKLLM_API_KEY")
+            .ok()
+            .filter(|key| !key.is_empty())
+            .or_else(|| self.api_key.clone())
+    }
+
+    /// The URL CLI commands send requests to, such as `http://board:3000`:
+    /// `--host`, then `RKLLM_HOST`, then `base_url`. A host without a scheme
+    /// is reached over `http`, and one without a port gets `base_url`'s; a
+    /// bare IPv6 address such as `::1` never has a port.
+    pub fn client_host(&self) -> String {
+        let host = self
+            .host
+            .clone()
+            .or_else(|| std::env::var("RKLLM_HOST").ok())
+            .map(|host| host.trim().trim_end_matches('/').to_string())
+            .filter(|host| !host.is_empty());
+        let Some(host) = host else {
+            return format!("http://{}", self.base_url);
+        };
+        let (scheme, rest) = host.split_once("://").unwrap_or(("http", host.as_str()));
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let mut authority = if authority.parse::<Ipv6Addr>().is_ok() {
+            format!("[{}]", authority)
+        } else {
+            authority.to_string()
+        };
+        if split_port(&authority).is_none() {
+            let port = split_port(&self.base_url).map_or(3000, |(_, port)| port);
+            authority = format!("{}:{}", authority, port);
+        }
+        let url = format!("{}://{}{}", scheme, authority, path);
+        // An unparsable host is left for the request to report.
+        match Url::parse(&url) {
+            Ok(parsed) => parsed.as_str().trim_end_matches('/').to_string(),
+            Err(_) => url,
+        }
+    }
+
+    /// Whether the client host is a server this process could start itself:
+    /// `base_url`, or a loopback address on its port when `base_url` is one.
+    pub fn client_host_is_local(&self) -> bool {
+        let (Ok(client), Ok(base)) = (
+            Url::parse(&self.client_host()),
+            Url::parse(&format!("http://{}", self.base_url)),
+        ) else {
+            return false;
+        };
+        let is_loopback = |url: &Url| {
+            let host = url.host_str().unwrap_or_default();
+            host == "localhost"
+                || host
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse::<IpAddr>()
+                    .is_ok_and(|ip| ip.is_loopback() || ip.is_unspecified())
+        };
+        // The server started here speaks plain HTTP at the root.
+        client.scheme() == "http"
+            && client.path() == "/"
+            && client.port_or_known_default() == base.port_or_known_default()
+            && (client.host_str() == base.host_str() || (is_loopback(&client) && is_loopback(&base)))
+    }
+
     pub fn load(dir: &Path) -> Result<Config> {
         let file = dir.join(CONFIG_FILE_NAME);
         if !file.exists() {
@@ -52,6 +550,16 @@ impl Config {
 }
 
 
+/// `host:port` or `[ipv6]:port` split into the host and a valid port.
+fn split_port(host: &str) -> Option<(&str, u16)> {
+    let (address, port) = host.rsplit_once(':')?;
+    if address.contains(':') && !(address.starts_with('[') && address.ends_with(']')) {
+        return None;
+    }
+    Some((address, port.parse().ok()?))
+}
+
+
 /*
 
 use std::fs::File;
@@ -77,4 +585,8 @@ impl Config {
 }
 
 
-*/
\ No newline at end of file
+*/
+
+#[cfg(test)]
+#[path = "config_test.rs"]
+mod tests;
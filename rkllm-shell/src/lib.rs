@@ -0,0 +1,30 @@
+pub mod args;
+pub mod commands;
+pub mod config;
+pub mod error;
+pub mod terminal;
+pub mod logging;
+pub mod server;
+
+// Those constants will be used for:
+// https://docs.rs/directories/latest/directories/struct.ProjectDirs.html#method.from
+pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
+pub const APP_ORGANIZATION: &str = "";
+pub const APP_QUALIFIER: &str = "";
+
+
+struct AppInfo {
+    name: String,
+    homepage: &'static str,
+    version: &'static str,
+}
+
+impl Default for AppInfo {
+    fn default() -> Self {
+        Self {
+            name: APP_NAME.into(),
+            homepage: option_env!("CARGO_PKG_HOMEPAGE").unwrap_or(""),
+            version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+}
@@ -1,49 +1,70 @@
-use std::sync;
 use std::io;
 
-use log;
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::{
+    fmt::{self, writer::BoxMakeWriter},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
 
-struct Logger<T> {
-    level: log::LevelFilter,
-    output: sync::Mutex<T>
-}
+use crate::config::{LogFormat, LoggingConfig};
 
-impl<T: Send + io::Write> Logger<T> {
-    pub fn new(output: T, level: log::LevelFilter) -> Logger<io::LineWriter<T>> {
-        Logger {
-            level: level,
-            output: sync::Mutex::new(io::LineWriter::new(output))
-        }
-    }
-}
+const LOG_FILE_PREFIX: &str = "rkllm-shell.log";
 
-impl<T: Send + io::Write> log::Log for Logger<T> {
-    fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.level() <= self.level &&
-            metadata.target().starts_with("rkllm_shell")
-    }
+/// Builds the filter: `-v` flags win over the config's `level`, which wins
+/// over `RUST_LOG`. Other crates stay at `warn` unless named explicitly.
+fn filter(verbosity: u8, config: &LoggingConfig) -> EnvFilter {
+    let level = match verbosity {
+        0 => None,
+        1 => Some("info"),
+        2 => Some("debug"),
+        _ => Some("trace"),
+    };
+    let directives = match (level, &config.level) {
+        (Some(level), _) => format!("warn,rkllm_shell={}", level),
+        (None, Some(configured)) => configured.clone(),
+        (None, None) => match std::env::var(EnvFilter::DEFAULT_ENV) {
+            Ok(env) => env,
+            Err(_) => "warn".to_string(),
+        },
+    };
+    EnvFilter::try_new(&directives).unwrap_or_else(|_| EnvFilter::new("warn"))
+}
 
-    fn log(&self, record: &log::Record) {
-        if self.enabled(record.metadata()) {
-            if let Ok(ref mut writer) = self.output.lock() {
-                let _ = writeln!(writer, "{}", record.args());
-            }
+/// Installs the global subscriber. Logs go to stderr, or to rotated files
+/// when `logging.dir` is set, and never to stdout so they cannot interleave
+/// with the REPL. Keep the returned guard alive to flush file output.
+pub fn init(verbosity: u8, config: &LoggingConfig) -> Option<WorkerGuard> {
+    let (writer, guard) = match &config.dir {
+        Some(dir) => {
+            let appender = rolling::RollingFileAppender::new(
+                config.rotation.into(),
+                dir,
+                LOG_FILE_PREFIX,
+            );
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (BoxMakeWriter::new(writer), Some(guard))
         }
-    }
-    
-    fn flush(&self) {}
-}
+        None => (BoxMakeWriter::new(io::stderr), None),
+    };
+
+    let layer = match config.format {
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(writer)
+            .boxed(),
+        LogFormat::Text => fmt::layer()
+            .with_ansi(config.dir.is_none())
+            .with_writer(writer)
+            .boxed(),
+    };
 
-pub fn init(verbosity: &u8) -> Result<(), log::SetLoggerError> {
-        let max_log_level = match verbosity{
-            0 => log::LevelFilter::Warn,
-            1 => log::LevelFilter::Info,
-            2 => log::LevelFilter::Debug,
-            _ => log::LevelFilter::Trace
-        };
-    let _ = log::set_boxed_logger(Box::new( Logger::new(io::stderr(),
-        max_log_level
-    )));
-    log::set_max_level(max_log_level);
-    Ok(())
+    let _ = tracing_subscriber::registry()
+        .with(filter(verbosity, config))
+        .with(layer)
+        .try_init();
+    guard
 }
@@ -1,38 +1,10 @@
-mod args;
-mod commands;
-mod config;
-mod error;
-mod terminal;
-mod logging;
-mod server;
-
 use clap::Parser;
 use directories::ProjectDirs;
 
-use crate::{args::Args, config::Config, error::Result, terminal::message::write};
-
-// Those constants will be used for:
-// https://docs.rs/directories/latest/directories/struct.ProjectDirs.html#method.from
-pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
-pub const APP_ORGANIZATION: &str = "";
-pub const APP_QUALIFIER: &str = "";
-
-
-struct AppInfo {
-    name: String,
-    homepage: &'static str,
-    version: &'static str,
-}
-
-impl Default for AppInfo {
-    fn default() -> Self {
-        Self {
-            name: APP_NAME.into(),
-            homepage: option_env!("CARGO_PKG_HOMEPAGE").unwrap_or(""),
-            version: env!("CARGO_PKG_VERSION"),
-        }
-    }
-}
+use rkllm_shell::{
+    args::Args, config::Config, error::Result, logging, terminal::message::write, APP_NAME,
+    APP_ORGANIZATION, APP_QUALIFIER,
+};
 
 async fn run() -> Result<()> {
     let mut args = Args::parse();
@@ -43,10 +15,12 @@ async fn run() -> Result<()> {
             .to_path_buf()
     });
 
-    // Initialize logging
-    let _ = logging::init(&args.verbosity);
+    let mut config = Config::load(config_dir)?;
+    config.host = args.host.take();
+
+    // Initialize logging; the guard flushes file output when dropped
+    let _log_guard = logging::init(args.verbosity, &config.logging);
 
-    let config = Config::load(config_dir)?;
     args.run_command(&config).await
 }
 
@@ -56,4 +30,4 @@ async fn main() {
         write::error(error).expect("cannot write error to stderr");
         std::process::exit(1);
     }
-}
\ No newline at end of file
+}
@@ -11,12 +11,15 @@ pub use ollama::{
     GenerateRequest, GenerateResponse,
     EmbedRequest, EmbedResponse,
     EmbedInput,
+    ContextReport,
+    TokenizeRequest, TokenizeResponse, DetokenizeRequest, DetokenizeResponse,
     Role,
+    VideoInput,
 };
 
 // Re-export Ollama model management types
 pub use ollama_models::{
-    PullRequest, ProgressResponse, DeleteRequest, ShowRequest, ShowResponse,
+    PullRequest, ProgressResponse, CreateRequest, PruneRequest, PruneResponse, DeleteRequest, UnloadRequest, CopyRequest, ShowRequest, ShowResponse,
     ListResponse, ListModelResponse, ModelDetails,
     ModelOptions,
 };
@@ -31,9 +31,19 @@ pub struct ChatCompletionRequestMessage {
     pub role: Role,
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub thunking: Option<String>,
+    pub thinking: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub images: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub videos: Option<Vec<VideoInput>>,
+}
+
+/// A base64 MP4/WebM video, or frames already taken from one as base64 images
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(untagged)]
+pub enum VideoInput {
+    Encoded(String),
+    Frames(Vec<String>),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -50,6 +60,9 @@ pub struct ChatCompletionRequest {
     pub max_tokens: Option<i32>,
     #[serde(default = "default_keep_alive")]
     pub keep_alive: Duration,
+    /// Let reasoning models think first; the thinking is returned in `message.thinking`
+    #[serde(default = "default_think")]
+    pub think: bool,
     #[serde(default = "default_model_options")]
     pub options: crate::server::ollama_models::ModelOptions,
 }
@@ -61,6 +74,25 @@ pub struct ChatCompletionResponse {
     pub message: ChatCompletionRequestMessage,
     pub done_reason: String,
     pub done: bool,
+    /// How the prompt was fitted into the context window; set on the final response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextReport>,
+}
+
+/// How a chat prompt was fitted into the model's context window
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ToSchema)]
+pub struct ContextReport {
+    pub num_ctx: usize,
+    /// Prompt tokens after any truncation
+    pub prompt_tokens: usize,
+    /// Tokens kept free for the completion
+    pub reserved_tokens: usize,
+    /// Oldest turns removed to make the prompt fit
+    pub dropped_messages: usize,
+    /// Whether the removed turns were replaced by a summary
+    pub summarized: bool,
+    /// True when no tokenizer file was found and counts are estimates
+    pub estimated: bool,
 }
 
 // ---------------------------------------------------------------------------
@@ -87,6 +119,9 @@ pub struct GenerateRequest {
     pub max_tokens: Option<i32>,
     #[serde(default = "default_keep_alive")]
     pub keep_alive: Duration,
+    /// Let reasoning models think first; the thinking is returned in `thinking`
+    #[serde(default = "default_think")]
+    pub think: bool,
     #[serde(default = "default_model_options")]
     pub options: crate::server::ollama_models::ModelOptions,
 }
@@ -96,6 +131,8 @@ pub struct GenerateResponse {
     pub model: String,
     pub created_at: DateTime<Utc>,
     pub response: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
     pub done: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context: Option<Vec<i32>>,
@@ -144,3 +181,38 @@ pub struct EmbedResponse {
     pub load_duration: Duration,
     pub prompt_eval_count: i32,
 }
+
+/// Text to tokenize: a raw `prompt`, or chat `messages` rendered with the
+/// chat template. `apply_chat_template` renders `prompt` as a user turn.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct TokenizeRequest {
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<ChatCompletionRequestMessage>>,
+    #[serde(default)]
+    pub apply_chat_template: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct TokenizeResponse {
+    pub model: String,
+    /// Token ids; empty when `estimated` is true
+    pub tokens: Vec<u32>,
+    pub count: usize,
+    /// True when no tokenizer file was found and `count` is an estimate
+    pub estimated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct DetokenizeRequest {
+    pub model: String,
+    pub tokens: Vec<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct DetokenizeResponse {
+    pub model: String,
+    pub content: String,
+}
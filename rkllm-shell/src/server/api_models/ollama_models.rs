@@ -2,6 +2,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::time::Duration;
 use utoipa::ToSchema;
 
@@ -18,6 +19,9 @@ pub struct PullRequest {
     pub insecure: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    /// Globs naming the files to download, instead of picking a variant
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -31,9 +35,51 @@ pub struct ProgressResponse {
     pub completed: Option<u64>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CreateRequest {
+    /// Name to store the model as, `name[:tag]`
+    #[serde(alias = "name")]
+    pub model: String,
+    /// Modelfile text; file paths in it are read on the server
+    pub modelfile: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct PruneRequest {
+    /// Report what would be removed without removing it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct PruneResponse {
+    pub removed: Vec<String>,
+    pub reclaimed_bytes: u64,
+    pub dry_run: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct DeleteRequest {
+    #[serde(alias = "name")]
     pub model: String,
+    /// Unload the model first instead of refusing while it is loaded
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct UnloadRequest {
+    #[serde(alias = "name")]
+    pub model: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CopyRequest {
+    pub source: String,
+    /// The new `name[:tag]`; an existing model of that name is replaced
+    pub destination: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -89,7 +135,10 @@ pub struct ModelDetails {
 // Shared types
 // ---------------------------------------------------------------------------
 
+/// Sampling options. Options a request leaves out take their defaults, or
+/// the model's `PARAMETER`s where it has them.
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema, Default)]
+#[serde(from = "GivenOptions")]
 pub struct ModelOptions {
     pub num_ctx: i32,
     pub repeat_last_n: i32,
@@ -101,6 +150,56 @@ pub struct ModelOptions {
     pub top_k: i32,
     pub top_p: f32,
     pub min_p: f32,
+    /// Names of the options the request gave
+    #[serde(skip)]
+    pub given: BTreeSet<&'static str>,
+}
+
+impl ModelOptions {
+    /// Whether the request gave option `name`, so a model's parameter must
+    /// not replace it.
+    pub fn is_given(&self, name: &str) -> bool {
+        self.given.contains(name)
+    }
+}
+
+/// `options` as a request sends them, any of them left out
+#[derive(Deserialize)]
+struct GivenOptions {
+    num_ctx: Option<i32>,
+    repeat_last_n: Option<i32>,
+    repeat_penalty: Option<f32>,
+    temperature: Option<f32>,
+    seed: Option<i32>,
+    stop: Option<Vec<String>>,
+    num_predict: Option<i32>,
+    top_k: Option<i32>,
+    top_p: Option<f32>,
+    min_p: Option<f32>,
+}
+
+impl From<GivenOptions> for ModelOptions {
+    fn from(given: GivenOptions) -> Self {
+        fn take<T>(field: &mut T, value: Option<T>, name: &'static str, names: &mut BTreeSet<&'static str>) {
+            if let Some(value) = value {
+                *field = value;
+                names.insert(name);
+            }
+        }
+        let mut options = default_model_options();
+        let names = &mut options.given;
+        take(&mut options.num_ctx, given.num_ctx, "num_ctx", names);
+        take(&mut options.repeat_last_n, given.repeat_last_n, "repeat_last_n", names);
+        take(&mut options.repeat_penalty, given.repeat_penalty, "repeat_penalty", names);
+        take(&mut options.temperature, given.temperature, "temperature", names);
+        take(&mut options.seed, given.seed, "seed", names);
+        take(&mut options.stop, given.stop, "stop", names);
+        take(&mut options.num_predict, given.num_predict, "num_predict", names);
+        take(&mut options.top_k, given.top_k, "top_k", names);
+        take(&mut options.top_p, given.top_p, "top_p", names);
+        take(&mut options.min_p, given.min_p, "min_p", names);
+        options
+    }
 }
 
 // Re-export defaults for external use
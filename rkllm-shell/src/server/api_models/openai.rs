@@ -39,6 +39,8 @@ pub enum OpenAiContentPart {
     Text { text: String },
     #[serde(rename = "image_url")]
     ImageUrl { image_url: OpenAiImageUrl },
+    #[serde(rename = "video_url")]
+    VideoUrl { video_url: OpenAiVideoUrl },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -48,10 +50,17 @@ pub struct OpenAiImageUrl {
     pub detail: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct OpenAiVideoUrl {
+    pub url: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct OpenAiMessage {
     pub role: String,
     pub content: OpenAiContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -60,14 +69,18 @@ pub struct OpenAiChatRequest {
     pub messages: Vec<OpenAiMessage>,
     #[serde(default = "default_stream")]
     pub stream: bool,
-    #[serde(default = "default_temperature")]
-    pub temperature: f32,
-    #[serde(default = "default_top_p")]
-    pub top_p: f32,
+    /// Left out, the model's `PARAMETER temperature` or the default applies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<i32>,
     #[serde(default = "default_keep_alive")]
     pub keep_alive: Duration,
+    /// Any value but `none` lets reasoning models think first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -92,6 +105,8 @@ pub struct OpenAiChatResponse {
     pub model: String,
     pub choices: Vec<OpenAiChoice>,
     pub usage: OpenAiUsage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<crate::server::api_models::ContextReport>,
 }
 
 // Streaming types
@@ -102,6 +117,8 @@ pub struct OpenAiDelta {
     pub role: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -118,6 +135,9 @@ pub struct OpenAiChatChunk {
     pub created: i64,
     pub model: String,
     pub choices: Vec<OpenAiStreamChoice>,
+    /// Set on the final chunk only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<crate::server::api_models::ContextReport>,
 }
 
 // ---------------------------------------------------------------------------
@@ -165,14 +185,6 @@ fn default_stream() -> bool {
     false
 }
 
-fn default_temperature() -> f32 {
-    0.8
-}
-
-fn default_top_p() -> f32 {
-    0.9
-}
-
 fn default_keep_alive() -> Duration {
     std::time::Duration::from_secs(300) // 5 minutes default
 }
@@ -4,9 +4,10 @@ use chrono::Utc;
 use std::time::Duration;
 
 use crate::server::api_models::{
-    ollama::{ChatCompletionRequest as OllamaChatRequest, ChatCompletionRequestMessage as OllamaMessage, ChatCompletionResponse as OllamaChatResponse, GenerateRequest as OllamaGenerateRequest, GenerateResponse as OllamaGenerateResponse, EmbedRequest as OllamaEmbedRequest, EmbedResponse as OllamaEmbedResponse, Role},
+    ollama::{ChatCompletionRequest as OllamaChatRequest, ChatCompletionRequestMessage as OllamaMessage, VideoInput, ChatCompletionResponse as OllamaChatResponse, GenerateRequest as OllamaGenerateRequest, GenerateResponse as OllamaGenerateResponse, EmbedRequest as OllamaEmbedRequest, EmbedResponse as OllamaEmbedResponse, Role},
     openai::{OpenAiChatRequest, OpenAiChatResponse, OpenAiChatChunk, OpenAiStreamChoice, OpenAiDelta, OpenAiChoice, OpenAiUsage, OpenAiMessage, OpenAiContent, OpenAiContentPart, OpenAiImageUrl},
 };
+use crate::server::vision::{IMAGE_PLACEHOLDER, VIDEO_PLACEHOLDER};
 
 /// Convert OpenAI Chat Request to Ollama Chat Request
 impl From<OpenAiChatRequest> for OllamaChatRequest {
@@ -15,17 +16,13 @@ impl From<OpenAiChatRequest> for OllamaChatRequest {
             .messages
             .into_iter()
             .map(|m| {
-                let (content, images) = extract_content_and_images(m.content);
+                let (content, images, videos) = extract_content_and_media(m.content);
                 OllamaMessage {
-                    role: match m.role.as_str() {
-                        "system" => Role::System,
-                        "user" => Role::User,
-                        "assistant" => Role::Assistant,
-                        _ => Role::User,
-                    },
+                    role: openai_role_to_ollama(&m.role),
                     content,
-                    thunking: None,
+                    thinking: None,
                     images,
+                    videos,
                 }
             })
             .collect();
@@ -34,22 +31,30 @@ impl From<OpenAiChatRequest> for OllamaChatRequest {
             model: req.model,
             messages,
             stream: req.stream,
-            temperature: req.temperature,
-            top_p: req.top_p,
+            temperature: req.temperature.unwrap_or_else(crate::server::defaults::default_temperature),
+            top_p: req.top_p.unwrap_or_else(crate::server::defaults::default_top_p),
             max_tokens: req.max_tokens,
             keep_alive: req.keep_alive,
+            think: crate::server::thinking::reasoning_effort_enables_thinking(
+                req.reasoning_effort.as_deref(),
+            ),
             options: crate::server::defaults::default_model_options(),
         }
     }
 }
 
-/// Extract text content and base64 images from OpenAI content
-pub fn extract_content_and_images(content: OpenAiContent) -> (String, Option<Vec<String>>) {
+/// Extract text content, base64 images and base64 videos from OpenAI content.
+/// Each image or video leaves a placeholder in the text, so it keeps its place
+/// among the text parts.
+pub fn extract_content_and_media(
+    content: OpenAiContent,
+) -> (String, Option<Vec<String>>, Option<Vec<VideoInput>>) {
     match content {
-        OpenAiContent::Text(text) => (text, None),
+        OpenAiContent::Text(text) => (text, None, None),
         OpenAiContent::Array(parts) => {
             let mut text_parts = Vec::new();
             let mut images = Vec::new();
+            let mut videos = Vec::new();
             for part in parts {
                 match part {
                     OpenAiContentPart::Text { text } => text_parts.push(text),
@@ -63,16 +68,25 @@ pub fn extract_content_and_images(content: OpenAiContent) -> (String, Option<Vec
                         };
                         if !base64.is_empty() {
                             images.push(base64);
+                            text_parts.push(IMAGE_PLACEHOLDER.to_string());
+                        }
+                    }
+                    OpenAiContentPart::VideoUrl { video_url } => {
+                        let base64 = match video_url.url.split_once(',') {
+                            Some((_, data)) if video_url.url.starts_with("data:") => data.to_string(),
+                            _ => video_url.url,
+                        };
+                        if !base64.is_empty() {
+                            videos.push(VideoInput::Encoded(base64));
+                            text_parts.push(VIDEO_PLACEHOLDER.to_string());
                         }
                     }
                 }
             }
             let combined_text = text_parts.join("\n");
-            if images.is_empty() {
-                (combined_text, None)
-            } else {
-                (combined_text, Some(images))
-            }
+            let images = (!images.is_empty()).then_some(images);
+            let videos = (!videos.is_empty()).then_some(videos);
+            (combined_text, images, videos)
         }
     }
 }
@@ -91,6 +105,7 @@ impl From<OllamaChatResponse> for OpenAiChatResponse {
                 message: OpenAiMessage {
                     role: format!("{:?}", resp.message.role).to_lowercase(),
                     content: OpenAiContent::Text(resp.message.content),
+                    reasoning_content: resp.message.thinking,
                 },
                 finish_reason: if resp.done { "stop" } else { "length" }.to_string(),
             }],
@@ -99,6 +114,7 @@ impl From<OllamaChatResponse> for OpenAiChatResponse {
                 completion_tokens: 0,
                 total_tokens: 0,
             },
+            context: resp.context,
         }
     }
 }
@@ -117,9 +133,11 @@ impl From<OllamaChatResponse> for OpenAiChatChunk {
                 delta: OpenAiDelta {
                     role: if resp.done { None } else { Some("assistant".to_string()) },
                     content: if resp.done { None } else { Some(resp.message.content) },
+                    reasoning_content: if resp.done { None } else { resp.message.thinking },
                 },
                 finish_reason: if resp.done { Some("stop".to_string()) } else { None },
             }],
+            context: resp.context,
         }
     }
 }
@@ -136,9 +154,11 @@ pub fn openai_done_chunk(model: String, id: String) -> OpenAiChatChunk {
             delta: OpenAiDelta {
                 role: None,
                 content: None,
+                reasoning_content: None,
             },
             finish_reason: Some("stop".to_string()),
         }],
+        context: None,
     }
 }
 
@@ -150,12 +170,14 @@ impl From<OllamaGenerateRequest> for OpenAiChatRequest {
             messages: vec![OpenAiMessage {
                 role: "user".to_string(),
                 content: OpenAiContent::Text(req.prompt),
+                reasoning_content: None,
             }],
             stream: req.stream,
-            temperature: req.temperature,
-            top_p: req.top_p,
+            temperature: Some(req.temperature),
+            top_p: Some(req.top_p),
             max_tokens: req.max_tokens,
             keep_alive: req.keep_alive,
+            reasoning_effort: req.think.then(|| "medium".to_string()),
         }
     }
 }
@@ -183,9 +205,10 @@ pub fn ollama_embed_to_openai(resp: OllamaEmbedResponse) -> serde_json::Value {
 /// Shared role mapping helper
 pub fn openai_role_to_ollama(role: &str) -> Role {
     match role {
-        "system" => Role::System,
+        "system" | "developer" => Role::System,
         "user" => Role::User,
         "assistant" => Role::Assistant,
+        "tool" => Role::Tool,
         _ => Role::User,
     }
 }
@@ -1,7 +1,7 @@
 //! Agent API endpoints for rig-based agents
 
 use axum::{
-    extract::{Json, State},
+    extract::{Extension, Json, State},
     response::sse::{Event, Sse},
     response::IntoResponse,
 };
@@ -24,7 +24,9 @@ use crate::error::Result as RkllmResult;
 use crate::server::rig_provider::RkllmClient;
 use crate::server::AppState;
 use crate::server::api_models::ollama::{ChatCompletionRequestMessage, Role};
-use crate::terminal::message::write;
+use crate::server::rate_limit::{self, TokenUsage};
+use crate::server::request_log;
+use crate::server::runtime_trait::ModelRuntime;
 
 /// Request for agent chat
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
@@ -91,8 +93,10 @@ pub struct AgentStreamChunk {
 )]
 pub async fn agent_chat(
     State(state): State<AppState>,
+    usage: Option<Extension<TokenUsage>>,
     Json(req): Json<AgentChatRequest>,
 ) -> RkllmResult<impl IntoResponse> {
+    request_log::record_model(&req.model);
     let model_name = req.model.clone();
     let session_id = req.session_id.clone();
 
@@ -116,6 +120,15 @@ pub async fn agent_chat(
     let response = agent.prompt(&prompt).await
         .map_err(|e| crate::error::Error::Server(format!("Agent error: {}", e)))?;
 
+    if let Some(usage) = usage {
+        // The agent returns only text, so count its tokens for the quota.
+        let tokens = match state.runtime.tokenizer(&model_name).await {
+            Ok(tokenizer) => tokenizer.count(&response),
+            Err(_) => response.split_whitespace().count(),
+        };
+        usage.charge(tokens as u64).await;
+    }
+
     Ok(Json(AgentChatResponse {
         response,
         model: model_name,
@@ -141,8 +154,10 @@ pub async fn agent_chat(
 )]
 pub async fn agent_stream(
     State(state): State<AppState>,
+    usage: Option<Extension<TokenUsage>>,
     Json(req): Json<AgentChatRequest>,
 ) -> RkllmResult<Sse<Pin<Box<dyn Stream<Item = std::result::Result<Event, Infallible>> + Send>>>> {
+    request_log::record_model(&req.model);
     let model_name = req.model.clone();
     let session_id = req.session_id.clone();
 
@@ -158,63 +173,57 @@ pub async fn agent_stream(
     // Convert messages to prompt
     let prompt = messages_to_prompt(&req.messages);
 
-    // Create streaming response
-    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-    
+    // Text chunks go through the quota tracker before becoming events.
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+
     tokio::spawn(async move {
         // Stream the agent response
         let mut stream = agent.stream_prompt(&prompt).await;
 
         while let Some(chunk) = stream.next().await {
-            match chunk {
-                Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text))) => {
-                    let content = text.text;
-                    let chunk_json = serde_json::to_string(&AgentStreamChunk {
-                        content,
-                        done: false,
-                    }).unwrap();
-                    let event = Event::default().data(chunk_json);
-                    if tx.send(Ok(event)).is_err() {
-                        break;
-                    }
-                }
+            let content = match chunk {
+                Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text))) => text.text,
                 Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Reasoning(reasoning))) => {
-                    let content = reasoning.display_text();
-                    let chunk_json = serde_json::to_string(&AgentStreamChunk {
-                        content,
-                        done: false,
-                    }).unwrap();
-                    let event = Event::default().data(chunk_json);
-                    if tx.send(Ok(event)).is_err() {
-                        break;
-                    }
+                    reasoning.display_text()
                 }
                 Ok(MultiTurnStreamItem::FinalResponse(_)) => {
                     // Final response received
+                    continue;
                 }
                 Ok(MultiTurnStreamItem::ModelTurnRetried { turn: _ }) => {
                     // Model turn retried, continue
+                    continue;
                 }
                 Ok(_) => {
                     // Other stream items (tool calls, etc.) - ignore for now
+                    continue;
                 }
                 Err(e) => {
-                    write::error(format!("Stream error: {}", e)).ok();
+                    tracing::error!(error = %e, "agent stream error");
                     break;
                 }
+            };
+            if tx.send(content).is_err() {
+                break;
             }
         }
-        // Send done event
+    });
+
+    let rx = rate_limit::track_usage(usage.as_deref(), rx);
+    let chunks = tokio_stream::wrappers::UnboundedReceiverStream::new(rx).map(|content| {
+        let chunk_json = serde_json::to_string(&AgentStreamChunk { content, done: false }).unwrap();
+        Ok::<Event, Infallible>(Event::default().data(chunk_json))
+    });
+    // Send done event
+    let done = tokio_stream::once(()).map(|_| {
         let done_chunk = serde_json::to_string(&AgentStreamChunk {
             content: String::new(),
             done: true,
-        }).unwrap();
-        let event = Event::default().data(done_chunk);
-        let _ = tx.send(Ok(event));
+        })
+        .unwrap();
+        Ok::<Event, Infallible>(Event::default().data(done_chunk))
     });
-
-    let stream = tokio_stream::wrappers::UnboundedReceiverStream::new(rx);
-    Ok(Sse::new(Box::pin(stream)))
+    Ok(Sse::new(Box::pin(chunks.chain(done))))
 }
 
 /// Convert messages to a single prompt string
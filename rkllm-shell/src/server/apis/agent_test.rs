@@ -202,7 +202,7 @@ mod tests {
 #[cfg(test)]
 mod integration_tests {
     use super::*;
-    use crate::server::{AppState, rig_provider::RkllmClient, rkllm_runtime::RkllmRuntime};
+    use crate::server::{AppState, rkllm_runtime::RkllmRuntime};
     use crate::config::Config;
     use std::path::PathBuf;
     use std::sync::Arc;
@@ -211,14 +211,8 @@ mod integration_tests {
         let config = Arc::new(test_config());
         let models_path = PathBuf::from("./mock_models");
         let runtime = RkllmRuntime::new(models_path);
-        let rig_client = RkllmClient::new(Arc::new(runtime.clone()));
-
-        AppState {
-            runtime,
-            config,
-            digest_cache: crate::server::DigestCache::default(),
-            rig_client,
-        }
+
+        AppState::new(runtime, config)
     }
 
     #[tokio::test]
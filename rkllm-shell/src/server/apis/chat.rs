@@ -1,5 +1,5 @@
 use axum::{
-    extract::State,
+    extract::{Extension, State},
     response::{
         sse::{Event, KeepAlive, Sse},
         IntoResponse, Response,
@@ -7,18 +7,29 @@ use axum::{
     Json,
 };
 use chrono::Utc;
+use image::DynamicImage;
+use std::time::Instant;
 use futures::stream::{self, StreamExt};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
+use crate::config::ContextPolicy;
 use crate::server::{
     api_models::{
         ChatCompletionRequest, ChatCompletionRequestMessage, ChatCompletionResponse,
-        OpenAiChatChunk, OpenAiChatRequest, OpenAiChatResponse, OpenAiChoice, OpenAiDelta,
+        ModelOptions, OpenAiChatChunk, OpenAiChatRequest, OpenAiChatResponse, OpenAiChoice, OpenAiDelta,
         OpenAiMessage, OpenAiStreamChoice, OpenAiUsage, Role,
     },
     api_models::openai::OpenAiContent,
-    api_models::translate::extract_content_and_images,
-    rkllm_runtime::CompletionRequest,
+    api_models::translate::{extract_content_and_media, openai_role_to_ollama},
+    context::{self, ContextBudget, ContextPlan},
+    modelfile,
+    image_urls::ImageUrlResolver,
+    rkllm_runtime::{CompletionRequest, RkllmModel},
+    apis::{error::ApiError, validation::Validate},
+    rate_limit::{self, TokenUsage},
+    request_log,
+    thinking::{self, Segment},
+    vision::video,
     AppState,
 };
 
@@ -37,18 +48,48 @@ fn extract_images(messages: &[ChatCompletionRequestMessage]) -> Vec<String> {
     images
 }
 
+/// Decode the videos in messages into their sampled frames
+async fn load_videos(
+    state: &AppState,
+    messages: &[ChatCompletionRequestMessage],
+) -> Result<Vec<Vec<DynamicImage>>, ApiError> {
+    let mut videos = Vec::new();
+    for video in messages.iter().filter_map(|msg| msg.videos.as_ref()).flatten() {
+        videos.push(video::load_video(video, &state.config.video).await?);
+    }
+    Ok(videos)
+}
+
 /// Build prompt string from messages (without images)
 fn build_prompt_from_messages(messages: &[ChatCompletionRequestMessage]) -> String {
-    messages
-        .iter()
-        .map(|m| match m.role {
-            Role::System => format!("<|System|>: {}", m.content),
-            Role::User => format!("<|User|>: {}", m.content),
-            Role::Assistant => format!("<|Assistant|>: {}", m.content),
-            _ => m.content.clone(),
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
+    build_ollama_messages(messages).join("\n")
+}
+
+/// Fits the conversation into the model's context window per `context.policy`.
+async fn fit_context(
+    state: &AppState,
+    model: &RkllmModel,
+    messages: Vec<ChatCompletionRequestMessage>,
+    options: &ModelOptions,
+) -> Result<ContextPlan, ApiError> {
+    let tokenizer = state.runtime.tokenizers().get(model.model_path(), model.tokenizer_path()).await;
+    let budget = ContextBudget::new(options, &state.config.context);
+    let policy = state.config.context.policy;
+    let mut plan = context::fit_messages(messages, &*tokenizer, budget, policy)?;
+    if plan.report.dropped_messages > 0 {
+        tracing::info!(
+            dropped = plan.report.dropped_messages,
+            prompt_tokens = plan.report.prompt_tokens,
+            "conversation truncated to fit the context window"
+        );
+        if policy == ContextPolicy::Summarize {
+            context::summarize_dropped(&mut plan, &*tokenizer, budget, |prompt| {
+                model.run_inference(prompt)
+            })
+            .await;
+        }
+    }
+    Ok(plan)
 }
 
 // ---------------------------------------------------------------------------
@@ -60,49 +101,74 @@ fn build_prompt_from_messages(messages: &[ChatCompletionRequestMessage]) -> Stri
     path = "/api/chat",
     request_body = ChatCompletionRequest,
     responses(
-        (status = 200, description = "Chat completion response", body = ChatCompletionResponse)
+        (status = 200, description = "Chat completion response", body = ChatCompletionResponse),
+        (status = 400, description = "Invalid request")
     ),
     tag = "rkllm"
 )]
 pub async fn generate_chat_completion(
     State(state): State<AppState>,
-    Json(request): Json<ChatCompletionRequest>,
+    usage: Option<Extension<TokenUsage>>,
+    Json(mut request): Json<ChatCompletionRequest>,
 ) -> axum::response::Result<Response> {
+    let started = Instant::now();
+    request_log::record_model(&request.model);
+    let stored = state.runtime.resolve(&request.model).await.map_err(ApiError::from)?;
+    // Validated with the model's parameters in, as they reach the runtime too.
+    modelfile::apply_chat_defaults(&stored, &mut request);
+    request.validate(&state.config.limits)?;
     let model = state
         .runtime
         .get_request_model(&CompletionRequest::Chat(request.clone()))
         .await
-        .map_err(|e| axum::response::ErrorResponse::from(e))?;
+        .map_err(ApiError::from)?;
+
+    let plan = fit_context(&state, &model, request.messages.clone(), &request.options).await?;
+    let report = plan.report;
 
     // Extract images from messages
-    let images = extract_images(&request.messages);
+    let images = extract_images(&plan.messages);
+    let videos = load_videos(&state, &plan.messages).await?;
     // Build prompt from messages
-    let prompt = build_prompt_from_messages(&request.messages);
+    let prompt = build_prompt_from_messages(&plan.messages);
     let model_name = request.model.clone();
     let stream_mode = request.stream;
 
-    // Use multimodal inference if images are present
-    let rx = if images.is_empty() {
-        model.run_inference(vec![prompt])
+    let think = request.think;
+
+    // Use multimodal inference if images or videos are present
+    let rx = if images.is_empty() && videos.is_empty() {
+        model.run_inference_with_thinking(vec![prompt], think)
     } else {
-        model.run_multimodal_inference(prompt, images)
+        model
+            .run_multimodal_inference_with_thinking(prompt, images, videos, think)
+            .await
+            .map_err(ApiError::from)?
     };
+    let rx = state.metrics.instrument("/api/chat", started, rx);
+    let rx = rate_limit::track_usage(usage.as_deref(), rx);
 
     if stream_mode {
-        // Stream one JSON object per token.
-        let token_stream = UnboundedReceiverStream::new(rx);
-        let event_stream = token_stream.map(move |token| {
+        // Stream one JSON object per token, thinking and answer apart.
+        let segment_stream = UnboundedReceiverStream::new(thinking::split_stream(rx, think));
+        let event_stream = segment_stream.map(move |segment| {
+            let (content, thinking) = match segment {
+                Segment::Content(text) => (text, None),
+                Segment::Thinking(text) => (String::new(), Some(text)),
+            };
             let chunk = ChatCompletionResponse {
                 model: model_name.clone(),
                 created_at: Utc::now(),
                 message: ChatCompletionRequestMessage {
                     role: Role::Assistant,
-                    content: token,
-                    thunking: None,
+                    content,
+                    thinking,
                     images: None,
+                    videos: None,
                 },
                 done_reason: String::new(),
                 done: false,
+                context: None,
             };
             let data = serde_json::to_string(&chunk).unwrap_or_default();
             Ok::<Event, std::convert::Infallible>(Event::default().data(data))
@@ -110,6 +176,7 @@ pub async fn generate_chat_completion(
 
         // Append a final "done" event.
         let done_model = request.model.clone();
+        let done_report = report.clone();
         let done_event = stream::once(async move {
             let final_chunk = ChatCompletionResponse {
                 model: done_model,
@@ -117,11 +184,13 @@ pub async fn generate_chat_completion(
                 message: ChatCompletionRequestMessage {
                     role: Role::Assistant,
                     content: String::new(),
-                    thunking: None,
+                    thinking: None,
                     images: None,
+                    videos: None,
                 },
                 done_reason: "stop".to_string(),
                 done: true,
+                context: Some(done_report),
             };
             let data = serde_json::to_string(&final_chunk).unwrap_or_default();
             Ok::<Event, std::convert::Infallible>(Event::default().data(data))
@@ -136,17 +205,20 @@ pub async fn generate_chat_completion(
         while let Some(token) = rx.recv().await {
             response_text.push_str(&token);
         }
+        let (thinking, content) = thinking::split(&response_text);
         let response = ChatCompletionResponse {
             model: request.model.clone(),
             created_at: Utc::now(),
             message: ChatCompletionRequestMessage {
                 role: Role::Assistant,
-                content: response_text,
-                thunking: None,
+                content,
+                thinking: (think && !thinking.is_empty()).then_some(thinking),
                 images: None,
+                videos: None,
             },
             done_reason: "stop".to_string(),
             done: true,
+            context: Some(report),
         };
         Ok(Json(response).into_response())
     }
@@ -161,75 +233,105 @@ pub async fn generate_chat_completion(
     path = "/v1/chat/completions",
     request_body = OpenAiChatRequest,
     responses(
-        (status = 200, description = "OpenAI chat completion response", body = OpenAiChatResponse)
+        (status = 200, description = "OpenAI chat completion response", body = OpenAiChatResponse),
+        (status = 400, description = "Invalid request")
     ),
     tag = "rkllm"
 )]
 pub async fn openai_chat_completions(
     State(state): State<AppState>,
-    Json(request): Json<OpenAiChatRequest>,
+    usage: Option<Extension<TokenUsage>>,
+    Json(mut request): Json<OpenAiChatRequest>,
 ) -> axum::response::Result<Response> {
+    let started = Instant::now();
+    request_log::record_model(&request.model);
+    request.validate(&state.config.limits)?;
+    ImageUrlResolver::new(&state.config.image_urls, &state.config.limits)
+        .resolve_request(&mut request)
+        .await?;
     // Translate OpenAI request → internal ChatCompletionRequest
-    use crate::server::api_models::{ChatCompletionRequestMessage as Msg, ModelOptions};
+    use crate::server::api_models::ChatCompletionRequestMessage as Msg;
     use crate::server::defaults::*;
 
     let messages: Vec<Msg> = request
         .messages
         .iter()
         .map(|m| {
-            let (content, images) = extract_content_and_images(m.content.clone());
+            let (content, images, videos) = extract_content_and_media(m.content.clone());
             Msg {
-                role: match m.role.as_str() {
-                    "system" => Role::System,
-                    "assistant" => Role::Assistant,
-                    _ => Role::User,
-                },
+                role: openai_role_to_ollama(&m.role),
                 content,
-                thunking: None,
+                thinking: None,
                 images,
+                videos,
             }
         })
         .collect();
 
-    let num_predict = request.max_tokens.unwrap_or(default_num_predict());
-    let internal = ChatCompletionRequest {
+    // Only what the request gives is kept from the model's parameters.
+    let mut options = default_model_options();
+    if let Some(temperature) = request.temperature {
+        options.temperature = temperature;
+        options.given.insert("temperature");
+    }
+    if let Some(top_p) = request.top_p {
+        options.top_p = top_p;
+        options.given.insert("top_p");
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        options.num_predict = max_tokens;
+        options.given.insert("num_predict");
+    }
+    let mut internal = ChatCompletionRequest {
         model: request.model.clone(),
         messages,
         stream: request.stream,
-        temperature: request.temperature,
-        top_p: request.top_p,
+        temperature: options.temperature,
+        top_p: options.top_p,
         max_tokens: request.max_tokens,
         keep_alive: request.keep_alive,
-        options: ModelOptions {
-            temperature: request.temperature,
-            top_p: request.top_p,
-            num_predict,
-            num_ctx: default_context_window(),
-            repeat_last_n: default_repeat_last_n(),
-            repeat_penalty: default_repeat_penalty(),
-            seed: default_seed(),
-            stop: default_stop(),
-            top_k: default_top_k(),
-            min_p: default_min_p(),
-        },
+        think: thinking::reasoning_effort_enables_thinking(request.reasoning_effort.as_deref()),
+        options,
     };
 
+    let stored = state.runtime.resolve(&internal.model).await.map_err(ApiError::from)?;
+    modelfile::apply_chat_defaults(&stored, &mut internal);
+    internal.options.validate(&state.config.limits)?;
+
     let model = state
         .runtime
         .get_request_model(&CompletionRequest::Chat(internal.clone()))
         .await
-        .map_err(|e| axum::response::ErrorResponse::from(e))?;
+        .map_err(ApiError::from)?;
 
-    let ollama_msgs = build_ollama_messages(&internal.messages);
-    let rx = model.run_inference(ollama_msgs);
+    let plan = fit_context(&state, &model, internal.messages.clone(), &internal.options).await?;
+    let report = plan.report;
+    let images = extract_images(&plan.messages);
+    let videos = load_videos(&state, &plan.messages).await?;
+    let think = internal.think;
+    let rx = if images.is_empty() && videos.is_empty() {
+        model.run_inference_with_thinking(build_ollama_messages(&plan.messages), think)
+    } else {
+        let prompt = build_prompt_from_messages(&plan.messages);
+        model
+            .run_multimodal_inference_with_thinking(prompt, images, videos, think)
+            .await
+            .map_err(ApiError::from)?
+    };
+    let rx = state.metrics.instrument("/v1/chat/completions", started, rx);
+    let rx = rate_limit::track_usage(usage.as_deref(), rx);
     let model_name = request.model.clone();
     let stream_mode = request.stream;
     let completion_id = format!("chatcmpl-{}", uuid_simple());
 
     if stream_mode {
         let id = completion_id.clone();
-        let token_stream = UnboundedReceiverStream::new(rx);
-        let event_stream = token_stream.map(move |token| {
+        let segment_stream = UnboundedReceiverStream::new(thinking::split_stream(rx, think));
+        let event_stream = segment_stream.map(move |segment| {
+            let (content, reasoning_content) = match segment {
+                Segment::Content(text) => (Some(text), None),
+                Segment::Thinking(text) => (None, Some(text)),
+            };
             let chunk = OpenAiChatChunk {
                 id: id.clone(),
                 object: "chat.completion.chunk".to_string(),
@@ -239,10 +341,12 @@ pub async fn openai_chat_completions(
                     index: 0,
                     delta: OpenAiDelta {
                         role: None,
-                        content: Some(token),
+                        content,
+                        reasoning_content,
                     },
                     finish_reason: None,
                 }],
+                context: None,
             };
             let data = serde_json::to_string(&chunk).unwrap_or_default();
             Ok::<Event, std::convert::Infallible>(Event::default().data(data))
@@ -250,6 +354,7 @@ pub async fn openai_chat_completions(
 
         let done_id = completion_id.clone();
         let done_model = request.model.clone();
+        let done_report = report.clone();
         let done_event = stream::once(async move {
             let chunk = OpenAiChatChunk {
                 id: done_id,
@@ -261,9 +366,11 @@ pub async fn openai_chat_completions(
                     delta: OpenAiDelta {
                         role: None,
                         content: None,
+                        reasoning_content: None,
                     },
                     finish_reason: Some("stop".to_string()),
                 }],
+                context: Some(done_report),
             };
             let data = serde_json::to_string(&chunk).unwrap_or_default();
             Ok::<Event, std::convert::Infallible>(Event::default().data(data))
@@ -282,6 +389,7 @@ pub async fn openai_chat_completions(
         while let Some(token) = rx.recv().await {
             response_text.push_str(&token);
         }
+        let (thinking, content) = thinking::split(&response_text);
         let response = OpenAiChatResponse {
             id: completion_id,
             object: "chat.completion".to_string(),
@@ -291,15 +399,17 @@ pub async fn openai_chat_completions(
                 index: 0,
                 message: OpenAiMessage {
                     role: "assistant".to_string(),
-                    content: OpenAiContent::Text(response_text),
+                    content: OpenAiContent::Text(content),
+                    reasoning_content: (think && !thinking.is_empty()).then_some(thinking),
                 },
                 finish_reason: "stop".to_string(),
             }],
             usage: OpenAiUsage {
-                prompt_tokens: 0,
+                prompt_tokens: report.prompt_tokens as u32,
                 completion_tokens: 0,
-                total_tokens: 0,
+                total_tokens: report.prompt_tokens as u32,
             },
+            context: Some(report),
         };
         Ok(Json(response).into_response())
     }
@@ -310,15 +420,7 @@ pub async fn openai_chat_completions(
 // ---------------------------------------------------------------------------
 
 fn build_ollama_messages(messages: &[ChatCompletionRequestMessage]) -> Vec<String> {
-    messages
-        .iter()
-        .map(|m| match m.role {
-            Role::System => format!("<|System|>: {}", m.content),
-            Role::User => format!("<|User|>: {}", m.content),
-            Role::Assistant => format!("<|Assistant|>: {}", m.content),
-            _ => m.content.clone(),
-        })
-        .collect()
+    messages.iter().map(context::render_message).collect()
 }
 
 /// Tiny pseudo-UUID using the current timestamp nanos (no uuid crate needed).
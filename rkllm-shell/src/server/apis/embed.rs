@@ -3,6 +3,8 @@ use std::time::Duration;
 
 use crate::server::{
     api_models::{EmbedRequest, EmbedResponse},
+    apis::{error::ApiError, validation::Validate},
+    request_log,
     AppState,
 };
 
@@ -11,22 +13,24 @@ use crate::server::{
     path = "/api/embed",
     request_body = EmbedRequest,
     responses(
-        (status = 200, description = "Embedding response", body = EmbedResponse)
+        (status = 200, description = "Embedding response", body = EmbedResponse),
+        (status = 400, description = "Invalid request")
     ),
     tag = "rkllm"
 )]
-#[allow(unused_variables)]
 pub async fn generate_embeddings(
     State(state): State<AppState>,
     Json(request): Json<EmbedRequest>,
-) -> Json<EmbedResponse> {
+) -> Result<Json<EmbedResponse>, ApiError> {
+    request_log::record_model(&request.model);
+    request.validate(&state.config.limits)?;
     // RKLLM does not currently expose an embedding API.
     // Return an empty embedding so the endpoint is functional without panicking.
-    Json(EmbedResponse {
+    Ok(Json(EmbedResponse {
         model: request.model,
         embeddings: vec![],
         total_duration: Duration::ZERO,
         load_duration: Duration::ZERO,
         prompt_eval_count: 0,
-    })
+    }))
 }
\ No newline at end of file
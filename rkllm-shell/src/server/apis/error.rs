@@ -14,9 +14,18 @@ pub enum ApiError {
     
     #[error("Authentication failed: {0}")]
     AuthenticationError(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
     
     #[error("Model not found: {0}")]
     ModelNotFound(String),
+
+    #[error("Model in use: {0}")]
+    ModelInUse(String),
+
+    #[error("Context length exceeded: {0}")]
+    ContextLengthExceeded(String),
     
     #[error("Rate limit exceeded: {0}")]
     RateLimitExceeded(String),
@@ -54,11 +63,26 @@ impl IntoResponse for ApiError {
                         "authentication_error", 
                         msg.clone(),
                     ),
+            ApiError::PermissionDenied(msg) => (
+                        StatusCode::FORBIDDEN,
+                        "permission_error",
+                        msg.clone(),
+                    ),
             ApiError::ModelNotFound(msg) => (
                         StatusCode::NOT_FOUND,
                         "model_not_found",
                         msg.clone(), 
                     ),
+            ApiError::ModelInUse(msg) => (
+                        StatusCode::CONFLICT,
+                        "model_in_use",
+                        msg.clone(),
+                    ),
+            ApiError::ContextLengthExceeded(msg) => (
+                        StatusCode::BAD_REQUEST,
+                        "invalid_request_error",
+                        msg.clone(),
+                    ),
             ApiError::RateLimitExceeded(msg) => (
                         StatusCode::TOO_MANY_REQUESTS,
                         "rate_limit_exceeded",
@@ -80,7 +104,10 @@ impl IntoResponse for ApiError {
             error: ErrorDetails {
                 message,
                 error_type: error_type.to_string(),
-                code: None,
+                code: match &self {
+                    ApiError::ContextLengthExceeded(_) => Some("context_length_exceeded".to_string()),
+                    _ => None,
+                },
             },
         };
 
@@ -92,4 +119,23 @@ impl From<hf_hub::api::sync::ApiError> for ApiError {
     fn from(err: hf_hub::api::sync::ApiError) -> Self {
         ApiError::InternalError(err.to_string())
     }
-}
\ No newline at end of file
+}
+
+impl From<crate::server::store::StoreError> for ApiError {
+    fn from(err: crate::server::store::StoreError) -> Self {
+        crate::server::runtime_trait::RuntimeError::from(err).into()
+    }
+}
+
+impl From<crate::server::runtime_trait::RuntimeError> for ApiError {
+    fn from(err: crate::server::runtime_trait::RuntimeError) -> Self {
+        use crate::server::runtime_trait::RuntimeError;
+        match err {
+            RuntimeError::ModelNotFound(model) => ApiError::ModelNotFound(model),
+            RuntimeError::AmbiguousModel(msg) => ApiError::InvalidRequest(msg),
+            RuntimeError::TokenizerUnavailable(msg) => ApiError::InvalidRequest(msg),
+            RuntimeError::VisionUnavailable(msg) => ApiError::InvalidRequest(msg),
+            other => ApiError::InternalError(other.to_string()),
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::State,
+    extract::{Extension, State},
     response::{
         sse::{Event, KeepAlive, Sse},
         IntoResponse, Response,
@@ -7,12 +7,19 @@ use axum::{
     Json,
 };
 use chrono::Utc;
+use std::time::Instant;
 use futures::stream::{self, StreamExt};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 use crate::server::{
     api_models::{GenerateRequest, GenerateResponse},
+    context::{self, ContextBudget},
+    modelfile,
     rkllm_runtime::CompletionRequest,
+    apis::{error::ApiError, validation::Validate},
+    rate_limit::{self, TokenUsage},
+    request_log,
+    thinking::{self, Segment},
     AppState,
 };
 
@@ -21,19 +28,27 @@ use crate::server::{
     path = "/api/generate",
     request_body = GenerateRequest,
     responses(
-        (status = 200, description = "Completion response", body = GenerateResponse)
+        (status = 200, description = "Completion response", body = GenerateResponse),
+        (status = 400, description = "Invalid request")
     ),
     tag = "rkllm"
 )]
 pub async fn generate_completion(
     State(state): State<AppState>,
-    Json(request): Json<GenerateRequest>,
+    usage: Option<Extension<TokenUsage>>,
+    Json(mut request): Json<GenerateRequest>,
 ) -> axum::response::Result<Response> {
+    let started = Instant::now();
+    request_log::record_model(&request.model);
+    let stored = state.runtime.resolve(&request.model).await.map_err(ApiError::from)?;
+    // Validated with the model's parameters in, as they reach the runtime too.
+    modelfile::apply_generate_defaults(&stored, &mut request);
+    request.validate(&state.config.limits)?;
     let model = state
         .runtime
         .get_request_model(&CompletionRequest::Generate(request.clone()))
         .await
-        .map_err(|e| axum::response::ErrorResponse::from(e))?;
+        .map_err(ApiError::from)?;
 
     // Build the prompt string, optionally prepending a system message.
     let mut messages = Vec::new();
@@ -42,17 +57,33 @@ pub async fn generate_completion(
     }
     messages.push(request.prompt.clone());
 
-    let rx = model.run_inference(messages);
+    // A bare prompt has no turns to drop, so overflow is always an error.
+    let tokenizer = state.runtime.tokenizers().get(model.model_path(), model.tokenizer_path()).await;
+    let budget = ContextBudget::new(&request.options, &state.config.context);
+    context::check_prompt(&*tokenizer, &messages.join("\n"), budget)?;
+
+    let think = request.think;
+    let rx = state.metrics.instrument(
+        "/api/generate",
+        started,
+        model.run_inference_with_thinking(messages, think),
+    );
+    let rx = rate_limit::track_usage(usage.as_deref(), rx);
     let model_name = request.model.clone();
     let stream_mode = request.stream;
 
     if stream_mode {
-        let token_stream = UnboundedReceiverStream::new(rx);
-        let event_stream = token_stream.map(move |token| {
+        let segment_stream = UnboundedReceiverStream::new(thinking::split_stream(rx, think));
+        let event_stream = segment_stream.map(move |segment| {
+            let (response, thinking) = match segment {
+                Segment::Content(text) => (text, None),
+                Segment::Thinking(text) => (String::new(), Some(text)),
+            };
             let chunk = GenerateResponse {
                 model: model_name.clone(),
                 created_at: Utc::now(),
-                response: token,
+                response,
+                thinking,
                 done: false,
                 context: None,
                 total_duration: None,
@@ -72,6 +103,7 @@ pub async fn generate_completion(
                 model: done_model,
                 created_at: Utc::now(),
                 response: String::new(),
+                thinking: None,
                 done: true,
                 context: None,
                 total_duration: Some(0),
@@ -93,10 +125,12 @@ pub async fn generate_completion(
         while let Some(token) = rx.recv().await {
             response_text.push_str(&token);
         }
+        let (thinking, response_text) = thinking::split(&response_text);
         let response = GenerateResponse {
             model: request.model.clone(),
             created_at: Utc::now(),
             response: response_text,
+            thinking: (think && !thinking.is_empty()).then_some(thinking),
             done: true,
             context: None,
             total_duration: Some(0),
@@ -0,0 +1,183 @@
+use std::net::SocketAddr;
+use std::path::Path;
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::server::{
+    apis::error::ApiError, auth::{self, AuthenticatedKey}, preload::PreloadStatus, pulls::PullProgress,
+    runtime_trait::ModelRuntime, AppState,
+};
+use crate::AppInfo;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LivenessResponse {
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LoadedModelStatus {
+    pub key: String,
+    pub model_path: String,
+    pub loaded_at: DateTime<Utc>,
+    pub last_used: DateTime<Utc>,
+    pub keep_alive_remaining_secs: u64,
+    pub in_flight: usize,
+    pub queue_depth: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatusResponse {
+    pub version: String,
+    pub uptime_secs: u64,
+    pub ready: bool,
+    pub models_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_free_bytes: Option<u64>,
+    pub loaded_models: Vec<LoadedModelStatus>,
+    pub preloads: Vec<PreloadStatus>,
+    pub pulls: Vec<PullProgress>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ShutdownResponse {
+    pub status: String,
+}
+
+/// Free space available to unprivileged users on the filesystem holding `path`.
+fn disk_free_bytes(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+// ---------------------------------------------------------------------------
+// GET /health/live
+// ---------------------------------------------------------------------------
+
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    responses(
+        (status = 200, description = "Server process is up", body = LivenessResponse)
+    ),
+    tag = "health"
+)]
+pub async fn liveness() -> Json<LivenessResponse> {
+    Json(LivenessResponse {
+        status: "ok".to_string(),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// GET /health/ready
+// ---------------------------------------------------------------------------
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    responses(
+        (status = 200, description = "Server is ready to serve requests", body = ReadinessResponse),
+        (status = 503, description = "Models are still being preloaded", body = ReadinessResponse)
+    ),
+    tag = "health"
+)]
+pub async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let ready = state.preloader.is_complete();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(ReadinessResponse { ready }))
+}
+
+// ---------------------------------------------------------------------------
+// GET /api/status
+// ---------------------------------------------------------------------------
+
+#[utoipa::path(
+    get,
+    path = "/api/status",
+    responses(
+        (status = 200, description = "Detailed server status", body = StatusResponse)
+    ),
+    tag = "health"
+)]
+pub async fn server_status(State(state): State<AppState>) -> Json<StatusResponse> {
+    let models_path = state.runtime.models_path().to_path_buf();
+    let loaded_models = state
+        .runtime
+        .list_loaded_models()
+        .await
+        .into_iter()
+        .map(|info| LoadedModelStatus {
+            keep_alive_remaining_secs: info.keep_alive_remaining().as_secs(),
+            loaded_at: info.loaded_at.into(),
+            last_used: info.last_used.into(),
+            in_flight: info.in_flight,
+            queue_depth: info.queue_depth,
+            key: info.key,
+            model_path: info.model_path,
+        })
+        .collect();
+
+    Json(StatusResponse {
+        version: AppInfo::default().version.to_string(),
+        uptime_secs: state.started_at.elapsed().as_secs(),
+        ready: state.preloader.is_complete(),
+        disk_free_bytes: disk_free_bytes(&models_path),
+        models_path: models_path.to_string_lossy().into_owned(),
+        loaded_models,
+        preloads: state.preloader.statuses(),
+        pulls: state.pulls.list(),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// POST /api/shutdown
+// ---------------------------------------------------------------------------
+
+#[utoipa::path(
+    post,
+    path = "/api/shutdown",
+    responses(
+        (status = 200, description = "The server is shutting down", body = ShutdownResponse),
+        (status = 403, description = "API keys are off and the request did not come from this machine")
+    ),
+    tag = "health"
+)]
+pub async fn shutdown(
+    State(state): State<AppState>,
+    key: Option<Extension<AuthenticatedKey>>,
+    peer: Option<Extension<ConnectInfo<SocketAddr>>>,
+) -> Result<Json<ShutdownResponse>, ApiError> {
+    // With keys off, only a client on this machine may stop the server.
+    if !auth::is_trusted(key.as_deref(), peer.as_deref()) {
+        return Err(ApiError::PermissionDenied(
+            "Shutdown without an API key is only allowed from this machine".to_string(),
+        ));
+    }
+    // In-flight requests finish; the listener stops taking new ones.
+    tracing::info!("shutdown requested");
+    state.shutdown.notify_one();
+    Ok(Json(ShutdownResponse {
+        status: "shutting down".to_string(),
+    }))
+}
@@ -0,0 +1,110 @@
+//! Tests for the health endpoints
+
+use crate::server::{rkllm_runtime::RkllmRuntime, AppState};
+use crate::server::test_helpers::test_config;
+use axum::{
+    extract::ConnectInfo,
+    http::StatusCode,
+    routing::{get, post},
+    Extension, Router,
+};
+use axum_test::TestServer;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn create_test_app_state() -> AppState {
+    let runtime = RkllmRuntime::new(PathBuf::from("./mock_models"));
+    AppState::new(runtime, Arc::new(test_config()))
+}
+
+#[tokio::test]
+async fn test_readiness_waits_for_preload() {
+    let state = create_test_app_state();
+    let preloader = state.preloader.clone();
+    let runtime = state.runtime.clone();
+
+    let app = Router::new()
+        .route("/health/ready", get(crate::server::apis::health::readiness))
+        .with_state(state);
+    let server = TestServer::new(app);
+
+    let response = server.get("/health/ready").await;
+    assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // Nothing to preload: the task completes right away.
+    preloader.spawn(runtime, vec![]).await.unwrap();
+
+    let response = server.get("/health/ready").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(preloader.statuses().is_empty());
+}
+
+#[tokio::test]
+async fn test_liveness() {
+    let app = Router::new()
+        .route("/health/live", get(crate::server::apis::health::liveness))
+        .with_state(create_test_app_state());
+    let server = TestServer::new(app);
+
+    let response = server.get("/health/live").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_server_status() {
+    let state = create_test_app_state();
+    let _pull = state.pulls.start("user/model").unwrap();
+
+    let app = Router::new()
+        .route("/api/status", get(crate::server::apis::health::server_status))
+        .with_state(state);
+    let server = TestServer::new(app);
+
+    let response = server.get("/api/status").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(body["ready"], false);
+    assert_eq!(body["loaded_models"].as_array().unwrap().len(), 0);
+    assert_eq!(body["pulls"][0]["name"], "user/model");
+}
+
+#[tokio::test]
+async fn test_shutdown_notifies_the_server() {
+    let state = create_test_app_state();
+    let shutdown = state.shutdown.clone();
+
+    let app = Router::new()
+        .route("/api/shutdown", post(crate::server::apis::health::shutdown))
+        .layer(Extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 50000)))))
+        .with_state(state);
+    let server = TestServer::new(app);
+
+    let response = server.post("/api/shutdown").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    // The permit is kept until the server waits for it.
+    tokio::time::timeout(std::time::Duration::from_secs(1), shutdown.notified())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_shutdown_from_another_machine_needs_a_key() {
+    let state = create_test_app_state();
+    let shutdown = state.shutdown.clone();
+
+    // No keys are configured, so no request is authenticated.
+    let app = Router::new()
+        .route("/api/shutdown", post(crate::server::apis::health::shutdown))
+        .layer(Extension(ConnectInfo(SocketAddr::from(([192, 168, 1, 20], 50000)))))
+        .with_state(state);
+    let server = TestServer::new(app);
+
+    let response = server.post("/api/shutdown").await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    assert!(tokio::time::timeout(std::time::Duration::from_millis(100), shutdown.notified())
+        .await
+        .is_err());
+}
@@ -0,0 +1,31 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+
+use crate::server::{apis::error::ApiError, runtime_trait::ModelRuntime, AppState};
+
+// ---------------------------------------------------------------------------
+// GET /metrics
+// ---------------------------------------------------------------------------
+
+/// Prometheus scrape endpoint. Gauges describing the loaded models are
+/// refreshed from the runtime on every scrape.
+pub async fn prometheus_metrics(State(state): State<AppState>) -> Response {
+    let models = state.runtime.list_loaded_models().await;
+    state.metrics.loaded_models.set(models.len() as i64);
+    state
+        .metrics
+        .queue_depth
+        .set(models.iter().map(|m| m.queue_depth).sum::<usize>() as i64);
+
+    match state.metrics.render() {
+        Ok(body) => (
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            body,
+        )
+            .into_response(),
+        Err(e) => ApiError::Internal(format!("Failed to render metrics: {}", e)).into_response(),
+    }
+}
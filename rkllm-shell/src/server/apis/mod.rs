@@ -4,6 +4,10 @@ pub mod generate;
 pub mod models;
 pub mod error;
 pub mod agent;
+pub mod health;
+pub mod metrics;
+pub mod validation;
+pub mod tokenize;
 
 #[cfg(test)]
 mod chat_test;
@@ -14,4 +18,10 @@ mod generate_test;
 #[cfg(test)]
 mod models_test;
 #[cfg(test)]
-mod agent_test;
\ No newline at end of file
+mod agent_test;
+#[cfg(test)]
+mod health_test;
+#[cfg(test)]
+mod validation_test;
+#[cfg(test)]
+mod tokenize_test;
\ No newline at end of file
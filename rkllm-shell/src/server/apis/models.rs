@@ -1,24 +1,47 @@
 #![allow(unused_variables)]
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    body::Body,
+    extract::{ConnectInfo, Path, State},
+    Extension,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
+use futures::StreamExt;
 use chrono::Utc;
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::io::Read;
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
 
 use crate::server::{
     api_models::{
-        DeleteRequest, ListModelResponse, ListResponse, ModelDetails, ProgressResponse,
-        PullRequest, ShowRequest, ShowResponse,
+        CopyRequest, CreateRequest, DeleteRequest, ListModelResponse, ListResponse, ModelDetails,
+        ProgressResponse, PruneRequest, PruneResponse, PullRequest, ShowRequest, ShowResponse,
+        UnloadRequest,
     },
-    apis::error::ApiError,
+    apis::{error::ApiError, validation::Validate},
+    auth::{self, AuthenticatedKey},
+    defaults::default_model_options,
+    hub::{self, HubClient},
+    modelfile::{self, Modelfile},
+    prune::{self, PruneTargets},
+    pull_spec::{self, PullSpec},
+    runtime_trait::RuntimeError,
+    store::{Layer, Manifest, ManifestBase, ModelName, ModelStore, IMPORT_SETTLE, PULLS},
+    metrics::Metrics,
+    pulls::{PullGuard, PullTracker},
+    request_log,
     AppState,
 };
 
+/// How often `/api/pull` reports download progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
 // ---------------------------------------------------------------------------
 // Quantization helpers
 // ---------------------------------------------------------------------------
@@ -41,7 +64,7 @@ fn detect_quantization(filename: &str) -> String {
 // SHA-256 digest
 // ---------------------------------------------------------------------------
 
-fn sha256_file(path: &std::path::Path) -> Result<String, std::io::Error> {
+pub fn sha256_file(path: &std::path::Path) -> Result<String, std::io::Error> {
     let mut file = fs::File::open(path)?;
     let mut hasher = Sha256::new();
     let mut buf = [0u8; 65536];
@@ -62,96 +85,110 @@ pub async fn sha256_file_async(path: std::path::PathBuf) -> Result<String, std::
 }
 
 // ---------------------------------------------------------------------------
-// Recursive .rkllm file collector
+// DELETE /api/delete
 // ---------------------------------------------------------------------------
 
-/// Recursively search for the model file whose name or directory contains the model name.
-fn find_model_file(models_dir: &PathBuf, model_name: &str) -> Option<PathBuf> {
-    let direct = models_dir.join(model_name);
-    if direct.is_file() {
-        return Some(direct);
-    }
-    // Check if model_name is a directory with an .rkllm file inside.
-    if direct.is_dir() {
-        if let Ok(entries) = fs::read_dir(&direct) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().and_then(|e| e.to_str()) == Some("rkllm") {
-                    return Some(path);
-                }
-            }
-        }
-    }
-    // Recursive search for matching name.
-    let mut found = None;
-    let _ = collect_rkllm_files(models_dir, &mut Vec::new())
-        .map(|_| ());
-    // Actually search properly
-    let mut all = Vec::new();
-    if collect_rkllm_files(models_dir, &mut all).is_ok() {
-        for path in all {
-            let file_stem = path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-            if file_stem.contains(&model_name.to_lowercase()) {
-                found = Some(path);
-                break;
-            }
+#[utoipa::path(
+    delete,
+    path = "/api/delete",
+    request_body = DeleteRequest,
+    responses(
+        (status = 200, description = "Delete model"),
+        (status = 409, description = "The model is loaded and `force` is not set; with `force` it is unloaded once running requests end")
+    ),
+    tag = "models"
+)]
+pub async fn delete_model(
+    State(state): State<AppState>,
+    Json(request): Json<DeleteRequest>,
+) -> Result<StatusCode, ApiError> {
+    // Only an exact name deletes; part of one could match the wrong model.
+    let store = state.runtime.store().clone();
+    let query = request.model.clone();
+    let name = tokio::task::spawn_blocking(move || store.resolve_exact(&query))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))??
+        .name;
+
+    if state.runtime.list_running_models().contains(&name) {
+        if !request.force {
+            return Err(ApiError::ModelInUse(format!(
+                "Model '{}' is loaded; stop it first or delete with force",
+                name
+            )));
         }
+        // Running inference keeps the model files mapped; wait for it to
+        // end before deleting them.
+        state.runtime.unload_and_wait(&name).await;
     }
-    found
-}
 
-fn collect_rkllm_files(dir: &PathBuf, files: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
-    if !dir.exists() {
-        return Ok(());
-    }
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_dir() {
-            collect_rkllm_files(&path, files)?;
-        } else if path.extension().and_then(|e| e.to_str()) == Some("rkllm") {
-            files.push(path);
-        }
-    }
-    Ok(())
+    let store = state.runtime.store().clone();
+    let freed = tokio::task::spawn_blocking(move || store.remove(&name))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))??;
+    tracing::info!(freed_bytes = freed, "deleted model");
+
+    Ok(StatusCode::OK)
 }
 
 // ---------------------------------------------------------------------------
-// DELETE /api/delete
+// POST /api/copy
 // ---------------------------------------------------------------------------
 
 #[utoipa::path(
-    delete,
-    path = "/v1/models/{model}",
-    request_body = DeleteRequest,
+    post,
+    path = "/api/copy",
+    request_body = CopyRequest,
     responses(
-        (status = 200, description = "Delete model")
+        (status = 200, description = "Model copied"),
+        (status = 404, description = "Source model not found")
     ),
     tag = "models"
 )]
-pub async fn delete_model(
+pub async fn copy_model(
     State(state): State<AppState>,
-    Json(request): Json<DeleteRequest>,
+    Json(request): Json<CopyRequest>,
 ) -> Result<StatusCode, ApiError> {
-    let models_dir = state
-        .config
-        .models_path
-        .clone()
-        .unwrap_or_else(|| PathBuf::from("./data"));
+    request_log::record_model(&request.source);
+    let destination = ModelName::parse(&request.destination)?;
+    // Like deleting, copying takes an exact name: a guess could alias the wrong model.
+    let store = state.runtime.store().clone();
+    let source = request.source.clone();
+    let target = destination.clone();
+    let source = tokio::task::spawn_blocking(move || store.copy(&source, &target))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))??;
+    tracing::info!(%source, %destination, "copied model");
 
-    // Use find_model_file to support subdirectory models.
-    let model_path = find_model_file(&models_dir, &request.model)
-        .ok_or_else(|| ApiError::ModelNotFound(format!(
-            "Model '{}' not found",
-            request.model
-        )))?;
+    Ok(StatusCode::OK)
+}
+
+// ---------------------------------------------------------------------------
+// POST /api/unload
+// ---------------------------------------------------------------------------
 
-    fs::remove_file(&model_path)
-        .map_err(|e| ApiError::Internal(format!("Failed to delete model: {}", e)))?;
+#[utoipa::path(
+    post,
+    path = "/api/unload",
+    request_body = UnloadRequest,
+    responses(
+        (status = 200, description = "Model unloaded"),
+        (status = 404, description = "The model is not loaded")
+    ),
+    tag = "models"
+)]
+pub async fn unload_model(
+    State(state): State<AppState>,
+    Json(request): Json<UnloadRequest>,
+) -> Result<StatusCode, ApiError> {
+    request_log::record_model(&request.model);
+    let name = state.runtime.resolve(&request.model).await?.name;
+    // Every instance goes, whatever options it was loaded with.
+    let instances = state.runtime.unload(&name);
+    if instances == 0 {
+        return Err(ApiError::ModelNotFound(format!("Model '{}' is not loaded", name)));
+    }
+    tracing::info!(%name, instances, "unloaded model");
 
     Ok(StatusCode::OK)
 }
@@ -172,25 +209,13 @@ pub async fn retrieve_model(
     State(state): State<AppState>,
     Path(model_name): Path<String>,
 ) -> Result<Json<ShowResponse>, ApiError> {
-    use crate::server::api_models::ModelOptions;
     use crate::server::defaults::*;
 
     let request = ShowRequest {
         model: model_name,
         system: Some(String::new()),
         verbose: Some(false),
-        options: ModelOptions {
-            num_ctx: default_context_window(),
-            repeat_last_n: default_repeat_last_n(),
-            repeat_penalty: default_repeat_penalty(),
-            temperature: default_temperature(),
-            seed: default_seed(),
-            stop: default_stop(),
-            num_predict: default_num_predict(),
-            top_k: default_top_k(),
-            top_p: default_top_p(),
-            min_p: default_min_p(),
-        },
+        options: default_model_options(),
     };
     show_model_info(State(state), Json(request)).await
 }
@@ -210,72 +235,51 @@ pub async fn retrieve_model(
 pub async fn list_local_models(
     State(state): State<AppState>,
 ) -> Result<Json<ListResponse>, ApiError> {
-    let models_dir = state
-        .config
-        .models_path
-        .clone()
-        .unwrap_or_else(|| PathBuf::from("./data"));
-
-    if !models_dir.exists() {
-        fs::create_dir_all(&models_dir)
-            .map_err(|e| ApiError::Internal(format!("Failed to create models directory: {}", e)))?;
-    }
-
-    let mut models = Vec::new();
+    // Files dropped into `models_path` show up once imported.
+    let store = state.runtime.store().clone();
+    let stored = tokio::task::spawn_blocking(move || {
+        store.import_loose(IMPORT_SETTLE)?;
+        store.list()
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.to_string()))??;
+    tracing::debug!(count = stored.len(), "listed stored models");
 
-    // Recursively collect all .rkllm files.
-    let mut rkllm_files = Vec::new();
-    collect_rkllm_files(&models_dir, &mut rkllm_files)
-        .map_err(|e| ApiError::Internal(format!("Failed to scan for models: {}", e)))?;
+    let models = stored
+        .into_iter()
+        .map(|(name, manifest)| list_entry(name.to_string(), Some(&manifest), manifest.created_at))
+        .collect();
 
-    // Use the digest cache from AppState for fast repeated requests
-    let digest_cache = &state.digest_cache;
-    crate::terminal::message::write::info(format!("Found {} model files", rkllm_files.len())).ok();
-    
-    for path in &rkllm_files {
-        crate::terminal::message::write::info(format!("Processing model file: {:?}", path)).ok();
-        let metadata = fs::metadata(path)
-            .map_err(|e| ApiError::Internal(format!("Failed to get file metadata: {}", e)))?;
+    Ok(Json(ListResponse { models }))
+}
 
-        let file_name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-
-        let modified_at = metadata
-            .modified()
-            .ok()
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .and_then(|d| chrono::DateTime::from_timestamp(d.as_secs() as i64, 0))
-            .unwrap_or_else(Utc::now);
-
-        // Get digest from cache (computes on first request for this file, then cached)
-        // Note: We don't await this to avoid blocking on large files; return empty digest initially
-        let digest = String::new();
-        let quantization = detect_quantization(&file_name);
-
-        models.push(ListModelResponse {
-            name: file_name.clone(),
-            model: file_name.clone(),
-            modified_at,
-            size: metadata.len() as i64,
-            digest,
-            details: ModelDetails {
-                parent_model: String::new(),
-                format: "rkllm".to_string(),
-                family: "rkllm".to_string(),
-                families: vec!["rkllm".to_string()],
-                parameter_size: format!(
-                    "{:.2} GB",
-                    metadata.len() as f64 / 1_073_741_824.0
-                ),
-                quantization_level: quantization,
-            },
-        });
+/// A `ListModelResponse` for a stored model, or for a name alone when its
+/// manifest is gone.
+fn list_entry(name: String, manifest: Option<&Manifest>, modified_at: chrono::DateTime<Utc>) -> ListModelResponse {
+    let (size, digest, quantization, parameter_size) = match manifest {
+        Some(manifest) => (
+            manifest.size() as i64,
+            manifest.model.digest.clone(),
+            detect_quantization(&manifest.model.file),
+            format!("{:.2} GB", manifest.model.size as f64 / 1_073_741_824.0),
+        ),
+        None => (0, String::new(), detect_quantization(&name), "unknown".to_string()),
+    };
+    ListModelResponse {
+        name: name.clone(),
+        model: name,
+        modified_at,
+        size,
+        digest,
+        details: ModelDetails {
+            parent_model: String::new(),
+            format: "rkllm".to_string(),
+            family: "rkllm".to_string(),
+            families: vec!["rkllm".to_string()],
+            parameter_size,
+            quantization_level: quantization,
+        },
     }
-
-    Ok(Json(ListResponse { models }))
 }
 
 // ---------------------------------------------------------------------------
@@ -294,26 +298,13 @@ pub async fn list_running_models(
     State(state): State<AppState>,
 ) -> Result<Json<ListResponse>, ApiError> {
     let running = state.runtime.list_running_models();
+    let store = state.runtime.store();
 
     let models = running
         .into_iter()
-        .map(|model_key| {
-            let quantization = detect_quantization(&model_key);
-            ListModelResponse {
-                name: model_key.clone(),
-                model: model_key,
-                modified_at: Utc::now(),
-                size: 0,
-                digest: String::new(),
-                details: ModelDetails {
-                    parent_model: String::new(),
-                    format: "rkllm".to_string(),
-                    family: "rkllm".to_string(),
-                    families: vec!["rkllm".to_string()],
-                    parameter_size: "unknown".to_string(),
-                    quantization_level: quantization,
-                },
-            }
+        .map(|name| {
+            let manifest = store.read(&name).ok().flatten();
+            list_entry(name.to_string(), manifest.as_ref(), Utc::now())
         })
         .collect();
 
@@ -337,51 +328,54 @@ pub async fn show_model_info(
     State(state): State<AppState>,
     Json(request): Json<ShowRequest>,
 ) -> Result<Json<ShowResponse>, ApiError> {
-    let models_dir = state
-        .config
-        .models_path
-        .clone()
-        .unwrap_or_else(|| PathBuf::from("./data"));
-
-    // Use find_model_file to support subdirectory models.
-    let model_path = find_model_file(&models_dir, &request.model)
-        .ok_or_else(|| ApiError::ModelNotFound(format!(
-            "Model '{}' not found",
-            request.model
-        )))?;
-
-    let metadata = fs::metadata(&model_path)
-        .map_err(|e| ApiError::Internal(format!("Failed to get file metadata: {}", e)))?;
+    let model = state.runtime.resolve(&request.model).await?;
+    let manifest = &model.manifest;
 
-    let modified_at = metadata
-        .modified()
-        .ok()
-        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-        .and_then(|d| chrono::DateTime::from_timestamp(d.as_secs() as i64, 0))
-        .unwrap_or_else(Utc::now);
-
-    let size_gb = metadata.len() as f64 / 1_073_741_824.0;
-    let quantization = detect_quantization(&request.model);
+    let size_gb = manifest.model.size as f64 / 1_073_741_824.0;
+    let quantization = detect_quantization(&manifest.model.file);
+    let file = |layer: Option<&Layer>| layer.map_or("none".to_string(), |l| l.file.clone());
+    let adapters: Vec<&str> = manifest.adapters.iter().map(|l| l.file.as_str()).collect();
 
     let details = format!(
-        "Model: {}\nSize: {:.2} GB\nFormat: RKLLM\nQuantization: {}",
-        request.model, size_gb, quantization
+        "Model: {}\nFile: {}\nDigest: {}\nSize: {:.2} GB\nFormat: RKLLM\nQuantization: {}\nTokenizer: {}\nVision encoder: {}\nAdapters: {}",
+        model.name,
+        manifest.model.file,
+        manifest.model.digest,
+        size_gb,
+        quantization,
+        file(manifest.tokenizer.as_ref()),
+        file(manifest.vision_encoder.as_ref()),
+        if adapters.is_empty() { "none".to_string() } else { adapters.join(", ") }
     );
-
-    Ok(Json(ShowResponse {
-        license: "Unknown".to_string(),
-        modelfile: format!("FROM {}", request.model),
-        parameters: format!(
+    let modified_at = manifest.created_at;
+    let modelfile = Modelfile::from_model(&model);
+    // The model's own parameters, else the options this request would run with.
+    let parameters = if modelfile.parameters.is_empty() {
+        format!(
             "num_ctx: {}\ntemperature: {}\ntop_p: {}\ntop_k: {}\nrepeat_penalty: {}",
             request.options.num_ctx,
             request.options.temperature,
             request.options.top_p,
             request.options.top_k,
             request.options.repeat_penalty
-        ),
-        template: "<|System|>\n{{ .System }}\n<|User|>\n{{ .Prompt }}\n<|Assistant|>"
-            .to_string(),
-        system: request.system.unwrap_or_default(),
+        )
+    } else {
+        let lines: Vec<String> = modelfile.parameters.iter().map(|(key, value)| format!("{} {}", key, value)).collect();
+        lines.join("\n")
+    };
+
+    Ok(Json(ShowResponse {
+        license: if manifest.license.is_empty() {
+            "Unknown".to_string()
+        } else {
+            manifest.license.join("\n\n")
+        },
+        modelfile: modelfile.to_string(),
+        parameters,
+        template: manifest.template.clone().unwrap_or_else(|| {
+            "<|System|>\n{{ .System }}\n<|User|>\n{{ .Prompt }}\n<|Assistant|>".to_string()
+        }),
+        system: request.system.or_else(|| manifest.system.clone()).unwrap_or_default(),
         details,
         modified_at,
     }))
@@ -396,77 +390,412 @@ pub async fn show_model_info(
     path = "/api/pull",
     request_body = PullRequest,
     responses(
-        (status = 200, description = "Pull model", body = ProgressResponse)
+        (status = 200, description = "Pull progress, one JSON object per line when streaming", body = ProgressResponse),
+        (status = 409, description = "A pull of this model is already running")
     ),
     tag = "models"
 )]
 pub async fn pull_model(
     State(state): State<AppState>,
     Json(model): Json<PullRequest>,
-) -> Result<Json<ProgressResponse>, ApiError> {
-    let models_dir = state
-        .config
-        .models_path
-        .clone()
-        .unwrap_or_else(|| PathBuf::from("./data"));
+) -> Result<Response, ApiError> {
+    request_log::record_model(&model.name);
+    let store = state.runtime.store().clone();
+
+    let spec = PullSpec::parse(&model.name)?;
+    let hub = registry_client(&state, &spec)?;
+    let soc = pull_spec::target_soc(&state.config.pull);
+
+    // The pull carries on when a streaming client goes away, as Ollama's does.
+    let pull = already_pulling(&state, &model.name)?;
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let pulls = state.pulls.clone();
+    let metrics = state.metrics.clone();
+    let name = model.name.clone();
+    let globs = model.files.clone();
+    tokio::spawn(async move {
+        let _pull = pull;
+        let target = PullTarget {
+            name: &name,
+            hub: &hub,
+            spec: &spec,
+            globs: &globs,
+            soc: soc.as_deref(),
+        };
+        let result = pull_files(&target, &store, &pulls, &metrics, &tx).await;
+        let _ = tx.send(result.map(|()| progress("success", None, None, None)));
+    });
+    progress_response(rx, model.stream.unwrap_or(true)).await
+}
 
-    // Ensure destination directory exists.
-    if !models_dir.exists() {
-        fs::create_dir_all(&models_dir)
-            .map_err(|e| ApiError::Internal(format!("Failed to create models directory: {}", e)))?;
+/// Registers a pull of `name`, unless one is already running.
+fn already_pulling(state: &AppState, name: &str) -> Result<PullGuard, ApiError> {
+    state
+        .pulls
+        .start(name)
+        .ok_or_else(|| ApiError::ModelInUse(format!("{} is already being pulled", name)))
+}
+
+/// The client for the registry a pull names, or the default one.
+fn registry_client(state: &AppState, spec: &PullSpec) -> Result<HubClient, ApiError> {
+    let pull_config = &state.config.pull;
+    let registry_name = spec.registry.as_deref().unwrap_or(&pull_config.default_registry);
+    let registry = pull_config.registry(registry_name).ok_or_else(|| {
+        let mut known: Vec<&str> = pull_config.registries.iter().map(|r| r.name.as_str()).collect();
+        known.extend(["hf", "modelscope"]);
+        ApiError::InvalidRequest(format!(
+            "name: unknown registry '{}', expected one of {}",
+            registry_name,
+            known.join(", ")
+        ))
+    })?;
+    HubClient::from_config(&registry)
+}
+
+/// Progress as NDJSON when streaming, otherwise only the final status.
+async fn progress_response(
+    mut rx: UnboundedReceiver<Result<ProgressResponse, ApiError>>,
+    stream: bool,
+) -> Result<Response, ApiError> {
+    if stream {
+        let lines = UnboundedReceiverStream::new(rx).map(|event| {
+            let line = match event {
+                Ok(progress) => serde_json::to_string(&progress),
+                Err(e) => serde_json::to_string(&serde_json::json!({ "error": e.to_string() })),
+            };
+            Ok::<_, std::convert::Infallible>(format!("{}\n", line.unwrap_or_default()))
+        });
+        return Ok((
+            [(header::CONTENT_TYPE, "application/x-ndjson")],
+            Body::from_stream(lines),
+        )
+            .into_response());
     }
 
-    let repo_id = model.name.clone();
-    let dest_dir = models_dir.clone();
+    let mut last = None;
+    while let Some(event) = rx.recv().await {
+        last = Some(event?);
+    }
+    let last = last.ok_or_else(|| ApiError::Internal("Ended without a status".to_string()))?;
+    Ok(Json(last).into_response())
+}
 
-    // Run the blocking HF download in a spawn_blocking thread.
-    let result = tokio::task::spawn_blocking(move || -> Result<(), ApiError> {
-        let api = hf_hub::api::sync::Api::new()?;
-        let repo = api.model(repo_id.clone());
-        let files = repo.info()?.siblings;
+fn progress(
+    status: impl Into<String>,
+    digest: Option<&str>,
+    total: Option<u64>,
+    completed: Option<u64>,
+) -> ProgressResponse {
+    ProgressResponse {
+        status: status.into(),
+        digest: digest.map(|sha| format!("sha256:{}", sha)),
+        total,
+        completed,
+    }
+}
 
-        let rkllm_files: Vec<_> = files
-            .iter()
-            .filter(|f| f.rfilename.ends_with(".rkllm"))
-            .collect();
+/// What a pull asked for, with the registry and the SoC to pick builds for
+struct PullTarget<'a> {
+    /// The name as requested, which the pull is tracked under
+    name: &'a str,
+    hub: &'a HubClient,
+    spec: &'a PullSpec,
+    globs: &'a [String],
+    soc: Option<&'a str>,
+}
 
-        if rkllm_files.is_empty() {
-            return Err(ApiError::ModelNotFound(format!(
-                "No .rkllm files found in repo {}",
-                repo_id
-            )));
-        }
+/// Downloads the files `target` selects into a staging directory and imports
+/// them into `store` under the pull's model name, reporting on `tx`.
+async fn pull_files(
+    target: &PullTarget<'_>,
+    store: &ModelStore,
+    pulls: &PullTracker,
+    metrics: &Metrics,
+    tx: &UnboundedSender<Result<ProgressResponse, ApiError>>,
+) -> Result<(), ApiError> {
+    let _ = tx.send(Ok(progress("pulling manifest", None, None, None)));
+    let (spec, revision) = (target.spec, target.spec.revision());
+    let hub = target.hub;
+    let listing = hub.list_files(&spec.repo, revision).await?;
+    let files = pull_spec::select_files(&listing, spec, target.globs, target.soc)?;
+
+    // Loose-file imports skip hidden directories, so a pull is not imported half done.
+    let repo_dir = store.root().join(PULLS).join(spec.dir_name());
+    tokio::fs::create_dir_all(&repo_dir)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to create {}: {}", repo_dir.display(), e)))?;
 
-        for file in &rkllm_files {
-            // hf_hub downloads to a local cache; copy from cache to our models_dir.
-            let cached_path = repo.get(&file.rfilename)?;
-            let filename = std::path::Path::new(&file.rfilename)
-                .file_name()
-                .unwrap_or_else(|| std::ffi::OsStr::new(&file.rfilename));
-            let dest = dest_dir.join(filename);
-            if cached_path != dest {
-                fs::copy(&cached_path, &dest).map_err(|e| {
-                    ApiError::Internal(format!(
-                        "Failed to copy {} to {}: {}",
-                        cached_path.display(),
-                        dest.display(),
-                        e
-                    ))
-                })?;
+    for file in files {
+        let filename = std::path::Path::new(&file.rfilename)
+            .file_name()
+            .unwrap_or_else(|| std::ffi::OsStr::new(&file.rfilename));
+        let dest = repo_dir.join(filename);
+        let status = format!("downloading {}", file.rfilename);
+        let digest = file.sha256();
+
+        // Files are only moved into place once verified, so a complete one is current.
+        let existing = tokio::fs::metadata(&dest).await.ok().map(|m| m.len());
+        if existing.is_some() && existing == file.total() {
+            let _ = tx.send(Ok(progress(status, digest, existing, existing)));
+            continue;
+        }
+        // A file the store already holds is linked instead of downloaded again.
+        if let (Some(sha), Some(total)) = (digest, file.total()) {
+            let (store, dest) = (store.clone(), dest.clone());
+            let blob = format!("sha256:{}", sha);
+            let linked = tokio::task::spawn_blocking(move || store.link_blob(&blob, total, &dest))
+                .await
+                .map_err(|e| ApiError::Internal(e.to_string()))?
+                .map_err(|e| ApiError::Internal(format!("Failed to link {}: {}", file.rfilename, e)))?;
+            if linked {
+                let _ = tx.send(Ok(progress(status, digest, Some(total), Some(total))));
+                continue;
             }
-            println!("Saved {} to {}", file.rfilename, dest.display());
         }
-        Ok(())
+        if let Some(placement) = hub.reuse_cached(&spec.repo, file, &dest).await? {
+            let status = format!("reusing {} from the Hugging Face cache", file.rfilename);
+            let _ = tx.send(Ok(progress(status, digest, file.total(), file.total())));
+            tracing::info!(file = %file.rfilename, dest = %dest.display(), ?placement, "reused cached model file");
+            continue;
+        }
+
+        // Chunks arrive every few kilobytes; report a few times a second.
+        let mut last_sent: Option<Instant> = None;
+        let transferred = hub
+            .download(file, &dest, |completed, total| {
+                let done = total == Some(completed);
+                if done || last_sent.map_or(true, |at| at.elapsed() >= PROGRESS_INTERVAL) {
+                    last_sent = Some(Instant::now());
+                    pulls.update(target.name, status.clone(), total, Some(completed));
+                    let _ = tx.send(Ok(progress(status.clone(), digest, total, Some(completed))));
+                }
+            })
+            .await?;
+        metrics.pull_bytes.inc_by(transferred);
+
+        if digest.is_some() {
+            pulls.update(target.name, "verifying sha256 digest", None, None);
+            let _ = tx.send(Ok(progress("verifying sha256 digest", digest, None, None)));
+        }
+        hub.finish(&dest, digest).await?;
+        tracing::info!(file = %file.rfilename, dest = %dest.display(), "saved model file");
+    }
+
+    let _ = tx.send(Ok(progress("writing manifest", None, None, None)));
+    let (store, name) = (store.clone(), spec.model_name());
+    let imported = tokio::task::spawn_blocking(move || store.import_dir(&repo_dir, Some(name)))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))??;
+    tracing::info!(models = ?imported, "stored pulled model");
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// POST /api/create
+// ---------------------------------------------------------------------------
+
+#[utoipa::path(
+    post,
+    path = "/api/create",
+    request_body = CreateRequest,
+    responses(
+        (status = 200, description = "Create progress, one JSON object per line when streaming", body = ProgressResponse),
+        (status = 400, description = "The Modelfile uses TEMPLATE or ADAPTER"),
+        (status = 403, description = "`FROM` names a path outside the models directory, without an admin key or, with keys off, from another machine")
+    ),
+    tag = "models"
+)]
+pub async fn create_model(
+    State(state): State<AppState>,
+    key: Option<Extension<AuthenticatedKey>>,
+    peer: Option<Extension<ConnectInfo<SocketAddr>>>,
+    Json(request): Json<CreateRequest>,
+) -> Result<Response, ApiError> {
+    request_log::record_model(&request.model);
+    let name = ModelName::parse(&request.model)?;
+    let modelfile = Modelfile::parse(&request.modelfile)?;
+    if modelfile.template.is_some() {
+        return Err(ApiError::InvalidRequest(
+            "modelfile: TEMPLATE is not supported; the model's own chat template is used".to_string(),
+        ));
+    }
+    if !modelfile.adapters.is_empty() {
+        return Err(ApiError::InvalidRequest("modelfile: ADAPTER is not supported yet".to_string()));
+    }
+    // A model's parameters reach the runtime like request options do.
+    let mut options = default_model_options();
+    modelfile::apply_parameters(&modelfile.parameter_map()?, &mut options);
+    options.validate(&state.config.limits)?;
+    let trusted = auth::is_trusted(key.as_deref(), peer.as_deref());
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let result = create_from(&state, &name, &modelfile, trusted, &tx).await;
+        let _ = tx.send(result.map(|()| progress("success", None, None, None)));
+    });
+    progress_response(rx, request.stream.unwrap_or(true)).await
+}
+
+/// Builds the manifest `modelfile` describes on top of its `FROM` and
+/// stores it as `name`.
+async fn create_from(
+    state: &AppState,
+    name: &ModelName,
+    modelfile: &Modelfile,
+    trusted: bool,
+    tx: &UnboundedSender<Result<ProgressResponse, ApiError>>,
+) -> Result<(), ApiError> {
+    let _ = tx.send(Ok(progress("parsing modelfile", None, None, None)));
+    let parameters = modelfile.parameter_map()?;
+    let base = base_manifest(state, &modelfile.from, trusted, tx).await?;
+
+    let _ = tx.send(Ok(progress("writing manifest", None, None, None)));
+    let (store, name, modelfile) = (state.runtime.store().clone(), name.clone(), modelfile.clone());
+    tokio::task::spawn_blocking(move || {
+        // What the Modelfile sets replaces what the base model had.
+        store.create(&name, base, |manifest| {
+            manifest.parameters.extend(parameters);
+            if modelfile.system.is_some() {
+                manifest.system = modelfile.system;
+            }
+            if !modelfile.messages.is_empty() {
+                manifest.messages = modelfile.messages;
+            }
+            if !modelfile.license.is_empty() {
+                manifest.license = modelfile.license;
+            }
+            manifest.created_at = Utc::now();
+        })
     })
     .await
-    .map_err(|e| ApiError::Internal(format!("Task panic: {}", e)))?;
+    .map_err(|e| ApiError::Internal(e.to_string()))??;
+    Ok(())
+}
 
-    result?;
+fn looks_like_path(from: &str) -> bool {
+    from.starts_with(['.', '/', '~']) || from.ends_with(".rkllm")
+}
+
+/// Whether `path` lies inside the store's directory
+fn inside_store(store: &ModelStore, path: &std::path::Path) -> std::io::Result<bool> {
+    Ok(path.canonicalize()?.starts_with(store.root().canonicalize()?))
+}
+
+/// What a `FROM` names: a model file or a directory holding one, to copy
+/// into the store; a stored model; or a repository, pulled first.
+async fn base_manifest(
+    state: &AppState,
+    from: &str,
+    trusted: bool,
+    tx: &UnboundedSender<Result<ProgressResponse, ApiError>>,
+) -> Result<ManifestBase, ApiError> {
+    let store = state.runtime.store().clone();
+    let path = PathBuf::from(from);
+    if path.exists() {
+        let (root, target) = (store.clone(), path.clone());
+        let inside = tokio::task::spawn_blocking(move || inside_store(&root, &target))
+            .await
+            .map_err(|e| ApiError::Internal(e.to_string()))?
+            .map_err(|e| ApiError::Internal(format!("Failed to resolve {}: {}", from, e)))?;
+        if !trusted && !inside {
+            return Err(ApiError::PermissionDenied(format!(
+                "modelfile: FROM {} is outside the models directory; paths elsewhere need \
+                 an API key with the model_admin scope, or without keys a client on this machine",
+                from
+            )));
+        }
+        let _ = tx.send(Ok(progress(format!("copying model file {}", from), None, None, None)));
+        return Ok(ManifestBase::File(path));
+    }
+
+    let spec = match state.runtime.resolve(from).await {
+        Ok(model) => {
+            let _ = tx.send(Ok(progress(format!("using model {}", model.name), None, None, None)));
+            return Ok(ManifestBase::Manifest(model.manifest));
+        }
+        // Only something shaped like `owner/repo` or `registry://name` is pulled.
+        Err(RuntimeError::ModelNotFound(_)) if from.contains('/') && !looks_like_path(from) => {
+            PullSpec::parse(from)?
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let hub = registry_client(state, &spec)?;
+    let soc = pull_spec::target_soc(&state.config.pull);
+    let _pull = already_pulling(state, from)?;
+    let target = PullTarget {
+        name: from,
+        hub: &hub,
+        spec: &spec,
+        globs: &[],
+        soc: soc.as_deref(),
+    };
+    pull_files(&target, &store, &state.pulls, &state.metrics, tx).await?;
+    let name = spec.model_name();
+    tokio::task::spawn_blocking(move || store.read(&name))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))??
+        .map(ManifestBase::Manifest)
+        .ok_or_else(|| ApiError::ModelNotFound(format!("Model '{}' not found after pulling it", from)))
+}
 
-    Ok(Json(ProgressResponse {
-        status: "completed".to_string(),
-        digest: None,
-        total: None,
-        completed: None,
+// ---------------------------------------------------------------------------
+// POST /api/prune
+// ---------------------------------------------------------------------------
+
+#[utoipa::path(
+    post,
+    path = "/api/prune",
+    request_body = PruneRequest,
+    responses(
+        (status = 200, description = "Files removed and bytes reclaimed", body = PruneResponse)
+    ),
+    tag = "models"
+)]
+pub async fn prune_models(
+    State(state): State<AppState>,
+    Json(request): Json<PruneRequest>,
+) -> Result<Json<PruneResponse>, ApiError> {
+    let models_dir = state
+        .config
+        .models_path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("./data"));
+
+    let mut partial_dirs = vec![models_dir];
+    partial_dirs.extend(state.config.pull.registries.iter().filter_map(|r| r.cache_dir.clone()));
+    // Pulls in progress keep their partial files.
+    let busy: Vec<String> = state
+        .pulls
+        .list()
+        .iter()
+        .filter_map(|pull| PullSpec::parse(&pull.name).ok())
+        .map(|spec| spec.dir_name())
+        .collect();
+    let pulls_dir = state.runtime.store().root().join(PULLS);
+
+    let dry_run = request.dry_run;
+    let report = tokio::task::spawn_blocking(move || {
+        let targets = PruneTargets {
+            partial_dirs,
+            hf_cache: hub::hf_cache_dir(),
+            hf_repos: prune::pulled_repos(&pulls_dir)?,
+            busy,
+        };
+        prune::prune(&targets, dry_run)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.to_string()))?
+    .map_err(|e| ApiError::Internal(format!("Failed to prune: {}", e)))?;
+    tracing::info!(
+        files = report.removed.len(),
+        reclaimed_bytes = report.reclaimed_bytes,
+        dry_run,
+        "pruned downloads"
+    );
+
+    Ok(Json(PruneResponse {
+        removed: report.removed.iter().map(|p| p.display().to_string()).collect(),
+        reclaimed_bytes: report.reclaimed_bytes,
+        dry_run,
     }))
-}
\ No newline at end of file
+}
@@ -1,10 +1,123 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn dummy_test() {
-        // Replace with real tests for models.rs
-        assert_eq!(2 + 2, 4);
+//! Tests for the model management endpoints
+
+use crate::server::test_helpers::test_config;
+use crate::server::{rkllm_runtime::RkllmRuntime, AppState};
+use axum::{extract::ConnectInfo, http::StatusCode, routing::post, Extension, Router};
+use axum_test::TestServer;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn create_test_app_state() -> AppState {
+    let runtime = RkllmRuntime::new(PathBuf::from("./mock_models"));
+    AppState::new(runtime, Arc::new(test_config()))
+}
+
+#[tokio::test]
+async fn test_concurrent_pull_of_the_same_model_conflicts() {
+    let state = create_test_app_state();
+    let _pull = state.pulls.start("user/model").unwrap();
+
+    let app = Router::new()
+        .route("/api/pull", post(crate::server::apis::models::pull_model))
+        .with_state(state.clone());
+    let server = TestServer::new(app);
+
+    let response = server
+        .post("/api/pull")
+        .json(&serde_json::json!({"name": "user/model"}))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+    assert!(response.text().contains("user/model is already being pulled"));
+    // The running pull is still tracked.
+    assert_eq!(state.pulls.list().len(), 1);
+}
+
+/// A server for `/api/create` on `models`, with no API keys, that sees
+/// requests come from `peer`.
+fn create_server(models: &std::path::Path, peer: [u8; 4]) -> TestServer {
+    let runtime = RkllmRuntime::new(models.to_path_buf());
+    let app = Router::new()
+        .route("/api/create", post(crate::server::apis::models::create_model))
+        .layer(Extension(ConnectInfo(SocketAddr::from((peer, 50000)))))
+        .with_state(AppState::new(runtime, Arc::new(test_config())));
+    TestServer::new(app)
+}
+
+fn create_from(path: &std::path::Path) -> serde_json::Value {
+    serde_json::json!({"model": "derived", "modelfile": format!("FROM {}", path.display()), "stream": false})
+}
+
+#[tokio::test]
+async fn test_create_confines_paths_to_the_models_directory() {
+    let models = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    std::fs::write(models.path().join("inside.rkllm"), b"model").unwrap();
+    std::fs::write(outside.path().join("outside.rkllm"), b"model").unwrap();
+    let server = create_server(models.path(), [192, 168, 1, 20]);
+
+    let response = server
+        .post("/api/create")
+        .json(&create_from(&outside.path().join("outside.rkllm")))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    assert!(response.text().contains("outside the models directory"));
+
+    let response = server
+        .post("/api/create")
+        .json(&create_from(&models.path().join("inside.rkllm")))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(models.path().join("manifests/derived/latest").is_file());
+}
+
+#[tokio::test]
+async fn test_create_from_any_path_on_this_machine_without_keys() {
+    let models = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    std::fs::write(outside.path().join("outside.rkllm"), b"model").unwrap();
+    let server = create_server(models.path(), [127, 0, 0, 1]);
+
+    let response = server
+        .post("/api/create")
+        .json(&create_from(&outside.path().join("outside.rkllm")))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(models.path().join("manifests/derived/latest").is_file());
+    assert!(outside.path().join("outside.rkllm").exists());
+}
+
+#[tokio::test]
+async fn test_create_rejects_templates_adapters_and_limits() {
+    let app = Router::new()
+        .route("/api/create", post(crate::server::apis::models::create_model))
+        .with_state(create_test_app_state());
+    let server = TestServer::new(app);
+
+    for line in ["TEMPLATE {{ .Prompt }}", "ADAPTER ./lora.rkllm", "PARAMETER num_ctx 1000000"] {
+        let response = server
+            .post("/api/create")
+            .json(&serde_json::json!({"model": "derived", "modelfile": format!("FROM qwen\n{}", line)}))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
     }
 }
+
+#[tokio::test]
+async fn test_unload_of_a_model_not_loaded_is_not_found() {
+    let models = tempfile::tempdir().unwrap();
+    std::fs::write(models.path().join("qwen.rkllm"), b"model").unwrap();
+    let runtime = RkllmRuntime::new(models.path().to_path_buf());
+    runtime.store().import_loose(std::time::Duration::ZERO).unwrap();
+    let app = Router::new()
+        .route("/api/unload", post(crate::server::apis::models::unload_model))
+        .with_state(AppState::new(runtime, Arc::new(test_config())));
+    let server = TestServer::new(app);
+
+    let response = server
+        .post("/api/unload")
+        .json(&serde_json::json!({"model": "qwen"}))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    assert!(response.text().contains("Model 'qwen:latest' is not loaded"));
+}
@@ -0,0 +1,106 @@
+use axum::{extract::State, Json};
+
+use crate::server::{
+    api_models::{
+        ChatCompletionRequestMessage, DetokenizeRequest, DetokenizeResponse, Role, TokenizeRequest,
+        TokenizeResponse,
+    },
+    apis::{error::ApiError, validation::Validate},
+    context::render_message,
+    request_log,
+    runtime_trait::ModelRuntime,
+    AppState,
+};
+
+/// The text a tokenize request counts, with the chat template applied if asked.
+fn prompt_text(request: &TokenizeRequest) -> String {
+    if let Some(messages) = &request.messages {
+        return messages.iter().map(render_message).collect::<Vec<_>>().join("\n");
+    }
+    let prompt = request.prompt.clone().unwrap_or_default();
+    if request.apply_chat_template {
+        render_message(&ChatCompletionRequestMessage {
+            role: Role::User,
+            content: prompt,
+            thinking: None,
+            images: None,
+            videos: None,
+        })
+    } else {
+        prompt
+    }
+}
+
+/// Tokenizes `request` with the model's tokenizer from `runtime`. Without a
+/// tokenizer file only an estimated count is returned.
+pub async fn tokenize_with(
+    runtime: &dyn ModelRuntime,
+    request: &TokenizeRequest,
+) -> Result<TokenizeResponse, ApiError> {
+    let tokenizer = runtime.tokenizer(&request.model).await?;
+    let text = prompt_text(request);
+    let (tokens, count) = if tokenizer.is_exact() {
+        let tokens = tokenizer.encode(&text)?;
+        let count = tokens.len();
+        (tokens, count)
+    } else {
+        (Vec::new(), tokenizer.count(&text))
+    };
+    Ok(TokenizeResponse {
+        model: request.model.clone(),
+        tokens,
+        count,
+        estimated: !tokenizer.is_exact(),
+    })
+}
+
+pub async fn detokenize_with(
+    runtime: &dyn ModelRuntime,
+    request: &DetokenizeRequest,
+) -> Result<DetokenizeResponse, ApiError> {
+    let tokenizer = runtime.tokenizer(&request.model).await?;
+    Ok(DetokenizeResponse {
+        model: request.model.clone(),
+        content: tokenizer.decode(&request.tokens)?,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/tokenize",
+    request_body = TokenizeRequest,
+    responses(
+        (status = 200, description = "Token ids and count", body = TokenizeResponse),
+        (status = 400, description = "Invalid request"),
+        (status = 404, description = "Model not found")
+    ),
+    tag = "rkllm"
+)]
+pub async fn tokenize(
+    State(state): State<AppState>,
+    Json(request): Json<TokenizeRequest>,
+) -> Result<Json<TokenizeResponse>, ApiError> {
+    request_log::record_model(&request.model);
+    request.validate(&state.config.limits)?;
+    Ok(Json(tokenize_with(&state.runtime, &request).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/detokenize",
+    request_body = DetokenizeRequest,
+    responses(
+        (status = 200, description = "Decoded text", body = DetokenizeResponse),
+        (status = 400, description = "Invalid request or no tokenizer for the model"),
+        (status = 404, description = "Model not found")
+    ),
+    tag = "rkllm"
+)]
+pub async fn detokenize(
+    State(state): State<AppState>,
+    Json(request): Json<DetokenizeRequest>,
+) -> Result<Json<DetokenizeResponse>, ApiError> {
+    request_log::record_model(&request.model);
+    request.validate(&state.config.limits)?;
+    Ok(Json(detokenize_with(&state.runtime, &request).await?))
+}
@@ -0,0 +1,71 @@
+//! Tests for the tokenize endpoints, using the mock runtime's byte-level tokenizer
+
+use crate::server::api_models::{DetokenizeRequest, TokenizeRequest};
+use crate::server::apis::error::ApiError;
+use crate::server::apis::tokenize::{detokenize_with, tokenize_with};
+use crate::server::mock_runtime::{MockRuntime, MockRuntimeBuilder};
+use crate::server::test_helpers::ChatRequestBuilder;
+
+fn request(prompt: &str) -> TokenizeRequest {
+    TokenizeRequest {
+        model: "test-model".into(),
+        prompt: Some(prompt.into()),
+        ..TokenizeRequest::default()
+    }
+}
+
+#[tokio::test]
+async fn test_tokenize_prompt() {
+    let response = tokenize_with(&MockRuntime::new(), &request("Hi!")).await.unwrap();
+    assert_eq!(response.tokens, vec![72, 105, 33]);
+    assert_eq!(response.count, 3);
+    assert!(!response.estimated);
+}
+
+#[tokio::test]
+async fn test_tokenize_applies_chat_template() {
+    let runtime = MockRuntime::new();
+    let mut templated = request("Hi!");
+    templated.apply_chat_template = true;
+    let response = tokenize_with(&runtime, &templated).await.unwrap();
+    assert_eq!(response.count, "<|User|>: Hi!".len());
+
+    let chat = TokenizeRequest {
+        model: "test-model".into(),
+        messages: Some(
+            ChatRequestBuilder::new("test-model")
+                .system("Be brief.")
+                .user("Hi!")
+                .build()
+                .messages,
+        ),
+        ..TokenizeRequest::default()
+    };
+    let response = tokenize_with(&runtime, &chat).await.unwrap();
+    assert_eq!(response.count, "<|System|>: Be brief.\n<|User|>: Hi!".len());
+}
+
+#[tokio::test]
+async fn test_detokenize_round_trip() {
+    let runtime = MockRuntime::new();
+    let tokens = tokenize_with(&runtime, &request("héllo")).await.unwrap().tokens;
+    let response = detokenize_with(
+        &runtime,
+        &DetokenizeRequest {
+            model: "test-model".into(),
+            tokens,
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.content, "héllo");
+}
+
+#[tokio::test]
+async fn test_tokenizer_failure_is_reported() {
+    let runtime = MockRuntimeBuilder::new()
+        .with_load_failure(true, "no such model")
+        .build();
+    let err = tokenize_with(&runtime, &request("Hi!")).await.unwrap_err();
+    assert!(matches!(err, ApiError::InternalError(msg) if msg.contains("no such model")));
+}
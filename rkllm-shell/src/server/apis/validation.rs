@@ -0,0 +1,391 @@
+//! Request validation for the inference endpoints
+//!
+//! Everything a handler passes to the native runtime is checked here first,
+//! so bad input becomes a `400` naming the offending fields instead of an FFI
+//! failure (or a panic on an interior NUL byte).
+
+use std::fmt;
+
+use crate::config::LimitsConfig;
+use crate::server::api_models::{
+    openai::{OpenAiContent, OpenAiContentPart},
+    ChatCompletionRequest, ChatCompletionRequestMessage, DetokenizeRequest, EmbedInput,
+    EmbedRequest, GenerateRequest, ModelOptions, OpenAiChatRequest, TokenizeRequest, VideoInput,
+};
+use crate::server::apis::error::ApiError;
+use crate::server::thinking::REASONING_EFFORTS;
+
+/// Roles accepted on `/v1/chat/completions`
+pub const OPENAI_ROLES: &[&str] = &["system", "developer", "user", "assistant", "tool"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Collects every problem with a request rather than stopping at the first.
+#[derive(Default)]
+struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    fn error(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    fn text(&mut self, field: impl Into<String>, value: &str) {
+        if value.contains('\0') {
+            self.error(field, "must not contain NUL bytes");
+        }
+    }
+
+    fn model(&mut self, model: &str) {
+        if model.trim().is_empty() {
+            self.error("model", "must not be empty");
+        }
+        self.text("model", model);
+    }
+
+    fn range_f32(&mut self, field: &str, value: f32, min: f32, max: f32) {
+        if !(min..=max).contains(&value) {
+            self.error(field, format!("must be between {} and {}, got {}", min, max, value));
+        }
+    }
+
+    fn max_tokens(&mut self, value: Option<i32>) {
+        if let Some(max_tokens) = value {
+            if max_tokens <= 0 {
+                self.error("max_tokens", format!("must be positive, got {}", max_tokens));
+            }
+        }
+    }
+
+    fn options(&mut self, options: &ModelOptions, limits: &LimitsConfig) {
+        if options.num_ctx <= 0 || options.num_ctx > limits.max_num_ctx {
+            self.error(
+                "options.num_ctx",
+                format!("must be between 1 and {}, got {}", limits.max_num_ctx, options.num_ctx),
+            );
+        }
+        if options.top_k < 0 {
+            self.error("options.top_k", format!("must not be negative, got {}", options.top_k));
+        }
+        if options.num_predict < -1 {
+            self.error(
+                "options.num_predict",
+                format!("must be -1 (unlimited) or more, got {}", options.num_predict),
+            );
+        }
+        if options.repeat_last_n < -1 {
+            self.error(
+                "options.repeat_last_n",
+                format!("must be -1 or more, got {}", options.repeat_last_n),
+            );
+        }
+        self.range_f32("options.temperature", options.temperature, 0.0, 2.0);
+        self.range_f32("options.top_p", options.top_p, 0.0, 1.0);
+        self.range_f32("options.min_p", options.min_p, 0.0, 1.0);
+        self.range_f32("options.repeat_penalty", options.repeat_penalty, 0.0, 10.0);
+        for (i, stop) in options.stop.iter().enumerate() {
+            self.text(format!("options.stop[{}]", i), stop);
+        }
+    }
+
+    fn images<'a>(&mut self, images: impl Iterator<Item = (String, &'a str)>, limits: &LimitsConfig) {
+        let mut count = 0;
+        for (field, image) in images {
+            count += 1;
+            self.encoded_size(field, "image", image, limits.max_image_bytes);
+        }
+        if count > limits.max_images {
+            self.error(
+                "images",
+                format!("{} images given, the limit is {}", count, limits.max_images),
+            );
+        }
+    }
+
+    fn videos<'a>(&mut self, videos: impl Iterator<Item = (String, &'a VideoInput)>, limits: &LimitsConfig) {
+        let mut count = 0;
+        for (field, video) in videos {
+            count += 1;
+            match video {
+                VideoInput::Encoded(data) => {
+                    let data = data.split_once(',').map_or(data.as_str(), |(_, data)| data);
+                    self.encoded_size(field, "video", data, limits.max_video_bytes);
+                }
+                VideoInput::Frames(frames) => {
+                    for (k, frame) in frames.iter().enumerate() {
+                        self.encoded_size(format!("{}[{}]", field, k), "frame", frame, limits.max_image_bytes);
+                    }
+                }
+            }
+        }
+        self.video_count(count, limits);
+    }
+
+    fn video_count(&mut self, count: usize, limits: &LimitsConfig) {
+        if count > limits.max_videos {
+            self.error(
+                "videos",
+                format!("{} videos given, the limit is {}", count, limits.max_videos),
+            );
+        }
+    }
+
+    fn encoded_size(&mut self, field: impl Into<String>, what: &str, data: &str, limit: usize) {
+        // Decoded size of base64 data, without allocating it.
+        let decoded = data.trim_end_matches('=').len() * 3 / 4;
+        if decoded > limit {
+            self.error(field, format!("{} is {} bytes, the limit is {}", what, decoded, limit));
+        }
+    }
+
+    fn prompt_length(&mut self, chars: usize, limits: &LimitsConfig) {
+        if chars > limits.max_prompt_chars {
+            self.error(
+                "messages",
+                format!(
+                    "prompt is {} characters, the limit is {}",
+                    chars, limits.max_prompt_chars
+                ),
+            );
+        }
+    }
+
+    fn messages(&mut self, count: usize, limits: &LimitsConfig) {
+        if count == 0 {
+            self.error("messages", "must not be empty");
+        } else if count > limits.max_messages {
+            self.error(
+                "messages",
+                format!("{} messages given, the limit is {}", count, limits.max_messages),
+            );
+        }
+    }
+
+    fn finish(self) -> Result<(), ApiError> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        let message = self
+            .errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        Err(ApiError::InvalidRequest(message))
+    }
+}
+
+pub trait Validate {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError>;
+}
+
+impl Validate for ModelOptions {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        let mut v = Validator::default();
+        v.options(self, limits);
+        v.finish()
+    }
+}
+
+impl Validate for GenerateRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        let mut v = Validator::default();
+        v.model(&self.model);
+        v.text("prompt", &self.prompt);
+        if let Some(system) = &self.system {
+            v.text("system", system);
+        }
+        let chars = self.prompt.chars().count()
+            + self.system.as_deref().map_or(0, |s| s.chars().count());
+        if chars > limits.max_prompt_chars {
+            v.error(
+                "prompt",
+                format!("prompt is {} characters, the limit is {}", chars, limits.max_prompt_chars),
+            );
+        }
+        v.max_tokens(self.max_tokens);
+        v.options(&self.options, limits);
+        v.finish()
+    }
+}
+
+fn chat_messages(v: &mut Validator, messages: &[ChatCompletionRequestMessage], limits: &LimitsConfig) {
+    v.messages(messages.len(), limits);
+    let mut chars = 0;
+    for (i, message) in messages.iter().enumerate() {
+        v.text(format!("messages[{}].content", i), &message.content);
+        chars += message.content.chars().count();
+    }
+    v.prompt_length(chars, limits);
+    let images = messages.iter().enumerate().flat_map(|(i, m)| {
+        m.images.iter().flatten().enumerate().map(move |(j, image)| {
+            (format!("messages[{}].images[{}]", i, j), image.as_str())
+        })
+    });
+    v.images(images, limits);
+    let videos = messages.iter().enumerate().flat_map(|(i, m)| {
+        m.videos.iter().flatten().enumerate().map(move |(j, video)| {
+            (format!("messages[{}].videos[{}]", i, j), video)
+        })
+    });
+    v.videos(videos, limits);
+}
+
+impl Validate for ChatCompletionRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        let mut v = Validator::default();
+        v.model(&self.model);
+        chat_messages(&mut v, &self.messages, limits);
+        v.max_tokens(self.max_tokens);
+        v.options(&self.options, limits);
+        v.finish()
+    }
+}
+
+impl Validate for OpenAiChatRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        let mut v = Validator::default();
+        v.model(&self.model);
+        v.messages(self.messages.len(), limits);
+
+        let mut chars = 0;
+        let mut images = Vec::new();
+        let mut videos = 0;
+        for (i, message) in self.messages.iter().enumerate() {
+            if !OPENAI_ROLES.contains(&message.role.as_str()) {
+                v.error(
+                    format!("messages[{}].role", i),
+                    format!("unknown role '{}', expected one of {}", message.role, OPENAI_ROLES.join(", ")),
+                );
+            }
+            match &message.content {
+                OpenAiContent::Text(text) => {
+                    v.text(format!("messages[{}].content", i), text);
+                    chars += text.chars().count();
+                }
+                OpenAiContent::Array(parts) => {
+                    for (j, part) in parts.iter().enumerate() {
+                        match part {
+                            OpenAiContentPart::Text { text } => {
+                                v.text(format!("messages[{}].content[{}].text", i, j), text);
+                                chars += text.chars().count();
+                            }
+                            OpenAiContentPart::ImageUrl { image_url } => {
+                                let data = image_url
+                                    .url
+                                    .split_once(',')
+                                    .map_or(image_url.url.as_str(), |(_, data)| data);
+                                images.push((format!("messages[{}].content[{}].image_url", i, j), data));
+                            }
+                            OpenAiContentPart::VideoUrl { video_url } => {
+                                videos += 1;
+                                let data = video_url
+                                    .url
+                                    .split_once(',')
+                                    .map_or(video_url.url.as_str(), |(_, data)| data);
+                                v.encoded_size(
+                                    format!("messages[{}].content[{}].video_url", i, j),
+                                    "video",
+                                    data,
+                                    limits.max_video_bytes,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        v.prompt_length(chars, limits);
+        v.images(images.into_iter(), limits);
+        v.video_count(videos, limits);
+        if let Some(temperature) = self.temperature {
+            v.range_f32("temperature", temperature, 0.0, 2.0);
+        }
+        if let Some(top_p) = self.top_p {
+            v.range_f32("top_p", top_p, 0.0, 1.0);
+        }
+        v.max_tokens(self.max_tokens);
+        if let Some(effort) = &self.reasoning_effort {
+            if !REASONING_EFFORTS.contains(&effort.as_str()) {
+                v.error(
+                    "reasoning_effort",
+                    format!("unknown value '{}', expected one of {}", effort, REASONING_EFFORTS.join(", ")),
+                );
+            }
+        }
+        v.finish()
+    }
+}
+
+impl Validate for EmbedRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        let mut v = Validator::default();
+        v.model(&self.model);
+        let inputs: Vec<&String> = match &self.input {
+            EmbedInput::Single(input) => vec![input],
+            EmbedInput::Multiple(inputs) => inputs.iter().collect(),
+        };
+        let mut chars = 0;
+        for (i, input) in inputs.iter().enumerate() {
+            v.text(format!("input[{}]", i), input);
+            chars += input.chars().count();
+        }
+        if chars > limits.max_prompt_chars {
+            v.error(
+                "input",
+                format!("input is {} characters, the limit is {}", chars, limits.max_prompt_chars),
+            );
+        }
+        v.finish()
+    }
+}
+
+impl Validate for TokenizeRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        let mut v = Validator::default();
+        v.model(&self.model);
+        match (&self.prompt, &self.messages) {
+            (Some(prompt), None) => {
+                v.text("prompt", prompt);
+                let chars = prompt.chars().count();
+                if chars > limits.max_prompt_chars {
+                    v.error(
+                        "prompt",
+                        format!("prompt is {} characters, the limit is {}", chars, limits.max_prompt_chars),
+                    );
+                }
+            }
+            (None, Some(messages)) => chat_messages(&mut v, messages, limits),
+            _ => v.error("prompt", "exactly one of prompt or messages is required"),
+        }
+        v.finish()
+    }
+}
+
+impl Validate for DetokenizeRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), ApiError> {
+        let mut v = Validator::default();
+        v.model(&self.model);
+        if self.tokens.len() > limits.max_detokenize_tokens {
+            v.error(
+                "tokens",
+                format!("{} tokens given, the limit is {}", self.tokens.len(), limits.max_detokenize_tokens),
+            );
+        }
+        v.finish()
+    }
+}
@@ -0,0 +1,174 @@
+//! Tests for request validation
+
+use crate::config::LimitsConfig;
+use crate::server::api_models::{
+    openai::{OpenAiContent, OpenAiContentPart, OpenAiImageUrl, OpenAiVideoUrl},
+    DetokenizeRequest, OpenAiChatRequest, VideoInput,
+};
+use crate::server::apis::error::ApiError;
+use crate::server::apis::validation::Validate;
+use crate::server::test_helpers::{ChatRequestBuilder, GenerateRequestBuilder, OpenAiChatRequestBuilder};
+
+fn message(err: ApiError) -> String {
+    match err {
+        ApiError::InvalidRequest(message) => message,
+        other => panic!("expected InvalidRequest, got {:?}", other),
+    }
+}
+
+fn openai_request(role: &str, content: OpenAiContent) -> OpenAiChatRequest {
+    let mut request = OpenAiChatRequestBuilder::new("test-model").message(role, "").build();
+    request.messages[0].content = content;
+    request
+}
+
+#[test]
+fn test_valid_requests_pass() {
+    let limits = LimitsConfig::default();
+    assert!(GenerateRequestBuilder::new("test-model", "Hello").build().validate(&limits).is_ok());
+    assert!(ChatRequestBuilder::new("test-model").user("Hello").build().validate(&limits).is_ok());
+    assert!(openai_request("developer", OpenAiContent::Text("Hi".into()))
+        .validate(&limits)
+        .is_ok());
+}
+
+#[test]
+fn test_empty_messages_rejected() {
+    let request = ChatRequestBuilder::new("test-model").build();
+    let err = message(request.validate(&LimitsConfig::default()).unwrap_err());
+    assert!(err.contains("messages: must not be empty"));
+}
+
+#[test]
+fn test_nul_byte_rejected() {
+    let request = GenerateRequestBuilder::new("test-model", "Hello\0world").build();
+    let err = message(request.validate(&LimitsConfig::default()).unwrap_err());
+    assert!(err.contains("prompt: must not contain NUL bytes"));
+}
+
+#[test]
+fn test_out_of_range_options_are_all_reported() {
+    let mut request = GenerateRequestBuilder::new("test-model", "Hello").build();
+    request.options.top_k = -1;
+    request.options.temperature = 3.5;
+    request.options.num_ctx = 1_000_000;
+
+    let err = message(request.validate(&LimitsConfig::default()).unwrap_err());
+    assert!(err.contains("options.top_k"));
+    assert!(err.contains("options.temperature"));
+    assert!(err.contains("options.num_ctx: must be between 1 and 16384"));
+}
+
+#[test]
+fn test_unknown_openai_role_rejected() {
+    let request = openai_request("wizard", OpenAiContent::Text("Hi".into()));
+    let err = message(request.validate(&LimitsConfig::default()).unwrap_err());
+    assert!(err.contains("messages[0].role: unknown role 'wizard'"));
+}
+
+#[test]
+fn test_image_limits() {
+    let limits = LimitsConfig {
+        max_images: 1,
+        max_image_bytes: 3,
+        ..LimitsConfig::default()
+    };
+    let image = |data: &str| OpenAiContentPart::ImageUrl {
+        image_url: OpenAiImageUrl {
+            url: format!("data:image/png;base64,{}", data),
+            detail: None,
+        },
+    };
+    let request = openai_request(
+        "user",
+        OpenAiContent::Array(vec![image("AAAA"), image("AAAAAAAA")]),
+    );
+
+    let err = message(request.validate(&limits).unwrap_err());
+    assert!(err.contains("messages[0].content[1].image_url: image is 6 bytes"));
+    assert!(err.contains("images: 2 images given, the limit is 1"));
+}
+
+#[test]
+fn test_video_limits() {
+    let limits = LimitsConfig {
+        max_videos: 1,
+        max_video_bytes: 3,
+        max_image_bytes: 3,
+        ..LimitsConfig::default()
+    };
+    let mut request = ChatRequestBuilder::new("test-model").user("Watch").build();
+    request.messages[0].videos = Some(vec![
+        VideoInput::Encoded("data:video/mp4;base64,AAAAAAAA".to_string()),
+        VideoInput::Frames(vec!["AAAA".to_string(), "AAAAAAAA".to_string()]),
+    ]);
+
+    let err = message(request.validate(&limits).unwrap_err());
+    assert!(err.contains("messages[0].videos[0]: video is 6 bytes"));
+    assert!(err.contains("messages[0].videos[1][1]: frame is 6 bytes"));
+    assert!(!err.contains("videos[1][0]"));
+    assert!(err.contains("videos: 2 videos given, the limit is 1"));
+}
+
+#[test]
+fn test_openai_video_limits() {
+    let limits = LimitsConfig {
+        max_video_bytes: 3,
+        ..LimitsConfig::default()
+    };
+    let video = |url: &str| OpenAiContentPart::VideoUrl {
+        video_url: OpenAiVideoUrl { url: url.to_string() },
+    };
+    // Bare base64 without a data URL prefix is checked too.
+    let request = openai_request(
+        "user",
+        OpenAiContent::Array(vec![video("data:video/mp4;base64,AAAA"), video("AAAAAAAA")]),
+    );
+
+    let err = message(request.validate(&limits).unwrap_err());
+    assert!(!err.contains("content[0]"));
+    assert!(err.contains("messages[0].content[1].video_url: video is 6 bytes"));
+}
+
+#[test]
+fn test_prompt_length_limit() {
+    let limits = LimitsConfig {
+        max_prompt_chars: 4,
+        ..LimitsConfig::default()
+    };
+    let request = ChatRequestBuilder::new("test-model").user("Hello").build();
+    let err = message(request.validate(&limits).unwrap_err());
+    assert!(err.contains("prompt is 5 characters, the limit is 4"));
+}
+
+#[test]
+fn test_detokenize_token_limit() {
+    let limits = LimitsConfig {
+        max_prompt_chars: 1,
+        max_detokenize_tokens: 3,
+        ..LimitsConfig::default()
+    };
+    let request = DetokenizeRequest {
+        model: "test-model".into(),
+        tokens: vec![1, 2, 3],
+    };
+    assert!(request.validate(&limits).is_ok());
+
+    let request = DetokenizeRequest {
+        model: "test-model".into(),
+        tokens: vec![1, 2, 3, 4],
+    };
+    let err = message(request.validate(&limits).unwrap_err());
+    assert!(err.contains("tokens: 4 tokens given, the limit is 3"));
+}
+
+#[test]
+fn test_unknown_reasoning_effort_rejected() {
+    let mut request = OpenAiChatRequestBuilder::new("test-model").user("Hi").build();
+    request.reasoning_effort = Some("extreme".into());
+    let err = message(request.validate(&LimitsConfig::default()).unwrap_err());
+    assert!(err.contains("reasoning_effort: unknown value 'extreme'"));
+
+    request.reasoning_effort = Some("high".into());
+    assert!(request.validate(&LimitsConfig::default()).is_ok());
+}
@@ -0,0 +1,179 @@
+//! API key authentication
+//!
+//! Keys come from `auth.keys` / `auth.keys_file` in the config and are stored
+//! as SHA-256 digests. Clients send them as `Authorization: Bearer <key>` or
+//! `x-api-key: <key>`. Each route requires a scope; unknown routes require
+//! `model_admin` so new endpoints are locked down by default.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sha2::{Digest, Sha256};
+
+use crate::config::{ApiKeyConfig, AuthConfig, Scope, TokenQuota};
+use crate::server::apis::error::ApiError;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Paths that stay reachable without a key when `exempt_public` is set
+const PUBLIC_PATHS: &[&str] = &["/", "/healthz", "/health/live", "/health/ready", "/openapi.json"];
+
+#[derive(Debug)]
+struct KeyEntry {
+    name: String,
+    scopes: Vec<Scope>,
+    quota: Option<TokenQuota>,
+}
+
+/// Request extension identifying the key a request authenticated with
+#[derive(Clone, Debug)]
+pub struct AuthenticatedKey {
+    pub name: String,
+    pub quota: Option<TokenQuota>,
+}
+
+/// The configured keys, indexed by digest
+#[derive(Clone, Debug, Default)]
+pub struct ApiKeys {
+    keys: Arc<HashMap<String, KeyEntry>>,
+    exempt_public: bool,
+}
+
+/// Whether a request to a `model_admin` route may act on the server's own
+/// files and process: it authenticated with a key, which the route's scope
+/// makes an admin's, or keys are off and it came from a loopback address.
+pub fn is_trusted(key: Option<&AuthenticatedKey>, peer: Option<&ConnectInfo<SocketAddr>>) -> bool {
+    key.is_some() || peer.is_some_and(|ConnectInfo(addr)| addr.ip().to_canonical().is_loopback())
+}
+
+pub fn hash_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+impl ApiKeys {
+    pub fn new(keys: Vec<ApiKeyConfig>, exempt_public: bool) -> Self {
+        let keys = keys
+            .into_iter()
+            .map(|k| {
+                (
+                    k.sha256.trim().to_ascii_lowercase(),
+                    KeyEntry {
+                        name: k.name,
+                        scopes: k.scopes,
+                        quota: k.quota,
+                    },
+                )
+            })
+            .collect();
+        Self {
+            keys: Arc::new(keys),
+            exempt_public,
+        }
+    }
+
+    pub fn from_config(config: &AuthConfig) -> crate::error::Result<Self> {
+        Ok(Self::new(config.load_keys()?, config.exempt_public))
+    }
+
+    /// Authentication is off until at least one key is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+}
+
+fn is_public(path: &str) -> bool {
+    PUBLIC_PATHS.contains(&path) || path == "/docs" || path.starts_with("/docs/")
+}
+
+/// The scope a request to `path` needs, or `None` for public paths.
+pub fn required_scope(path: &str) -> Option<Scope> {
+    if is_public(path) {
+        return None;
+    }
+    let scope = match path {
+        "/api/generate" | "/api/chat" | "/api/embed" | "/api/tags" | "/api/show"
+        | "/api/ps" | "/api/tokenize" | "/api/detokenize" | "/v1/chat/completions"
+        | "/v1/models" => Scope::Inference,
+        p if p.starts_with("/v1/models/") => Scope::Inference,
+        p if p.starts_with("/api/agent/") => Scope::Agent,
+        _ => Scope::ModelAdmin,
+    };
+    Some(scope)
+}
+
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    bearer
+        .or_else(|| headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()))
+        .map(str::trim)
+}
+
+/// OpenAI clients expect `{"error": {"message", "type", ...}}`, Ollama
+/// clients a plain `{"error": "..."}`.
+fn auth_error(path: &str, status: StatusCode, message: String) -> Response {
+    if path.starts_with("/v1/") {
+        let error = if status == StatusCode::FORBIDDEN {
+            ApiError::PermissionDenied(message)
+        } else {
+            ApiError::AuthenticationError(message)
+        };
+        return error.into_response();
+    }
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Middleware rejecting requests without a key holding the route's scope.
+pub async fn require_api_key(State(keys): State<ApiKeys>, mut request: Request, next: Next) -> Response {
+    if !keys.is_enabled() {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path().to_owned();
+    let scope = required_scope(&path);
+    if scope.is_none() && keys.exempt_public {
+        return next.run(request).await;
+    }
+
+    let Some(key) = presented_key(request.headers()) else {
+        tracing::warn!(%path, "request without API key");
+        return auth_error(&path, StatusCode::UNAUTHORIZED, "missing API key".to_string());
+    };
+    let Some(entry) = keys.keys.get(&hash_key(key)) else {
+        tracing::warn!(%path, "request with unknown API key");
+        return auth_error(&path, StatusCode::UNAUTHORIZED, "invalid API key".to_string());
+    };
+
+    match scope {
+        Some(scope) if !entry.scopes.contains(&scope) => {
+            tracing::warn!(%path, key = %entry.name, scope = scope.as_str(), "API key lacks scope");
+            auth_error(
+                &path,
+                StatusCode::FORBIDDEN,
+                format!("API key '{}' lacks the '{}' scope", entry.name, scope.as_str()),
+            )
+        }
+        _ => {
+            tracing::debug!(key = %entry.name, "authenticated");
+            request.extensions_mut().insert(AuthenticatedKey {
+                name: entry.name.clone(),
+                quota: entry.quota,
+            });
+            next.run(request).await
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "auth_test.rs"]
+mod tests;
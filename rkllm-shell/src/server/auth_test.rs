@@ -0,0 +1,112 @@
+use super::*;
+use axum::{
+    routing::{delete, get, post},
+    Router,
+};
+use axum_test::TestServer;
+
+fn key(name: &str, secret: &str, scopes: Vec<Scope>) -> ApiKeyConfig {
+    ApiKeyConfig {
+        name: name.to_string(),
+        sha256: hash_key(secret),
+        scopes,
+        quota: None,
+    }
+}
+
+fn test_server(keys: Vec<ApiKeyConfig>, exempt_public: bool) -> TestServer {
+    let app = Router::new()
+        .route("/healthz", get(|| async { "OK" }))
+        .route("/api/generate", post(|| async { "generated" }))
+        .route("/api/delete", delete(|| async { "deleted" }))
+        .route("/v1/chat/completions", post(|| async { "completed" }))
+        .layer(axum::middleware::from_fn_with_state(
+            ApiKeys::new(keys, exempt_public),
+            require_api_key,
+        ));
+    TestServer::new(app)
+}
+
+#[test]
+fn test_required_scope() {
+    assert_eq!(required_scope("/healthz"), None);
+    assert_eq!(required_scope("/docs/index.html"), None);
+    assert_eq!(required_scope("/api/chat"), Some(Scope::Inference));
+    assert_eq!(required_scope("/v1/models/qwen"), Some(Scope::Inference));
+    assert_eq!(required_scope("/api/agent/stream"), Some(Scope::Agent));
+    assert_eq!(required_scope("/api/delete"), Some(Scope::ModelAdmin));
+    assert_eq!(required_scope("/api/shutdown"), Some(Scope::ModelAdmin));
+    assert_eq!(required_scope("/api/something-new"), Some(Scope::ModelAdmin));
+}
+
+#[tokio::test]
+async fn test_no_keys_disables_auth() {
+    let server = test_server(vec![], true);
+
+    let response = server.delete("/api/delete").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_missing_key_is_rejected_with_ollama_body() {
+    let server = test_server(vec![key("ci", "secret", vec![Scope::Inference])], true);
+
+    let response = server.post("/api/generate").await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"], "missing API key");
+}
+
+#[tokio::test]
+async fn test_invalid_key_is_rejected_with_openai_body() {
+    let server = test_server(vec![key("ci", "secret", vec![Scope::Inference])], true);
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header("authorization", "Bearer wrong")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["type"], "authentication_error");
+}
+
+#[tokio::test]
+async fn test_bearer_and_x_api_key_are_accepted() {
+    let server = test_server(vec![key("ci", "secret", vec![Scope::Inference])], true);
+
+    let response = server
+        .post("/api/generate")
+        .add_header("authorization", "Bearer secret")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = server
+        .post("/api/generate")
+        .add_header(API_KEY_HEADER, "secret")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_key_without_scope_is_forbidden() {
+    let server = test_server(vec![key("ci", "secret", vec![Scope::Inference])], true);
+
+    let response = server
+        .delete("/api/delete")
+        .add_header(API_KEY_HEADER, "secret")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_public_paths_can_require_a_key() {
+    let keys = vec![key("ci", "secret", vec![])];
+
+    let server = test_server(keys.clone(), true);
+    assert_eq!(server.get("/healthz").await.status_code(), StatusCode::OK);
+
+    let server = test_server(keys, false);
+    assert_eq!(server.get("/healthz").await.status_code(), StatusCode::UNAUTHORIZED);
+    let response = server.get("/healthz").add_header(API_KEY_HEADER, "secret").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
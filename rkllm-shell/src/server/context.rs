@@ -0,0 +1,231 @@
+//! Context-window management for chat prompts
+//!
+//! Prompts are counted with the model's tokenizer (see `tokenizer`) against
+//! `num_ctx` minus the tokens reserved for the reply. Conversations that do
+//! not fit are handled according to `context.policy`: the oldest non-system
+//! turns are dropped (and optionally summarised), or the request is rejected.
+
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::config::{ContextConfig, ContextPolicy};
+use crate::server::api_models::{ChatCompletionRequestMessage, ContextReport, ModelOptions, Role};
+use crate::server::apis::error::ApiError;
+use crate::server::runtime_trait::Tokenize;
+use crate::server::vision::{IMAGE_PLACEHOLDER, VIDEO_PLACEHOLDER};
+
+/// Renders a message the way the RKLLM chat template expects it. Images and
+/// videos without a placeholder in the text are placed before it.
+pub fn render_message(message: &ChatCompletionRequestMessage) -> String {
+    let missing = |placeholder: &str, count: usize| {
+        let missing = count.saturating_sub(message.content.matches(placeholder).count());
+        format!("{}\n", placeholder).repeat(missing)
+    };
+    let content = format!(
+        "{}{}{}",
+        missing(VIDEO_PLACEHOLDER, message.videos.as_ref().map_or(0, Vec::len)),
+        missing(IMAGE_PLACEHOLDER, message.images.as_ref().map_or(0, Vec::len)),
+        message.content
+    );
+    match message.role {
+        Role::System => format!("<|System|>: {}", content),
+        Role::User => format!("<|User|>: {}", content),
+        Role::Assistant => format!("<|Assistant|>: {}", content),
+        _ => content,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextBudget {
+    pub num_ctx: usize,
+    pub reserved: usize,
+}
+
+impl ContextBudget {
+    /// Reserves `num_predict` tokens for the reply when set, otherwise
+    /// `reserve_tokens`, but never the whole window.
+    pub fn new(options: &ModelOptions, config: &ContextConfig) -> Self {
+        let num_ctx = options.num_ctx.max(1) as usize;
+        let wanted = if options.num_predict > 0 {
+            options.num_predict as usize
+        } else {
+            config.reserve_tokens
+        };
+        Self {
+            num_ctx,
+            reserved: wanted.min(num_ctx / 2),
+        }
+    }
+
+    /// Tokens available to the prompt
+    pub fn available(&self) -> usize {
+        self.num_ctx - self.reserved
+    }
+
+    pub fn exceeded(&self, prompt_tokens: usize) -> ApiError {
+        ApiError::ContextLengthExceeded(format!(
+            "the model's context window is {} tokens with {} reserved for the reply, \
+             but the prompt is {} tokens ({} over)",
+            self.num_ctx,
+            self.reserved,
+            prompt_tokens,
+            prompt_tokens.saturating_sub(self.available()),
+        ))
+    }
+}
+
+/// The messages to send, plus what was removed to get there
+#[derive(Debug)]
+pub struct ContextPlan {
+    pub messages: Vec<ChatCompletionRequestMessage>,
+    pub dropped: Vec<ChatCompletionRequestMessage>,
+    pub report: ContextReport,
+}
+
+/// Tokens `message` adds to the prompt, including its separating newline.
+fn message_tokens(tokenizer: &dyn Tokenize, message: &ChatCompletionRequestMessage) -> usize {
+    tokenizer.count(&render_message(message)) + 1
+}
+
+/// Rejects a single prompt (as used by `/api/generate`) that does not fit.
+pub fn check_prompt(tokenizer: &dyn Tokenize, prompt: &str, budget: ContextBudget) -> Result<usize, ApiError> {
+    let tokens = tokenizer.count(prompt);
+    if tokens > budget.available() {
+        return Err(budget.exceeded(tokens));
+    }
+    Ok(tokens)
+}
+
+/// Fits `messages` into `budget`, dropping the oldest non-system turns unless
+/// the policy is `reject`. The last message is never dropped.
+pub fn fit_messages(
+    messages: Vec<ChatCompletionRequestMessage>,
+    tokenizer: &dyn Tokenize,
+    budget: ContextBudget,
+    policy: ContextPolicy,
+) -> Result<ContextPlan, ApiError> {
+    let counts: Vec<usize> = messages.iter().map(|m| message_tokens(tokenizer, m)).collect();
+    let mut total: usize = counts.iter().sum();
+    let original = total;
+
+    let mut keep = vec![true; messages.len()];
+    if total > budget.available() {
+        if policy == ContextPolicy::Reject {
+            return Err(budget.exceeded(total));
+        }
+        let last = messages.len().saturating_sub(1);
+        for (i, message) in messages.iter().enumerate() {
+            if total <= budget.available() {
+                break;
+            }
+            if i == last || message.role == Role::System {
+                continue;
+            }
+            keep[i] = false;
+            total -= counts[i];
+        }
+        if total > budget.available() {
+            return Err(budget.exceeded(original));
+        }
+    }
+
+    let (kept, dropped): (Vec<_>, Vec<_>) = messages
+        .into_iter()
+        .zip(keep)
+        .partition(|(_, keep)| *keep);
+    let report = ContextReport {
+        num_ctx: budget.num_ctx,
+        prompt_tokens: total,
+        reserved_tokens: budget.reserved,
+        dropped_messages: dropped.len(),
+        summarized: false,
+        estimated: !tokenizer.is_exact(),
+    };
+    Ok(ContextPlan {
+        messages: kept.into_iter().map(|(m, _)| m).collect(),
+        dropped: dropped.into_iter().map(|(m, _)| m).collect(),
+        report,
+    })
+}
+
+const SUMMARY_INSTRUCTION: &str =
+    "<|System|>: Summarise this conversation briefly, keeping names, facts and decisions.";
+
+/// Keeps the end of `text` so it counts at most `allowed` tokens.
+fn keep_tail(tokenizer: &dyn Tokenize, mut text: String, allowed: usize) -> String {
+    loop {
+        let tokens = tokenizer.count(&text);
+        if tokens <= allowed {
+            return text;
+        }
+        let chars = text.chars().count();
+        let keep = (chars * allowed / tokens).min(chars - 1);
+        text = text.chars().skip(chars - keep).collect();
+    }
+}
+
+/// Replaces the turns `plan` dropped with a system message summarising them,
+/// when the summary fits. `run` generates a completion for a prompt.
+pub async fn summarize_dropped<F>(
+    plan: &mut ContextPlan,
+    tokenizer: &dyn Tokenize,
+    budget: ContextBudget,
+    run: F,
+) where
+    F: FnOnce(Vec<String>) -> UnboundedReceiver<String>,
+{
+    if plan.dropped.is_empty() {
+        return;
+    }
+
+    // The summarisation prompt must fit too, so the oldest text goes first.
+    let transcript: Vec<String> = plan.dropped.iter().map(render_message).collect();
+    let overhead = tokenizer.count(SUMMARY_INSTRUCTION) + tokenizer.count("<|User|>: ") + 2;
+    let transcript = keep_tail(
+        tokenizer,
+        transcript.join("\n"),
+        budget.available().saturating_sub(overhead),
+    );
+    if transcript.is_empty() {
+        return;
+    }
+
+    let prompt = vec![
+        SUMMARY_INSTRUCTION.to_string(),
+        format!("<|User|>: {}", transcript),
+    ];
+    let mut rx = run(prompt);
+    let mut summary = String::new();
+    while let Some(token) = rx.recv().await {
+        summary.push_str(&token);
+    }
+    let summary = summary.trim();
+    if summary.is_empty() {
+        return;
+    }
+
+    let message = ChatCompletionRequestMessage {
+        role: Role::System,
+        content: format!("Summary of the earlier conversation: {}", summary),
+        thinking: None,
+        images: None,
+        videos: None,
+    };
+    let cost = message_tokens(tokenizer, &message);
+    if plan.report.prompt_tokens + cost > budget.available() {
+        tracing::debug!(cost, "conversation summary does not fit, dropping it");
+        return;
+    }
+    // After the leading system prompt(s), where the dropped turns used to start.
+    let position = plan
+        .messages
+        .iter()
+        .position(|m| m.role != Role::System)
+        .unwrap_or(plan.messages.len());
+    plan.messages.insert(position, message);
+    plan.report.prompt_tokens += cost;
+    plan.report.summarized = true;
+}
+
+#[cfg(test)]
+#[path = "context_test.rs"]
+mod tests;
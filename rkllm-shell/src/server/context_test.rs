@@ -0,0 +1,177 @@
+//! Tests for context-window fitting
+
+use super::*;
+use crate::server::tokenizer::ModelTokenizer;
+use crate::server::test_helpers::ChatRequestBuilder;
+
+/// Every rendered message costs `ceil(chars / 3) + 1` tokens with the estimate.
+fn budget(num_ctx: usize, reserved: usize) -> ContextBudget {
+    ContextBudget { num_ctx, reserved }
+}
+
+fn conversation() -> Vec<ChatCompletionRequestMessage> {
+    ChatRequestBuilder::new("test-model")
+        .system("Be brief.")
+        .user(&"a".repeat(60))
+        .assistant(&"b".repeat(60))
+        .user("Last question?")
+        .build()
+        .messages
+}
+
+#[test]
+fn test_budget_reserves_num_predict_or_default() {
+    let config = ContextConfig::default();
+    let mut options = ChatRequestBuilder::new("test-model").build().options;
+    options.num_ctx = 4096;
+    options.num_predict = -1;
+    assert_eq!(ContextBudget::new(&options, &config).reserved, 512);
+
+    options.num_predict = 100;
+    assert_eq!(ContextBudget::new(&options, &config).available(), 3996);
+
+    // Never more than half the window.
+    options.num_predict = 10_000;
+    assert_eq!(ContextBudget::new(&options, &config).reserved, 2048);
+}
+
+#[test]
+fn test_fitting_conversation_is_untouched() {
+    let plan = fit_messages(
+        conversation(),
+        &ModelTokenizer::Estimate,
+        budget(4096, 512),
+        ContextPolicy::TruncateOldest,
+    )
+    .unwrap();
+    assert_eq!(plan.messages.len(), 4);
+    assert!(plan.dropped.is_empty());
+    assert_eq!(plan.report.dropped_messages, 0);
+    assert!(plan.report.estimated);
+}
+
+#[test]
+fn test_truncate_drops_oldest_turns_but_keeps_system_and_last() {
+    let plan = fit_messages(
+        conversation(),
+        &ModelTokenizer::Estimate,
+        budget(64, 20),
+        ContextPolicy::TruncateOldest,
+    )
+    .unwrap();
+    let roles: Vec<Role> = plan.messages.iter().map(|m| m.role.clone()).collect();
+    assert_eq!(roles, vec![Role::System, Role::Assistant, Role::User]);
+    assert_eq!(plan.report.dropped_messages, 1);
+    assert!(plan.report.prompt_tokens <= 44);
+}
+
+#[test]
+fn test_reject_policy_reports_token_counts() {
+    let err = fit_messages(
+        conversation(),
+        &ModelTokenizer::Estimate,
+        budget(64, 20),
+        ContextPolicy::Reject,
+    )
+    .unwrap_err();
+    match err {
+        ApiError::ContextLengthExceeded(message) => {
+            assert!(message.contains("context window is 64 tokens with 20 reserved"));
+        }
+        other => panic!("expected ContextLengthExceeded, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_last_message_too_long_is_an_error() {
+    let messages = ChatRequestBuilder::new("test-model")
+        .user(&"x".repeat(300))
+        .build()
+        .messages;
+    let result = fit_messages(
+        messages,
+        &ModelTokenizer::Estimate,
+        budget(64, 20),
+        ContextPolicy::TruncateOldest,
+    );
+    assert!(matches!(result, Err(ApiError::ContextLengthExceeded(_))));
+}
+
+#[test]
+fn test_check_prompt() {
+    assert_eq!(check_prompt(&ModelTokenizer::Estimate, "abcdef", budget(64, 20)).unwrap(), 2);
+    assert!(check_prompt(&ModelTokenizer::Estimate, &"x".repeat(200), budget(64, 20)).is_err());
+}
+
+#[tokio::test]
+async fn test_summary_replaces_dropped_turns() {
+    let messages = ChatRequestBuilder::new("test-model")
+        .system("Be brief.")
+        .user(&"a".repeat(150))
+        .assistant("ok")
+        .user("Last question?")
+        .build()
+        .messages;
+    let mut plan = fit_messages(
+        messages,
+        &ModelTokenizer::Estimate,
+        budget(80, 20),
+        ContextPolicy::Summarize,
+    )
+    .unwrap();
+    assert_eq!(plan.report.dropped_messages, 1);
+
+    let mut prompt_seen = Vec::new();
+    summarize_dropped(&mut plan, &ModelTokenizer::Estimate, budget(80, 20), |prompt| {
+        prompt_seen = prompt;
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tx.send("User sent a's.".to_string()).unwrap();
+        rx
+    })
+    .await;
+
+    // The dropped turn is cut down to fit the summarisation prompt.
+    assert!(prompt_seen[1].ends_with(&"a".repeat(20)));
+    assert!(plan.report.summarized);
+    assert_eq!(plan.messages.len(), 4);
+    assert_eq!(plan.messages[1].role, Role::System);
+    assert_eq!(
+        plan.messages[1].content,
+        "Summary of the earlier conversation: User sent a's."
+    );
+}
+
+#[test]
+fn test_images_keep_their_place_in_the_prompt() {
+    use crate::server::api_models::openai::{OpenAiContent, OpenAiContentPart, OpenAiImageUrl};
+    use crate::server::api_models::translate::extract_content_and_media;
+
+    let (content, images, videos) = extract_content_and_media(OpenAiContent::Array(vec![
+        OpenAiContentPart::Text { text: "Compare".into() },
+        OpenAiContentPart::ImageUrl {
+            image_url: OpenAiImageUrl {
+                url: "data:image/png;base64,AAAA".into(),
+                detail: None,
+            },
+        },
+        OpenAiContentPart::Text { text: "with the first one.".into() },
+    ]));
+    assert_eq!(content, "Compare\n<image>\nwith the first one.");
+    let message = ChatCompletionRequestMessage {
+        role: Role::User,
+        content,
+        thinking: None,
+        images,
+        videos,
+    };
+    assert_eq!(render_message(&message), "<|User|>: Compare\n<image>\nwith the first one.");
+
+    // Ollama-style messages carry no placeholders: images go first.
+    let message = ChatRequestBuilder::new("test-model")
+        .user("What is this?")
+        .with_image("AAAA")
+        .build()
+        .messages
+        .remove(0);
+    assert_eq!(render_message(&message), "<|User|>: <image>\nWhat is this?");
+}
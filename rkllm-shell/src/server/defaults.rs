@@ -78,5 +78,6 @@ pub fn default_model_options() -> ModelOptions {
         top_k: default_top_k(),
         top_p: default_top_p(),
         min_p: default_min_p(),
+        given: Default::default(),
     }
 }
@@ -0,0 +1,531 @@
+//! Downloading model files from a registry
+//!
+//! A registry is a Hugging Face compatible hub, ModelScope, or a plain HTTP
+//! server with an `index.json` listing its models. Files are fetched into
+//! `<file>.partial`, so an interrupted pull picks up where it stopped with a
+//! `Range` request, and are checked against their sha256 before being moved
+//! into place. Files already in the Hugging Face cache are linked from there
+//! instead of being downloaded again.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use reqwest::{header, StatusCode, Url};
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{RegistryConfig, RegistryKind};
+use crate::server::apis::{error::ApiError, models::sha256_file_async};
+
+pub const DEFAULT_ENDPOINT: &str = "https://huggingface.co";
+pub const MODELSCOPE_ENDPOINT: &str = "https://www.modelscope.cn";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LfsInfo {
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// A file in a repository, as the Hub lists it with `?blobs=true`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RepoFile {
+    pub rfilename: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub lfs: Option<LfsInfo>,
+    /// Where the file is downloaded from, filled in by `list_files`
+    #[serde(skip)]
+    pub url: String,
+}
+
+impl RepoFile {
+    pub fn total(&self) -> Option<u64> {
+        self.lfs.as_ref().map(|lfs| lfs.size).or(self.size)
+    }
+
+    pub fn sha256(&self) -> Option<&str> {
+        self.lfs.as_ref().map(|lfs| lfs.sha256.as_str())
+    }
+}
+
+#[derive(Deserialize)]
+struct RepoInfo {
+    siblings: Vec<RepoFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ModelscopeListing {
+    data: ModelscopeFiles,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ModelscopeFiles {
+    files: Vec<ModelscopeFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ModelscopeFile {
+    path: String,
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+/// `index.json` of an `http` registry
+#[derive(Deserialize)]
+struct HttpIndex {
+    models: Vec<HttpIndexModel>,
+}
+
+#[derive(Deserialize)]
+struct HttpIndexModel {
+    name: String,
+    #[serde(default = "default_revision")]
+    revision: String,
+    files: Vec<HttpIndexFile>,
+}
+
+#[derive(Deserialize)]
+struct HttpIndexFile {
+    /// Relative to the registry's base URL
+    path: String,
+    /// Absolute URL, when the file is not under the base URL
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+fn default_revision() -> String {
+    "main".to_string()
+}
+
+pub struct HubClient {
+    client: reqwest::Client,
+    kind: RegistryKind,
+    endpoint: String,
+    token: Option<String>,
+    cache_dir: Option<PathBuf>,
+    hf_cache: Option<PathBuf>,
+}
+
+/// Whether two URLs share scheme, host and port. A plain prefix check would
+/// also match `https://huggingface.co.example.com`.
+fn same_origin(a: &str, b: &str) -> bool {
+    match (Url::parse(a), Url::parse(b)) {
+        (Ok(a), Ok(b)) => {
+            a.scheme() == b.scheme()
+                && a.host_str() == b.host_str()
+                && a.port_or_known_default() == b.port_or_known_default()
+        }
+        _ => false,
+    }
+}
+
+/// How `place_file` put a file in place
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    HardLink,
+    Reflink,
+    Move,
+}
+
+impl HubClient {
+    /// A client for `registry`, filling in the public endpoint and the usual
+    /// token variables for the built-in kinds.
+    pub fn from_config(registry: &RegistryConfig) -> Result<Self, ApiError> {
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let endpoint = match (&registry.endpoint, registry.kind) {
+            (Some(endpoint), _) => endpoint.clone(),
+            (None, RegistryKind::Hf) => env("HF_ENDPOINT").unwrap_or_else(|| DEFAULT_ENDPOINT.to_string()),
+            (None, RegistryKind::Modelscope) => MODELSCOPE_ENDPOINT.to_string(),
+            (None, RegistryKind::Http) => {
+                return Err(ApiError::InvalidRequest(format!(
+                    "Registry '{}' has no endpoint",
+                    registry.name
+                )))
+            }
+        };
+        let token = registry.token.clone().or_else(|| match registry.kind {
+            RegistryKind::Hf => env("HF_TOKEN"),
+            RegistryKind::Modelscope => env("MODELSCOPE_API_TOKEN"),
+            RegistryKind::Http => None,
+        });
+
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &registry.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|e| {
+                ApiError::Internal(format!("Invalid proxy for registry '{}': {}", registry.name, e))
+            })?;
+            builder = builder.proxy(proxy);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| ApiError::Internal(format!("Registry '{}': {}", registry.name, e)))?;
+
+        Ok(Self {
+            client,
+            kind: registry.kind,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token,
+            cache_dir: registry.cache_dir.clone(),
+            hf_cache: hf_cache_dir(),
+        })
+    }
+
+    /// Looks for already downloaded files in `dir` instead of the usual
+    /// Hugging Face cache.
+    pub fn with_hf_cache(mut self, dir: Option<PathBuf>) -> Self {
+        self.hf_cache = dir;
+        self
+    }
+
+    /// The Hugging Face cache's copy of `file`, if another tool downloaded it.
+    /// Blobs are named by their sha256, so only files with a digest are found.
+    pub async fn cached_blob(&self, repo: &str, file: &RepoFile) -> Option<PathBuf> {
+        let lfs = file.lfs.as_ref()?;
+        let blob = self
+            .hf_cache
+            .as_ref()?
+            .join(format!("models--{}", repo.replace('/', "--")))
+            .join("blobs")
+            .join(&lfs.sha256);
+        let metadata = tokio::fs::metadata(&blob).await.ok()?;
+        (metadata.is_file() && metadata.len() == lfs.size).then_some(blob)
+    }
+
+    /// Puts the Hugging Face cache's copy of `file` at `dest`, if there is
+    /// one, so the model is not stored twice.
+    pub async fn reuse_cached(
+        &self,
+        repo: &str,
+        file: &RepoFile,
+        dest: &Path,
+    ) -> Result<Option<Placement>, ApiError> {
+        let Some(blob) = self.cached_blob(repo, file).await else {
+            return Ok(None);
+        };
+        let dest = dest.to_path_buf();
+        let placement = tokio::task::spawn_blocking(move || {
+            match fs::remove_file(&dest) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            place_file(&blob, &dest)
+        })
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .map_err(|e| ApiError::InternalError(format!("{}: {}", file.rfilename, e)))?;
+        Ok(Some(placement))
+    }
+
+    /// Requests to the registry itself carry the token; files it points
+    /// elsewhere do not.
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.get(url);
+        match &self.token {
+            Some(token) if same_origin(url, &self.endpoint) => request.bearer_auth(token),
+            _ => request,
+        }
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str, repo: &str) -> Result<T, ApiError> {
+        let response = self
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to reach {}: {}", self.endpoint, e)))?;
+        if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::UNAUTHORIZED) {
+            return Err(ApiError::ModelNotFound(format!("Repository {} not found", repo)));
+        }
+        response
+            .error_for_status()
+            .map_err(|e| ApiError::InternalError(format!("Failed to list {}: {}", repo, e)))?
+            .json()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Invalid listing for {}: {}", repo, e)))
+    }
+
+    fn url_with_query(&self, path: &str, query: &[(&str, &str)]) -> Result<String, ApiError> {
+        let mut url = Url::parse(&format!("{}/{}", self.endpoint, path))
+            .map_err(|e| ApiError::Internal(format!("Invalid registry URL {}: {}", self.endpoint, e)))?;
+        url.query_pairs_mut().extend_pairs(query);
+        Ok(url.into())
+    }
+
+    /// Every file in `repo` at `revision`, with sizes, digests where the
+    /// registry has them, and download URLs.
+    pub async fn list_files(&self, repo: &str, revision: &str) -> Result<Vec<RepoFile>, ApiError> {
+        match self.kind {
+            RegistryKind::Hf => {
+                let url = format!(
+                    "{}/api/models/{}/revision/{}?blobs=true",
+                    self.endpoint,
+                    repo,
+                    encode_revision(revision)
+                );
+                let info: RepoInfo = self.get_json(&url, repo).await?;
+                Ok(info
+                    .siblings
+                    .into_iter()
+                    .map(|file| RepoFile {
+                        url: format!(
+                            "{}/{}/resolve/{}/{}",
+                            self.endpoint,
+                            repo,
+                            encode_revision(revision),
+                            file.rfilename
+                        ),
+                        ..file
+                    })
+                    .collect())
+            }
+            RegistryKind::Modelscope => {
+                let url = self.url_with_query(
+                    &format!("api/v1/models/{}/repo/files", repo),
+                    &[("Revision", revision), ("Recursive", "true")],
+                )?;
+                let listing: ModelscopeListing = self.get_json(&url, repo).await?;
+                listing
+                    .data
+                    .files
+                    .into_iter()
+                    .filter(|file| file.kind == "blob")
+                    .map(|file| {
+                        Ok(RepoFile {
+                            url: self.url_with_query(
+                                &format!("api/v1/models/{}/repo", repo),
+                                &[("Revision", revision), ("FilePath", &file.path)],
+                            )?,
+                            lfs: lfs_info(file.sha256, file.size),
+                            size: file.size,
+                            rfilename: file.path,
+                        })
+                    })
+                    .collect()
+            }
+            RegistryKind::Http => {
+                let index: HttpIndex = self
+                    .get_json(&format!("{}/index.json", self.endpoint), repo)
+                    .await?;
+                let model = index
+                    .models
+                    .into_iter()
+                    .find(|m| m.name == repo && m.revision == revision)
+                    .ok_or_else(|| {
+                        ApiError::ModelNotFound(format!("Repository {} (revision {}) not found", repo, revision))
+                    })?;
+                Ok(model
+                    .files
+                    .into_iter()
+                    .map(|file| RepoFile {
+                        url: file
+                            .url
+                            .unwrap_or_else(|| format!("{}/{}", self.endpoint, file.path)),
+                        lfs: lfs_info(file.sha256, file.size),
+                        size: file.size,
+                        rfilename: file.path,
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// Downloads `file` into `partial_path(dest)`, resuming what an earlier
+    /// attempt left there. `progress` is called with the bytes on disk and the
+    /// total after every chunk. Returns the bytes transferred.
+    pub async fn download(
+        &self,
+        file: &RepoFile,
+        dest: &Path,
+        mut progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<u64, ApiError> {
+        let io_error = |e: std::io::Error| ApiError::InternalError(format!("{}: {}", file.rfilename, e));
+        let partial = self.partial_path(dest);
+        if let Some(dir) = partial.parent() {
+            tokio::fs::create_dir_all(dir).await.map_err(io_error)?;
+        }
+        let mut total = file.total();
+        let mut offset = tokio::fs::metadata(&partial).await.map(|m| m.len()).unwrap_or(0);
+        if total.is_some_and(|total| offset > total) {
+            offset = 0;
+        }
+        if offset > 0 && total == Some(offset) {
+            progress(offset, total);
+            return Ok(0);
+        }
+
+        let fetch_error = |e: reqwest::Error| {
+            ApiError::InternalError(format!("Failed to download {}: {}", file.rfilename, e))
+        };
+        let mut request = self.get(&file.url);
+        if offset > 0 {
+            request = request.header(header::RANGE, format!("bytes={}-", offset));
+        }
+        let mut response = request.send().await.map_err(fetch_error)?;
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // The partial file does not belong to this version; start over.
+            offset = 0;
+            response = self.get(&file.url).send().await.map_err(fetch_error)?;
+        }
+        let mut response = response.error_for_status().map_err(fetch_error)?;
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            offset = 0;
+        }
+        if total.is_none() {
+            total = response.content_length().map(|length| length + offset);
+        }
+
+        let mut out = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(&partial)
+            .await
+            .map_err(io_error)?;
+        let mut transferred = 0;
+        progress(offset, total);
+        while let Some(chunk) = response.chunk().await.map_err(fetch_error)? {
+            out.write_all(&chunk).await.map_err(io_error)?;
+            transferred += chunk.len() as u64;
+            progress(offset + transferred, total);
+        }
+        out.flush().await.map_err(io_error)?;
+
+        if let Some(total) = total {
+            if offset + transferred != total {
+                return Err(ApiError::InternalError(format!(
+                    "Download of {} ended after {} of {} bytes",
+                    file.rfilename,
+                    offset + transferred,
+                    total
+                )));
+            }
+        }
+        Ok(transferred)
+    }
+
+    /// Where a download is kept until it is complete and verified: next to
+    /// `dest`, or with a `cache_dir`, in a directory there named like `dest`'s.
+    pub fn partial_path(&self, dest: &Path) -> PathBuf {
+        let mut name = dest.file_name().unwrap_or_default().to_os_string();
+        name.push(".partial");
+        match &self.cache_dir {
+            Some(cache_dir) => cache_dir
+                .join(dest.parent().and_then(Path::file_name).unwrap_or_default())
+                .join(name),
+            None => dest.with_file_name(name),
+        }
+    }
+
+    /// Checks a finished download against `sha256` and moves it to `dest`. A
+    /// mismatching file is deleted so the next pull starts from scratch.
+    pub async fn finish(&self, dest: &Path, sha256: Option<&str>) -> Result<(), ApiError> {
+        let partial = self.partial_path(dest);
+        if let Some(expected) = sha256 {
+            let actual = sha256_file_async(partial.clone())
+                .await
+                .map_err(|e| ApiError::InternalError(format!("{}: {}", partial.display(), e)))?;
+            if actual.strip_prefix("sha256:") != Some(expected) {
+                let _ = tokio::fs::remove_file(&partial).await;
+                return Err(ApiError::InternalError(format!(
+                    "sha256 mismatch for {}: expected {}, got {}",
+                    dest.display(),
+                    expected,
+                    actual
+                )));
+            }
+        }
+        let dest_path = dest.to_path_buf();
+        tokio::task::spawn_blocking(move || move_file(&partial, &dest_path))
+            .await
+            .map_err(|e| ApiError::Internal(e.to_string()))?
+            .map_err(|e| {
+                ApiError::InternalError(format!("Failed to move {} into place: {}", dest.display(), e))
+            })
+    }
+}
+
+/// The Hugging Face cache other tools download into: `HF_HUB_CACHE`, or
+/// `hub` under `HF_HOME`, `$XDG_CACHE_HOME/huggingface` or `~/.cache/huggingface`.
+pub fn hf_cache_dir() -> Option<PathBuf> {
+    let env = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    if let Some(dir) = env("HF_HUB_CACHE") {
+        return Some(dir);
+    }
+    let home = env("HF_HOME")
+        .or_else(|| env("XDG_CACHE_HOME").map(|cache| cache.join("huggingface")))
+        .or_else(|| env("HOME").map(|home| home.join(".cache").join("huggingface")))?;
+    Some(home.join("hub"))
+}
+
+/// Puts `src` at `dest` without a second copy on disk: a hard link, a reflink
+/// where hard links are not possible but the filesystem shares extents (as
+/// across btrfs subvolumes), or else by moving `src`.
+pub fn place_file(src: &Path, dest: &Path) -> io::Result<Placement> {
+    if fs::hard_link(src, dest).is_ok() {
+        return Ok(Placement::HardLink);
+    }
+    if reflink(src, dest).is_ok() {
+        return Ok(Placement::Reflink);
+    }
+    move_file(src, dest)?;
+    Ok(Placement::Move)
+}
+
+/// Renames `src` to `dest`, or copies and deletes it across filesystems.
+pub fn move_file(src: &Path, dest: &Path) -> io::Result<()> {
+    if fs::rename(src, dest).is_err() {
+        fs::copy(src, dest)?;
+        fs::remove_file(src)?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    /// `FICLONE` from `linux/fs.h`
+    const FICLONE: libc::c_ulong = 0x4004_9409;
+    let from = fs::File::open(src)?;
+    let to = fs::OpenOptions::new().write(true).create_new(true).open(dest)?;
+    // SAFETY: both descriptors are open for the duration of the call.
+    if unsafe { libc::ioctl(to.as_raw_fd(), FICLONE as _, from.as_raw_fd()) } != 0 {
+        let err = io::Error::last_os_error();
+        drop(to);
+        let _ = fs::remove_file(dest);
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_src: &Path, _dest: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// A digest is only usable together with the size it belongs to.
+fn lfs_info(sha256: Option<String>, size: Option<u64>) -> Option<LfsInfo> {
+    sha256.zip(size).map(|(sha256, size)| LfsInfo { sha256, size })
+}
+
+/// Branch names such as `refs/pr/1` are a single path segment in hub URLs.
+fn encode_revision(revision: &str) -> String {
+    revision.replace('/', "%2F")
+}
+
+#[cfg(test)]
+#[path = "hub_test.rs"]
+mod tests;
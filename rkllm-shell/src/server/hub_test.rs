@@ -0,0 +1,334 @@
+//! Tests for registry downloads, against a stand-in registry on localhost
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path as UrlPath, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use sha2::{Digest, Sha256};
+
+use super::*;
+
+const CONTENT: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Requests the stand-in saw: the `Range` and `Authorization` headers of each download
+#[derive(Clone, Default)]
+struct Seen {
+    ranges: Arc<Mutex<Vec<Option<String>>>>,
+    auth: Arc<Mutex<Vec<Option<String>>>>,
+}
+
+fn sha() -> String {
+    format!("{:x}", Sha256::digest(CONTENT))
+}
+
+async fn hf_listing(UrlPath((owner, repo, revision)): UrlPath<(String, String, String)>) -> Response {
+    if owner != "user" || repo != "model" || revision != "main" {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Json(serde_json::json!({
+        "siblings": [
+            { "rfilename": "README.md", "size": 5 },
+            {
+                "rfilename": "model.rkllm",
+                "size": CONTENT.len(),
+                "lfs": { "sha256": sha(), "size": CONTENT.len() }
+            }
+        ]
+    }))
+    .into_response()
+}
+
+async fn modelscope_listing(Query(query): Query<HashMap<String, String>>) -> Response {
+    assert_eq!(query.get("Revision").map(String::as_str), Some("v1"));
+    Json(serde_json::json!({
+        "Code": 200,
+        "Data": { "Files": [
+            { "Name": "configs", "Path": "configs", "Type": "tree", "Size": 0 },
+            { "Name": "model.rkllm", "Path": "model.rkllm", "Type": "blob", "Size": CONTENT.len(), "Sha256": sha() }
+        ] }
+    }))
+    .into_response()
+}
+
+async fn modelscope_file(
+    State(seen): State<Seen>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    assert_eq!(query.get("FilePath").map(String::as_str), Some("model.rkllm"));
+    serve_content(&seen, &headers)
+}
+
+async fn http_index() -> Response {
+    Json(serde_json::json!({
+        "models": [
+            { "name": "qwen2-vl", "files": [
+                { "path": "qwen2-vl/model.rkllm", "size": CONTENT.len(), "sha256": sha() },
+                { "path": "tokenizer.json", "url": "http://elsewhere.invalid/tokenizer.json" }
+            ] },
+            { "name": "qwen2-vl", "revision": "old", "files": [] }
+        ]
+    }))
+    .into_response()
+}
+
+async fn content(State(seen): State<Seen>, headers: HeaderMap) -> Response {
+    serve_content(&seen, &headers)
+}
+
+fn serve_content(seen: &Seen, headers: &HeaderMap) -> Response {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let range = header("range");
+    seen.ranges.lock().unwrap().push(range.clone());
+    seen.auth.lock().unwrap().push(header("authorization"));
+    let start = range
+        .as_deref()
+        .and_then(|r| r.strip_prefix("bytes="))
+        .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok());
+    match start {
+        Some(start) if start >= CONTENT.len() => StatusCode::RANGE_NOT_SATISFIABLE.into_response(),
+        Some(start) => (StatusCode::PARTIAL_CONTENT, CONTENT[start..].to_vec()).into_response(),
+        None => CONTENT.to_vec().into_response(),
+    }
+}
+
+/// Starts the stand-in and returns a client for it, configured by `registry`.
+async fn serve(registry: RegistryConfig) -> (HubClient, Seen) {
+    let seen = Seen::default();
+    let app = Router::new()
+        .route("/api/models/{owner}/{repo}/revision/{revision}", get(hf_listing))
+        .route("/{owner}/{repo}/resolve/main/{*file}", get(content))
+        .route("/api/v1/models/{owner}/{repo}/repo/files", get(modelscope_listing))
+        .route("/api/v1/models/{owner}/{repo}/repo", get(modelscope_file))
+        .route("/index.json", get(http_index))
+        .route("/qwen2-vl/model.rkllm", get(content))
+        .with_state(seen.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    let registry = RegistryConfig {
+        endpoint: Some(format!("http://{}/", addr)),
+        ..registry
+    };
+    (HubClient::from_config(&registry).unwrap(), seen)
+}
+
+async fn serve_hf() -> (HubClient, Seen) {
+    serve(RegistryConfig::default()).await
+}
+
+async fn model_file(client: &HubClient) -> RepoFile {
+    let files = client.list_files("user/model", "main").await.unwrap();
+    files.into_iter().find(|f| f.rfilename == "model.rkllm").unwrap()
+}
+
+#[tokio::test]
+async fn test_partial_path() {
+    let (client, _) = serve_hf().await;
+    assert_eq!(
+        client.partial_path(Path::new("/models/user--model/qwen.rkllm")),
+        PathBuf::from("/models/user--model/qwen.rkllm.partial")
+    );
+
+    let (client, _) = serve(RegistryConfig {
+        cache_dir: Some("/cache".into()),
+        ..RegistryConfig::default()
+    })
+    .await;
+    assert_eq!(
+        client.partial_path(Path::new("/models/user--model/qwen.rkllm")),
+        PathBuf::from("/cache/user--model/qwen.rkllm.partial")
+    );
+}
+
+#[tokio::test]
+async fn test_list_files() {
+    let (client, _) = serve_hf().await;
+    let files = client.list_files("user/model", "main").await.unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].total(), Some(5));
+    assert_eq!(files[0].sha256(), None);
+    assert_eq!(files[1].total(), Some(CONTENT.len() as u64));
+    assert_eq!(files[1].sha256().map(str::len), Some(64));
+    assert!(files[1].url.ends_with("/user/model/resolve/main/model.rkllm"));
+
+    let err = client.list_files("user/missing", "main").await.unwrap_err();
+    assert!(matches!(err, ApiError::ModelNotFound(_)));
+}
+
+#[tokio::test]
+async fn test_download_resumes_partial_file() {
+    let (client, seen) = serve_hf().await;
+    let file = model_file(&client).await;
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("model.rkllm");
+    std::fs::write(client.partial_path(&dest), &CONTENT[..10]).unwrap();
+
+    let mut reports = Vec::new();
+    let transferred = client
+        .download(&file, &dest, |completed, total| reports.push((completed, total)))
+        .await
+        .unwrap();
+    assert_eq!(transferred, CONTENT.len() as u64 - 10);
+    assert_eq!(*seen.ranges.lock().unwrap(), vec![Some("bytes=10-".to_string())]);
+    assert_eq!(reports.first(), Some(&(10, Some(CONTENT.len() as u64))));
+    assert_eq!(reports.last(), Some(&(CONTENT.len() as u64, Some(CONTENT.len() as u64))));
+
+    client.finish(&dest, file.sha256()).await.unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), CONTENT);
+    assert!(!client.partial_path(&dest).exists());
+}
+
+#[tokio::test]
+async fn test_complete_partial_file_is_not_fetched_again() {
+    let (client, seen) = serve_hf().await;
+    let file = model_file(&client).await;
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("model.rkllm");
+    std::fs::write(client.partial_path(&dest), CONTENT).unwrap();
+
+    let transferred = client.download(&file, &dest, |_, _| {}).await.unwrap();
+    assert_eq!(transferred, 0);
+    assert!(seen.ranges.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_digest_mismatch_discards_download() {
+    let (client, _) = serve_hf().await;
+    let file = model_file(&client).await;
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("model.rkllm");
+    // Same length, different bytes: resuming cannot tell, verifying must.
+    std::fs::write(client.partial_path(&dest), vec![b'x'; CONTENT.len()]).unwrap();
+
+    client.download(&file, &dest, |_, _| {}).await.unwrap();
+    let err = client.finish(&dest, file.sha256()).await.unwrap_err();
+    assert!(matches!(err, ApiError::InternalError(msg) if msg.contains("sha256 mismatch")));
+    assert!(!client.partial_path(&dest).exists());
+    assert!(!dest.exists());
+}
+
+#[tokio::test]
+async fn test_modelscope_registry() {
+    let (client, seen) = serve(RegistryConfig {
+        kind: RegistryKind::Modelscope,
+        token: Some("secret".into()),
+        ..RegistryConfig::default()
+    })
+    .await;
+    let files = client.list_files("user/model", "v1").await.unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].sha256(), Some(sha().as_str()));
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("model.rkllm");
+    client.download(&files[0], &dest, |_, _| {}).await.unwrap();
+    client.finish(&dest, files[0].sha256()).await.unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), CONTENT);
+    assert_eq!(*seen.auth.lock().unwrap(), vec![Some("Bearer secret".to_string())]);
+}
+
+#[tokio::test]
+async fn test_http_index_registry() {
+    let cache = tempfile::tempdir().unwrap();
+    let (client, seen) = serve(RegistryConfig {
+        kind: RegistryKind::Http,
+        cache_dir: Some(cache.path().to_path_buf()),
+        ..RegistryConfig::default()
+    })
+    .await;
+    let files = client.list_files("qwen2-vl", "main").await.unwrap();
+    assert_eq!(files[0].rfilename, "qwen2-vl/model.rkllm");
+    assert_eq!(files[1].url, "http://elsewhere.invalid/tokenizer.json");
+    assert_eq!(files[1].sha256(), None);
+    assert!(client.list_files("qwen2-vl", "old").await.unwrap().is_empty());
+    assert!(matches!(
+        client.list_files("missing", "main").await,
+        Err(ApiError::ModelNotFound(_))
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("model.rkllm");
+    client.download(&files[0], &dest, |_, _| {}).await.unwrap();
+    assert!(client.partial_path(&dest).starts_with(cache.path()));
+    client.finish(&dest, files[0].sha256()).await.unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), CONTENT);
+    assert_eq!(seen.ranges.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_token_only_goes_to_the_registry_host() {
+    let client = HubClient::from_config(&RegistryConfig {
+        endpoint: Some("https://huggingface.co".into()),
+        token: Some("secret".into()),
+        ..RegistryConfig::default()
+    })
+    .unwrap();
+    let auth = |url: &str| {
+        let request = client.get(url).build().unwrap();
+        request.headers().get(header::AUTHORIZATION).cloned()
+    };
+    assert!(auth("https://huggingface.co/api/models/user/model").is_some());
+    assert!(auth("https://huggingface.co:443/user/model/resolve/main/model.rkllm").is_some());
+    // Hosts that only share the endpoint's prefix, or another scheme or port
+    assert!(auth("https://huggingface.co.example.com/model.rkllm").is_none());
+    assert!(auth("https://huggingface.com/model.rkllm").is_none());
+    assert!(auth("http://huggingface.co/model.rkllm").is_none());
+    assert!(auth("https://huggingface.co:8443/model.rkllm").is_none());
+}
+
+#[test]
+fn test_http_registry_needs_an_endpoint() {
+    let registry = RegistryConfig {
+        name: "store".into(),
+        kind: RegistryKind::Http,
+        ..RegistryConfig::default()
+    };
+    assert!(matches!(HubClient::from_config(&registry), Err(ApiError::InvalidRequest(_))));
+}
+
+#[tokio::test]
+async fn test_files_in_the_hf_cache_are_linked_not_downloaded() {
+    let cache = tempfile::tempdir().unwrap();
+    let blobs = cache.path().join("models--user--model").join("blobs");
+    std::fs::create_dir_all(&blobs).unwrap();
+    std::fs::write(blobs.join(sha()), CONTENT).unwrap();
+    let (client, seen) = serve_hf().await;
+    let client = client.with_hf_cache(Some(cache.path().to_path_buf()));
+    let file = model_file(&client).await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("model.rkllm");
+    std::fs::write(&dest, b"stale").unwrap();
+    let placement = client.reuse_cached("user/model", &file, &dest).await.unwrap();
+    assert_eq!(placement, Some(Placement::HardLink));
+    assert_eq!(std::fs::read(&dest).unwrap(), CONTENT);
+    assert!(seen.ranges.lock().unwrap().is_empty());
+
+    // Another repository, or a blob of the wrong size, is not reused.
+    assert_eq!(client.reuse_cached("user/other", &file, &dest).await.unwrap(), None);
+    std::fs::write(blobs.join(sha()), b"short").unwrap();
+    assert_eq!(client.cached_blob("user/model", &file).await, None);
+}
+
+#[test]
+fn test_place_file_moves_as_a_last_resort() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("blob");
+    std::fs::write(&src, CONTENT).unwrap();
+    let dest = dir.path().join("model.rkllm");
+    assert_eq!(place_file(&src, &dest).unwrap(), Placement::HardLink);
+
+    // An existing `dest` defeats linking and cloning, but not a rename.
+    let other = dir.path().join("other");
+    std::fs::write(&other, b"other").unwrap();
+    assert_eq!(place_file(&other, &dest).unwrap(), Placement::Move);
+    assert!(!other.exists());
+    assert_eq!(std::fs::read(&dest).unwrap(), b"other");
+}
@@ -0,0 +1,197 @@
+//! Resolving OpenAI `image_url` and `video_url` parts to inline data
+//!
+//! `data:` URLs and bare base64 pass through. `http(s)://` and `file://` URLs
+//! are fetched only from the hosts and directories allowed in `image_urls`,
+//! and never more than `limits.max_image_bytes` (`max_video_bytes` for videos).
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::Url;
+
+use crate::config::{ImageUrlConfig, LimitsConfig};
+use crate::server::api_models::openai::{OpenAiContent, OpenAiContentPart};
+use crate::server::api_models::OpenAiChatRequest;
+use crate::server::apis::error::ApiError;
+
+/// What a URL points at, for the size limit and error messages
+#[derive(Clone, Copy)]
+struct Media {
+    field: &'static str,
+    kind: &'static str,
+    max_bytes: usize,
+}
+
+pub struct ImageUrlResolver<'a> {
+    config: &'a ImageUrlConfig,
+    image: Media,
+    video: Media,
+    client: Option<reqwest::Client>,
+}
+
+impl<'a> ImageUrlResolver<'a> {
+    pub fn new(config: &'a ImageUrlConfig, limits: &LimitsConfig) -> Self {
+        Self {
+            config,
+            image: Media {
+                field: "image_url",
+                kind: "image",
+                max_bytes: limits.max_image_bytes,
+            },
+            video: Media {
+                field: "video_url",
+                kind: "video",
+                max_bytes: limits.max_video_bytes,
+            },
+            client: None,
+        }
+    }
+
+    /// Replaces every remote or local image and video URL in `request` with a `data:` URL.
+    pub async fn resolve_request(&mut self, request: &mut OpenAiChatRequest) -> Result<(), ApiError> {
+        for message in &mut request.messages {
+            let OpenAiContent::Array(parts) = &mut message.content else {
+                continue;
+            };
+            for part in parts {
+                let (url, media) = match part {
+                    OpenAiContentPart::ImageUrl { image_url } => (&mut image_url.url, self.image),
+                    OpenAiContentPart::VideoUrl { video_url } => (&mut video_url.url, self.video),
+                    OpenAiContentPart::Text { .. } => continue,
+                };
+                if let Some(data) = self.resolve_media(url, media).await? {
+                    *url = format!("data:;base64,{}", data);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The base64 data behind an image `url`, or `None` when it is already inline.
+    pub async fn resolve(&mut self, url: &str) -> Result<Option<String>, ApiError> {
+        self.resolve_media(url, self.image).await
+    }
+
+    async fn resolve_media(&mut self, url: &str, media: Media) -> Result<Option<String>, ApiError> {
+        let bytes = match url.split_once("://").map(|(scheme, _)| scheme.to_ascii_lowercase()) {
+            Some(scheme) if scheme == "http" || scheme == "https" => self.fetch(url, media).await?,
+            Some(scheme) if scheme == "file" => self.read_file(url, media).await?,
+            Some(scheme) => {
+                return Err(ApiError::InvalidRequest(format!(
+                    "{}: unsupported URL scheme '{}'",
+                    media.field, scheme
+                )))
+            }
+            // data: URLs and bare base64
+            None => return Ok(None),
+        };
+        Ok(Some(general_purpose::STANDARD.encode(bytes)))
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.config.allow_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host.ends_with(&format!(".{}", domain)),
+                None => host == allowed,
+            }
+        })
+    }
+
+    fn too_large(media: Media, size: usize) -> ApiError {
+        ApiError::InvalidRequest(format!(
+            "{}: {} is {} bytes, the limit is {}",
+            media.field, media.kind, size, media.max_bytes
+        ))
+    }
+
+    async fn fetch(&mut self, url: &str, media: Media) -> Result<Vec<u8>, ApiError> {
+        let parsed = Url::parse(url)
+            .map_err(|e| ApiError::InvalidRequest(format!("{}: invalid URL: {}", media.field, e)))?;
+        let host = parsed.host_str().unwrap_or_default();
+        if !self.host_allowed(host) {
+            return Err(ApiError::PermissionDenied(format!(
+                "{}: host '{}' is not in image_urls.allow_hosts",
+                media.field, host
+            )));
+        }
+
+        let timeout = Duration::from_secs(self.config.fetch_timeout_secs);
+        let client = self.client.get_or_insert_with(|| {
+            reqwest::Client::builder()
+                .timeout(timeout)
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default()
+        });
+        let fetch_error = |e: reqwest::Error| {
+            ApiError::InvalidRequest(format!("{}: failed to fetch {}: {}", media.field, parsed, e))
+        };
+        let mut response = client
+            .get(parsed.clone())
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(fetch_error)?;
+        if let Some(length) = response.content_length() {
+            if length as usize > media.max_bytes {
+                return Err(Self::too_large(media, length as usize));
+            }
+        }
+        // The length header is optional, so count while reading as well.
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(fetch_error)? {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > media.max_bytes {
+                return Err(Self::too_large(media, bytes.len()));
+            }
+        }
+        Ok(bytes)
+    }
+
+    async fn read_file(&self, url: &str, media: Media) -> Result<Vec<u8>, ApiError> {
+        let path = Url::parse(url)
+            .ok()
+            .and_then(|u| u.to_file_path().ok())
+            .ok_or_else(|| ApiError::InvalidRequest(format!("{}: invalid file URL", media.field)))?;
+        let denied = || {
+            ApiError::PermissionDenied(format!(
+                "{}: {} is not under a directory in image_urls.allow_paths",
+                media.field,
+                path.display()
+            ))
+        };
+        // Canonicalise first so `..` and symlinks cannot leave the allowed directories.
+        let path = tokio::fs::canonicalize(&path).await.map_err(|_| denied())?;
+        if !allowed_path(&path, &self.config.allow_paths).await {
+            return Err(denied());
+        }
+        let size = tokio::fs::metadata(&path)
+            .await
+            .map_err(|e| ApiError::InvalidRequest(format!("{}: {}", media.field, e)))?
+            .len() as usize;
+        if size > media.max_bytes {
+            return Err(Self::too_large(media, size));
+        }
+        tokio::fs::read(&path)
+            .await
+            .map_err(|e| ApiError::InvalidRequest(format!("{}: {}", media.field, e)))
+    }
+}
+
+async fn allowed_path(path: &Path, allowed: &[PathBuf]) -> bool {
+    for dir in allowed {
+        if let Ok(dir) = tokio::fs::canonicalize(dir).await {
+            if path.starts_with(&dir) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+#[path = "image_urls_test.rs"]
+mod tests;
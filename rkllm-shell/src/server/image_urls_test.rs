@@ -0,0 +1,129 @@
+//! Tests for image URL resolution
+
+use super::*;
+
+fn resolver(config: &ImageUrlConfig) -> ImageUrlResolver<'_> {
+    ImageUrlResolver::new(config, &LimitsConfig::default())
+}
+
+#[tokio::test]
+async fn test_inline_images_pass_through() {
+    let config = ImageUrlConfig::default();
+    let mut resolver = resolver(&config);
+    assert_eq!(resolver.resolve("data:image/png;base64,AAAA").await.unwrap(), None);
+    assert_eq!(resolver.resolve("AAAA").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_remote_images_need_allowed_host() {
+    let config = ImageUrlConfig::default();
+    let err = resolver(&config)
+        .resolve("https://example.com/cat.png")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ApiError::PermissionDenied(msg) if msg.contains("example.com")));
+
+    let err = resolver(&config).resolve("ftp://example.com/cat.png").await.unwrap_err();
+    assert!(matches!(err, ApiError::InvalidRequest(msg) if msg.contains("unsupported URL scheme")));
+}
+
+#[test]
+fn test_host_allow_list() {
+    let config = ImageUrlConfig {
+        allow_hosts: vec!["images.example.com".into(), "*.cdn.example.net".into()],
+        ..ImageUrlConfig::default()
+    };
+    let resolver = resolver(&config);
+    assert!(resolver.host_allowed("images.example.com"));
+    assert!(resolver.host_allowed("eu.cdn.example.net"));
+    assert!(!resolver.host_allowed("cdn.example.net"));
+    assert!(!resolver.host_allowed("example.com"));
+}
+
+#[tokio::test]
+async fn test_file_urls_limited_to_allowed_paths() {
+    let allowed = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    std::fs::write(allowed.path().join("cat.png"), b"png bytes").unwrap();
+    std::fs::write(outside.path().join("secret.png"), b"secret").unwrap();
+    let config = ImageUrlConfig {
+        allow_paths: vec![allowed.path().to_path_buf()],
+        ..ImageUrlConfig::default()
+    };
+
+    let url = Url::from_file_path(allowed.path().join("cat.png")).unwrap();
+    let data = resolver(&config).resolve(url.as_str()).await.unwrap().unwrap();
+    assert_eq!(general_purpose::STANDARD.decode(data).unwrap(), b"png bytes");
+
+    let url = Url::from_file_path(outside.path().join("secret.png")).unwrap();
+    let err = resolver(&config).resolve(url.as_str()).await.unwrap_err();
+    assert!(matches!(err, ApiError::PermissionDenied(_)));
+
+    // `..` cannot climb out of an allowed directory.
+    let escape = format!(
+        "{}/../{}/secret.png",
+        url_dir(allowed.path()),
+        outside.path().file_name().unwrap().to_string_lossy()
+    );
+    let err = resolver(&config).resolve(&escape).await.unwrap_err();
+    assert!(matches!(err, ApiError::PermissionDenied(_)));
+}
+
+#[tokio::test]
+async fn test_file_size_cap() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("big.png"), vec![0u8; 64]).unwrap();
+    let config = ImageUrlConfig {
+        allow_paths: vec![dir.path().to_path_buf()],
+        ..ImageUrlConfig::default()
+    };
+    let limits = LimitsConfig {
+        max_image_bytes: 16,
+        ..LimitsConfig::default()
+    };
+
+    let url = Url::from_file_path(dir.path().join("big.png")).unwrap();
+    let err = ImageUrlResolver::new(&config, &limits)
+        .resolve(url.as_str())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ApiError::InvalidRequest(msg) if msg.contains("64 bytes")));
+}
+
+#[tokio::test]
+async fn test_video_urls_use_the_video_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("clip.mp4"), vec![0u8; 64]).unwrap();
+    let config = ImageUrlConfig {
+        allow_paths: vec![dir.path().to_path_buf()],
+        ..ImageUrlConfig::default()
+    };
+    let limits = LimitsConfig {
+        max_image_bytes: 16,
+        max_video_bytes: 128,
+        ..LimitsConfig::default()
+    };
+    let url = Url::from_file_path(dir.path().join("clip.mp4")).unwrap();
+    let mut request = crate::server::test_helpers::OpenAiChatRequestBuilder::new("test-model")
+        .message("user", "")
+        .build();
+    request.messages[0].content = OpenAiContent::Array(vec![OpenAiContentPart::VideoUrl {
+        video_url: crate::server::api_models::openai::OpenAiVideoUrl { url: url.to_string() },
+    }]);
+
+    ImageUrlResolver::new(&config, &limits)
+        .resolve_request(&mut request)
+        .await
+        .unwrap();
+    let OpenAiContent::Array(parts) = &request.messages[0].content else {
+        panic!("content should stay an array");
+    };
+    let OpenAiContentPart::VideoUrl { video_url } = &parts[0] else {
+        panic!("part should stay a video");
+    };
+    assert_eq!(video_url.url, format!("data:;base64,{}", general_purpose::STANDARD.encode([0u8; 64])));
+}
+
+fn url_dir(path: &Path) -> String {
+    Url::from_directory_path(path).unwrap().as_str().trim_end_matches('/').to_string()
+}
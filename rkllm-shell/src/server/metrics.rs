@@ -0,0 +1,264 @@
+//! Prometheus metrics for inference requests and the model lifecycle
+//!
+//! A `Metrics` instance owns its own registry, so tests can create one per
+//! runtime and inspect the rendered output without global state.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub time_to_first_token: HistogramVec,
+    pub request_duration: HistogramVec,
+    pub tokens_per_second: HistogramVec,
+    pub prompt_tokens: IntCounter,
+    pub completion_tokens: IntCounterVec,
+    pub cancelled_requests: IntCounterVec,
+    pub model_loads: IntCounterVec,
+    pub model_load_duration: Histogram,
+    pub model_unloads: IntCounterVec,
+    pub model_unload_duration: Histogram,
+    pub loaded_models: IntGauge,
+    pub queue_depth: IntGauge,
+    pub pull_bytes: IntCounter,
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics").finish_non_exhaustive()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn counter_vec(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let metric = IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric");
+    registry.register(Box::new(metric.clone())).expect("unique metric");
+    metric
+}
+
+fn counter(registry: &Registry, name: &str, help: &str) -> IntCounter {
+    let metric = IntCounter::new(name, help).expect("valid metric");
+    registry.register(Box::new(metric.clone())).expect("unique metric");
+    metric
+}
+
+fn gauge(registry: &Registry, name: &str, help: &str) -> IntGauge {
+    let metric = IntGauge::new(name, help).expect("valid metric");
+    registry.register(Box::new(metric.clone())).expect("unique metric");
+    metric
+}
+
+fn histogram_vec(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    buckets: &[f64],
+    labels: &[&str],
+) -> HistogramVec {
+    let opts = HistogramOpts::new(name, help).buckets(buckets.to_vec());
+    let metric = HistogramVec::new(opts, labels).expect("valid metric");
+    registry.register(Box::new(metric.clone())).expect("unique metric");
+    metric
+}
+
+fn histogram(registry: &Registry, name: &str, help: &str, buckets: &[f64]) -> Histogram {
+    let opts = HistogramOpts::new(name, help).buckets(buckets.to_vec());
+    let metric = Histogram::with_opts(opts).expect("valid metric");
+    registry.register(Box::new(metric.clone())).expect("unique metric");
+    metric
+}
+
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+const TOKENS_PER_SECOND_BUCKETS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 15.0, 20.0, 30.0, 50.0, 100.0];
+const LOAD_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0];
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        Self {
+            http_requests: counter_vec(
+                &registry,
+                "rkllm_http_requests_total",
+                "HTTP requests by route and status code",
+                &["route", "status"],
+            ),
+            time_to_first_token: histogram_vec(
+                &registry,
+                "rkllm_time_to_first_token_seconds",
+                "Time from request start to the first generated token",
+                LATENCY_BUCKETS,
+                &["route"],
+            ),
+            request_duration: histogram_vec(
+                &registry,
+                "rkllm_request_duration_seconds",
+                "Time from request start to the last generated token",
+                LATENCY_BUCKETS,
+                &["route"],
+            ),
+            tokens_per_second: histogram_vec(
+                &registry,
+                "rkllm_tokens_per_second",
+                "Generation speed after the first token",
+                TOKENS_PER_SECOND_BUCKETS,
+                &["route"],
+            ),
+            prompt_tokens: counter(
+                &registry,
+                "rkllm_prompt_tokens_total",
+                "Prompt tokens prefilled by the NPU",
+            ),
+            completion_tokens: counter_vec(
+                &registry,
+                "rkllm_completion_tokens_total",
+                "Tokens generated",
+                &["route"],
+            ),
+            cancelled_requests: counter_vec(
+                &registry,
+                "rkllm_cancelled_requests_total",
+                "Requests whose client went away before generation finished",
+                &["route"],
+            ),
+            model_loads: counter_vec(
+                &registry,
+                "rkllm_model_loads_total",
+                "Model loads by result",
+                &["result"],
+            ),
+            model_load_duration: histogram(
+                &registry,
+                "rkllm_model_load_duration_seconds",
+                "Time spent in rkllm_init",
+                LOAD_BUCKETS,
+            ),
+            model_unloads: counter_vec(
+                &registry,
+                "rkllm_model_unloads_total",
+                "Model unloads by reason",
+                &["reason"],
+            ),
+            model_unload_duration: histogram(
+                &registry,
+                "rkllm_model_unload_duration_seconds",
+                "Time spent releasing a model",
+                LOAD_BUCKETS,
+            ),
+            loaded_models: gauge(&registry, "rkllm_loaded_models", "Models currently loaded"),
+            queue_depth: gauge(
+                &registry,
+                "rkllm_queue_depth",
+                "Inferences waiting for a busy model",
+            ),
+            pull_bytes: counter(
+                &registry,
+                "rkllm_pull_bytes_total",
+                "Bytes downloaded by /api/pull",
+            ),
+            registry,
+        }
+    }
+
+    pub fn record_load(&self, duration: Duration, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.model_loads.with_label_values(&[result]).inc();
+        self.model_load_duration.observe(duration.as_secs_f64());
+    }
+
+    pub fn record_unload(&self, duration: Duration, reason: &str) {
+        self.model_unloads.with_label_values(&[reason]).inc();
+        self.model_unload_duration.observe(duration.as_secs_f64());
+    }
+
+    /// Forwards tokens from `rx`, recording latency and token metrics for
+    /// `route` once generation finishes, or a cancellation if the consumer
+    /// goes away first.
+    pub fn instrument(
+        &self,
+        route: &'static str,
+        started: Instant,
+        mut rx: UnboundedReceiver<String>,
+    ) -> UnboundedReceiver<String> {
+        let (tx, out) = mpsc::unbounded_channel();
+        let metrics = self.clone();
+        tokio::spawn(async move {
+            let mut first_token_at = None;
+            let mut tokens = 0u64;
+            while let Some(token) = rx.recv().await {
+                if first_token_at.is_none() {
+                    let now = Instant::now();
+                    metrics
+                        .time_to_first_token
+                        .with_label_values(&[route])
+                        .observe((now - started).as_secs_f64());
+                    first_token_at = Some(now);
+                }
+                tokens += 1;
+                if tx.send(token).is_err() {
+                    metrics.cancelled_requests.with_label_values(&[route]).inc();
+                    metrics.completion_tokens.with_label_values(&[route]).inc_by(tokens);
+                    return;
+                }
+            }
+
+            let finished = Instant::now();
+            metrics
+                .request_duration
+                .with_label_values(&[route])
+                .observe((finished - started).as_secs_f64());
+            metrics.completion_tokens.with_label_values(&[route]).inc_by(tokens);
+            if let Some(first) = first_token_at {
+                let generating = (finished - first).as_secs_f64();
+                if tokens > 1 && generating > 0.0 {
+                    metrics
+                        .tokens_per_second
+                        .with_label_values(&[route])
+                        .observe((tokens - 1) as f64 / generating);
+                }
+            }
+        });
+        out
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        TextEncoder::new().encode_to_string(&self.registry.gather())
+    }
+}
+
+/// Middleware counting requests by matched route and response status.
+pub async fn track_requests(State(metrics): State<Metrics>, request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let response = next.run(request).await;
+    metrics
+        .http_requests
+        .with_label_values(&[route.as_str(), response.status().as_str()])
+        .inc();
+    response
+}
+
+#[cfg(test)]
+#[path = "metrics_test.rs"]
+mod tests;
@@ -0,0 +1,71 @@
+use super::*;
+use crate::server::mock_runtime::{MockModel, MockRuntime};
+use crate::server::rkllm_runtime::CompletionRequest;
+use crate::server::runtime_trait::{ModelHandle, ModelRuntime};
+use crate::server::test_helpers::GenerateRequestBuilder;
+use axum::{routing::get, Router};
+use axum_test::TestServer;
+
+#[tokio::test]
+async fn test_mock_runtime_records_model_lifecycle() {
+    let runtime = MockRuntime::new();
+    let request = CompletionRequest::Generate(GenerateRequestBuilder::new("test-model", "hi").build());
+
+    let model = runtime.get_or_load_model(&request).await.unwrap();
+    runtime.unload_model(&model.model_info().key).await.unwrap();
+
+    let metrics = runtime.metrics();
+    assert_eq!(metrics.model_loads.with_label_values(&["success"]).get(), 1);
+    assert_eq!(metrics.model_unloads.with_label_values(&["explicit"]).get(), 1);
+    assert_eq!(metrics.model_load_duration.get_sample_count(), 1);
+
+    let rendered = metrics.render().unwrap();
+    assert!(rendered.contains("rkllm_model_loads_total{result=\"success\"} 1"));
+}
+
+#[tokio::test]
+async fn test_instrument_counts_tokens() {
+    let metrics = Metrics::new();
+    let model = MockModel::with_responses(vec!["Hello".into(), " world".into(), "!".into()]);
+
+    let mut rx = metrics.instrument("/api/generate", Instant::now(), model.run_inference(vec![]));
+    let mut text = String::new();
+    while let Some(token) = rx.recv().await {
+        text.push_str(&token);
+    }
+    // Give the forwarding task a moment to record after closing the channel.
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    assert_eq!(text, "Hello world!");
+    assert_eq!(metrics.completion_tokens.with_label_values(&["/api/generate"]).get(), 3);
+    assert_eq!(metrics.time_to_first_token.with_label_values(&["/api/generate"]).get_sample_count(), 1);
+    assert_eq!(metrics.request_duration.with_label_values(&["/api/generate"]).get_sample_count(), 1);
+    assert_eq!(metrics.cancelled_requests.with_label_values(&["/api/generate"]).get(), 0);
+}
+
+#[tokio::test]
+async fn test_instrument_records_cancellation() {
+    let metrics = Metrics::new();
+    let model = MockModel::with_responses(vec!["a".into(), "b".into(), "c".into()]);
+
+    let rx = metrics.instrument("/api/chat", Instant::now(), model.run_inference(vec![]));
+    drop(rx);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    assert_eq!(metrics.cancelled_requests.with_label_values(&["/api/chat"]).get(), 1);
+    assert_eq!(metrics.request_duration.with_label_values(&["/api/chat"]).get_sample_count(), 0);
+}
+
+#[tokio::test]
+async fn test_track_requests_by_route_and_status() {
+    let metrics = Metrics::new();
+    let app = Router::new()
+        .route("/ok", get(|| async { "OK" }))
+        .route_layer(axum::middleware::from_fn_with_state(metrics.clone(), track_requests));
+    let server = TestServer::new(app);
+
+    server.get("/ok").await;
+    server.get("/ok").await;
+
+    assert_eq!(metrics.http_requests.with_label_values(&["/ok", "200"]).get(), 2);
+}
@@ -3,8 +3,9 @@
 //! This module provides a fully functional mock implementation of the
 //! ModelRuntime and ModelHandle traits for unit and integration testing.
 
-use super::runtime_trait::{ModelHandle, ModelInfo, ModelRuntime, RuntimeError};
+use super::runtime_trait::{ModelHandle, ModelInfo, ModelRuntime, RuntimeError, Tokenize};
 use crate::error::Result;
+use crate::server::metrics::Metrics;
 use crate::server::rkllm_runtime::CompletionRequest;
 use async_trait::async_trait;
 use std::collections::HashMap;
@@ -51,6 +52,7 @@ struct MockModelEntry {
     quantization: String,
     size_bytes: u64,
     loaded_at: SystemTime,
+    last_used: SystemTime,
     responses: Vec<String>,
     should_error: bool,
     error_msg: String,
@@ -62,6 +64,7 @@ pub struct MockRuntime {
     config: MockRuntimeConfig,
     models: Arc<Mutex<HashMap<String, MockModelEntry>>>,
     models_path: PathBuf,
+    metrics: Metrics,
 }
 
 impl MockRuntime {
@@ -76,9 +79,15 @@ impl MockRuntime {
             config,
             models: Arc::new(Mutex::new(HashMap::new())),
             models_path: PathBuf::from("./mock_models"),
+            metrics: Metrics::new(),
         }
     }
 
+    /// Metrics recorded by this runtime's model lifecycle
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
     /// Create a mock runtime with a specific models path
     pub fn with_models_path(models_path: PathBuf) -> Self {
         let mut runtime = Self::new();
@@ -99,6 +108,7 @@ impl MockRuntime {
             quantization: "W4A16".into(),
             size_bytes: 1024 * 1024 * 500, // 500 MB
             loaded_at: SystemTime::now(),
+            last_used: SystemTime::now(),
             responses,
             should_error: false,
             error_msg: String::new(),
@@ -127,12 +137,14 @@ impl Default for MockRuntime {
 impl ModelRuntime for MockRuntime {
     async fn get_or_load_model(&self, request: &CompletionRequest) -> Result<Arc<dyn ModelHandle>> {
         // Simulate load delay
+        let load_started = std::time::Instant::now();
         if self.config.load_delay > Duration::ZERO {
             tokio::time::sleep(self.config.load_delay).await;
         }
 
         // Check if should fail
         if self.config.should_fail_load {
+            self.metrics.record_load(load_started.elapsed(), false);
             return Err(crate::error::Error::Server(
                 self.config.load_error_msg.clone(),
             ));
@@ -143,8 +155,9 @@ impl ModelRuntime for MockRuntime {
 
         // Check if already loaded
         {
-            let models = self.models.lock().unwrap();
-            if let Some(entry) = models.get(&model_key) {
+            let mut models = self.models.lock().unwrap();
+            if let Some(entry) = models.get_mut(&model_key) {
+                entry.last_used = SystemTime::now();
                 return Ok(Arc::new(MockModel::from_entry(entry.clone())));
             }
         }
@@ -157,12 +170,14 @@ impl ModelRuntime for MockRuntime {
             quantization: detect_quantization(&model_path),
             size_bytes: 1024 * 1024 * 500,
             loaded_at: SystemTime::now(),
+            last_used: SystemTime::now(),
             responses: self.config.default_responses.clone(),
             should_error: self.config.should_error_inference,
             error_msg: self.config.inference_error_msg.clone(),
         };
 
         self.models.lock().unwrap().insert(model_key.clone(), entry.clone());
+        self.metrics.record_load(load_started.elapsed(), true);
 
         Ok(Arc::new(MockModel::from_entry(entry)))
     }
@@ -177,6 +192,10 @@ impl ModelRuntime for MockRuntime {
                 quantization: entry.quantization.clone(),
                 size_bytes: entry.size_bytes,
                 loaded_at: entry.loaded_at,
+                last_used: entry.last_used,
+                keep_alive: Duration::from_secs(300),
+                in_flight: 0,
+                queue_depth: 0,
             })
             .collect()
     }
@@ -184,6 +203,7 @@ impl ModelRuntime for MockRuntime {
     async fn unload_model(&self, model_key: &str) -> Result<()> {
         let mut models = self.models.lock().unwrap();
         if models.remove(model_key).is_some() {
+            self.metrics.record_unload(Duration::ZERO, "explicit");
             Ok(())
         } else {
             Err(crate::error::Error::Server(format!(
@@ -193,6 +213,13 @@ impl ModelRuntime for MockRuntime {
         }
     }
 
+    async fn tokenizer(&self, _model: &str) -> std::result::Result<Arc<dyn Tokenize>, RuntimeError> {
+        if self.config.should_fail_load {
+            return Err(RuntimeError::LoadFailed(self.config.load_error_msg.clone()));
+        }
+        Ok(Arc::new(MockTokenizer))
+    }
+
     fn models_path(&self) -> &std::path::Path {
         &self.models_path
     }
@@ -261,6 +288,7 @@ impl MockModel {
             quantization: "W4A16".into(),
             size_bytes: 1024 * 1024 * 500,
             loaded_at: SystemTime::now(),
+            last_used: SystemTime::now(),
             responses,
             should_error: false,
             error_msg: String::new(),
@@ -276,6 +304,7 @@ impl MockModel {
             quantization: "W4A16".into(),
             size_bytes: 1024 * 1024 * 500,
             loaded_at: SystemTime::now(),
+            last_used: SystemTime::now(),
             responses: vec![],
             should_error: true,
             error_msg,
@@ -308,13 +337,16 @@ impl ModelHandle for MockModel {
         rx
     }
 
-    fn run_multimodal_inference(
+    async fn run_multimodal_inference(
         &self,
         _prompt: String,
         _images: Vec<String>,
-    ) -> mpsc::UnboundedReceiver<String> {
-        // For mock, treat multimodal same as regular inference
-        self.run_inference(vec![])
+    ) -> std::result::Result<mpsc::UnboundedReceiver<String>, RuntimeError> {
+        // Unlike text inference, a failing multimodal run never starts streaming
+        if self.entry.should_error {
+            return Err(RuntimeError::Internal(self.entry.error_msg.clone()));
+        }
+        Ok(self.run_inference(vec![]))
     }
 
     fn keep_alive(&self) -> Duration {
@@ -328,10 +360,44 @@ impl ModelHandle for MockModel {
             quantization: self.entry.quantization.clone(),
             size_bytes: self.entry.size_bytes,
             loaded_at: self.entry.loaded_at,
+            last_used: self.entry.last_used,
+            keep_alive: self.keep_alive(),
+            in_flight: 0,
+            queue_depth: 0,
         }
     }
 }
 
+/// Deterministic byte-level tokenizer: every UTF-8 byte is one token whose id
+/// is the byte value.
+#[derive(Debug, Clone, Copy)]
+pub struct MockTokenizer;
+
+impl Tokenize for MockTokenizer {
+    fn encode(&self, text: &str) -> std::result::Result<Vec<u32>, RuntimeError> {
+        Ok(text.bytes().map(u32::from).collect())
+    }
+
+    fn decode(&self, ids: &[u32]) -> std::result::Result<String, RuntimeError> {
+        let bytes = ids
+            .iter()
+            .map(|&id| {
+                u8::try_from(id)
+                    .map_err(|_| RuntimeError::Internal(format!("unknown token id {}", id)))
+            })
+            .collect::<std::result::Result<Vec<u8>, _>>()?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn count(&self, text: &str) -> usize {
+        text.len()
+    }
+
+    fn is_exact(&self) -> bool {
+        true
+    }
+}
+
 /// Helper function to detect quantization from filename
 fn detect_quantization(filename: &str) -> String {
     let upper = filename.to_uppercase();
@@ -444,6 +510,7 @@ mod tests {
             top_p: 0.9,
             max_tokens: None,
             keep_alive: Duration::from_secs(300),
+            think: false,
             options: crate::server::defaults::default_model_options(),
         });
 
@@ -466,6 +533,7 @@ mod tests {
             top_p: 0.9,
             max_tokens: None,
             keep_alive: Duration::from_secs(300),
+            think: false,
             options: crate::server::defaults::default_model_options(),
         });
 
@@ -504,6 +572,16 @@ mod tests {
         assert!(result.contains("Something went wrong"));
     }
 
+    #[tokio::test]
+    async fn test_mock_multimodal_inference_error() {
+        let model = MockModel::with_error("Encoder failed".into());
+        let err = model
+            .run_multimodal_inference("<image>".into(), vec!["not-an-image".into()])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Encoder failed"));
+    }
+
     #[tokio::test]
     async fn test_mock_runtime_builder() {
         let runtime = MockRuntimeBuilder::new()
@@ -534,6 +612,7 @@ mod tests {
             top_p: 0.9,
             max_tokens: None,
             keep_alive: Duration::from_secs(300),
+            think: false,
             options: crate::server::defaults::default_model_options(),
         });
 
@@ -561,6 +640,7 @@ mod tests {
             top_p: 0.9,
             max_tokens: None,
             keep_alive: Duration::from_secs(300),
+            think: false,
             options: crate::server::defaults::default_model_options(),
         });
 
@@ -569,5 +649,11 @@ mod tests {
         let models = runtime.list_loaded_models().await;
         assert_eq!(models.len(), 1);
         assert_eq!(models[0].key, MockRuntime::generate_model_key(&request));
+
+        // Picking the loaded model again records when it was last used.
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        runtime.get_or_load_model(&request).await.unwrap();
+        let models = runtime.list_loaded_models().await;
+        assert!(models[0].last_used > models[0].loaded_at);
     }
 }
\ No newline at end of file
@@ -2,17 +2,31 @@
 pub mod apis;
 pub mod api_models;
 pub mod rkllm_runtime;
-mod defaults;
-mod vision;
+pub mod defaults;
+pub mod vision;
 pub mod runtime_trait;
 mod mock_runtime;
 mod test_helpers;
 mod test_fixtures;
 pub mod rig_provider;
+pub mod preload;
+pub mod pulls;
+pub mod metrics;
+pub mod request_log;
+pub mod auth;
+pub mod rate_limit;
+pub mod tokenizer;
+pub mod context;
+pub mod thinking;
+pub mod image_urls;
+pub mod hub;
+pub mod pull_spec;
+pub mod prune;
+pub mod store;
+pub mod modelfile;
 
-use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::{net::SocketAddr, time::Duration};
 
 use axum::{
@@ -28,9 +42,14 @@ use apis::{
         show_model_info,
     },
 };
-use owo_colors::OwoColorize;
+use metrics::Metrics;
+use preload::Preloader;
+use pulls::PullTracker;
 use rkllm_runtime::RkllmRuntime;
-use tokio::{sync::oneshot, time::sleep};
+use tokio::{
+    sync::{oneshot, Notify},
+    time::sleep,
+};
 
 use crate::config::Config;
 use crate::error::Result;
@@ -42,64 +61,34 @@ use utoipa::OpenApi;
 // Shared application state — accessible by all axum handlers via State<…>
 // ---------------------------------------------------------------------------
 
-/// Simple in-memory cache for model file digests
-#[derive(Clone, Default)]
-struct DigestCache(Arc<Mutex<HashMap<PathBuf, String>>>);
-
-impl DigestCache {
-    async fn get_or_compute(&self, path: &PathBuf) -> String {
-        // Check cache first
-        {
-            let cache = self.0.lock().unwrap();
-            if let Some(digest) = cache.get(path) {
-                return digest.clone();
-            }
-        }
-        
-        // Compute digest (this is slow for large files, but we only do it once per file)
-        let result = crate::server::apis::models::sha256_file_async(path.clone()).await.unwrap_or_default();
-        
-        // Store in cache
-        {
-            let mut cache = self.0.lock().unwrap();
-            cache.insert(path.clone(), result.clone());
-        }
-        
-        result
-    }
-    
-    /// Pre-compute digests for multiple files concurrently (available for future use)
-    async fn precompute_all(&self, paths: &[PathBuf]) {
-        let mut futures = Vec::new();
-        for path in paths {
-            // Check if already cached
-            let needs_compute = {
-                let cache = self.0.lock().unwrap();
-                !cache.contains_key(path)
-            };
-            
-            if needs_compute {
-                let path_clone = path.clone();
-                let cache_clone = self.clone();
-                futures.push(async move {
-                    let result = crate::server::apis::models::sha256_file_async(path_clone.clone()).await.unwrap_or_default();
-                    let mut cache = cache_clone.0.lock().unwrap();
-                    cache.insert(path_clone, result);
-                });
-            }
-        }
-        
-        // Run all digest computations concurrently
-        futures::future::join_all(futures).await;
-    }
-}
-
 #[derive(Clone)]
 pub struct AppState {
     pub runtime: RkllmRuntime,
     pub config: Arc<Config>,
-    pub digest_cache: DigestCache,
     pub rig_client: RkllmClient,
+    pub preloader: Preloader,
+    pub pulls: PullTracker,
+    pub metrics: Metrics,
+    pub started_at: std::time::Instant,
+    /// Signalled by `POST /api/shutdown`
+    pub shutdown: Arc<Notify>,
+}
+
+impl AppState {
+    pub fn new(runtime: RkllmRuntime, config: Arc<Config>) -> Self {
+        let rig_client = RkllmClient::new(Arc::new(runtime.clone()));
+        let metrics = runtime.metrics().clone();
+        Self {
+            runtime,
+            config,
+            rig_client,
+            preloader: Preloader::default(),
+            pulls: PullTracker::default(),
+            metrics,
+            started_at: std::time::Instant::now(),
+            shutdown: Arc::new(Notify::new()),
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -115,14 +104,25 @@ pub struct AppState {
         apis::models::list_local_models,
         apis::models::show_model_info,
         apis::models::delete_model,
+        apis::models::copy_model,
+        apis::models::unload_model,
         apis::models::list_running_models,
         apis::models::pull_model,
+        apis::models::create_model,
+        apis::models::prune_models,
         apis::models::retrieve_model,
         apis::chat::openai_chat_completions,
+        apis::tokenize::tokenize,
+        apis::tokenize::detokenize,
+        apis::health::liveness,
+        apis::health::readiness,
+        apis::health::server_status,
+        apis::health::shutdown,
     ),
     components(
         schemas(
             ChatCompletionRequestMessage,
+            VideoInput,
             ModelOptions,
             ChatCompletionRequest,
             GenerateRequest,
@@ -130,10 +130,15 @@ pub struct AppState {
             GenerateResponse,
             PullRequest,
             ProgressResponse,
+            CreateRequest,
+            PruneRequest,
+            PruneResponse,
             EmbedInput,
             EmbedRequest,
             EmbedResponse,
             DeleteRequest,
+            UnloadRequest,
+            CopyRequest,
             ShowRequest,
             ShowResponse,
             ListResponse,
@@ -146,6 +151,17 @@ pub struct AppState {
             OpenAiMessage,
             OpenAiChoice,
             OpenAiUsage,
+            ContextReport,
+            TokenizeRequest,
+            TokenizeResponse,
+            DetokenizeRequest,
+            DetokenizeResponse,
+            apis::health::ReadinessResponse,
+            apis::health::StatusResponse,
+            apis::health::ShutdownResponse,
+            apis::health::LoadedModelStatus,
+            preload::PreloadStatus,
+            pulls::PullProgress,
         )
     ),
     tags(
@@ -173,15 +189,21 @@ pub async fn run_server(
         std::fs::create_dir_all(&models_path).ok();
     }
 
-    let runtime = RkllmRuntime::new(models_path);
-    let runtime_arc = Arc::new(runtime);
-    let rig_client = RkllmClient::new(runtime_arc.clone());
-    let state = AppState { 
-        runtime: (*runtime_arc).clone(), 
-        config,
-        digest_cache: DigestCache::default(),
-        rig_client,
-    };
+    let runtime = RkllmRuntime::new(models_path).with_vision(config.vision.clone());
+    let preload_models = config.preload.clone();
+    let api_keys = auth::ApiKeys::from_config(&config.auth)?;
+    let limits = config.limits.clone();
+    let rate_limiter = rate_limit::RateLimiter::new(
+        config.rate_limits.clone(),
+        rate_limit::QuotaStore::open(config.dir.join("quota_usage.json")),
+    );
+    if !api_keys.is_enabled() {
+        tracing::info!("no API keys configured, authentication is disabled");
+    }
+    let state = AppState::new(runtime.clone(), config);
+    let preloader = state.preloader.clone();
+    let metrics = state.metrics.clone();
+    let shutdown = state.shutdown.clone();
 
     let openapi = ApiDoc::openapi();
     let app = Router::new()
@@ -192,8 +214,14 @@ pub async fn run_server(
         .route("/api/tags", get(list_local_models))
         .route("/api/show", post(show_model_info))
         .route("/api/delete", delete(delete_model))
+        .route("/api/copy", post(apis::models::copy_model))
+        .route("/api/unload", post(apis::models::unload_model))
         .route("/api/ps", get(list_running_models))
         .route("/api/pull", post(pull_model))
+        .route("/api/create", post(apis::models::create_model))
+        .route("/api/prune", post(apis::models::prune_models))
+        .route("/api/tokenize", post(apis::tokenize::tokenize))
+        .route("/api/detokenize", post(apis::tokenize::detokenize))
         // Agent API endpoints
         .route("/api/agent/chat", post(apis::agent::agent_chat))
         .route("/api/agent/stream", post(apis::agent::agent_stream))
@@ -204,12 +232,33 @@ pub async fn run_server(
             "/v1/chat/completions",
             post(apis::chat::openai_chat_completions),
         )
+        .route("/health/live", get(apis::health::liveness))
+        .route("/health/ready", get(apis::health::readiness))
+        .route("/api/status", get(apis::health::server_status))
+        .route("/api/shutdown", post(apis::health::shutdown))
+        .route("/metrics", get(apis::metrics::prometheus_metrics))
         .with_state(state)
         .route("/healthz", get(|| async { "OK" }))
+        .route_layer(axum::middleware::from_fn_with_state(
+            metrics,
+            metrics::track_requests,
+        ))
         .merge(
             utoipa_swagger_ui::SwaggerUi::new("/docs")
                 .url("/openapi.json", openapi),
         );
+    // Auth runs first so the rate limiter can key limits by API key.
+    let app = app
+        .layer(axum::middleware::from_fn_with_state(
+            rate_limiter,
+            rate_limit::limit_requests,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            api_keys,
+            auth::require_api_key,
+        ));
+    let app = app.layer(axum::extract::DefaultBodyLimit::max(limits.max_body_bytes));
+    let app = request_log::layer(app);
 
     let addr = base_url.parse::<SocketAddr>().map_err(|e| {
         crate::error::Error::Server(format!(
@@ -224,17 +273,34 @@ pub async fn run_server(
         ))
     })?;
 
-    use crate::terminal::message::write;
-    write::info(format!("Listening on http://{}", addr).green()).ok();
+    tracing::info!(%addr, "listening");
+
+    // Files copied into `models_path` while the server was down join the store.
+    let store = runtime.store().clone();
+    tokio::task::spawn_blocking(move || match store.import_loose(store::IMPORT_SETTLE) {
+        Ok(imported) if !imported.is_empty() => tracing::info!(models = ?imported, "imported loose model files"),
+        Ok(_) => {}
+        Err(e) => tracing::warn!(error = %e, "failed to import loose model files"),
+    });
+
+    // Preload after binding so the port answers (not ready) while models load.
+    if !preload_models.is_empty() {
+        tracing::info!(count = preload_models.len(), "preloading models");
+    }
+    let preload_handle = preloader.spawn(runtime, preload_models);
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async {
-            shutdown_rx.await.ok();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            tokio::select! {
+                _ = shutdown_rx => {}
+                _ = shutdown.notified() => {}
+            }
         })
         .await
         .map_err(|e| crate::error::Error::Server(format!("Server error: {}", e)))?;
 
-    write::info("Server has been shut down.".green()).ok();
+    preload_handle.abort();
+    tracing::info!("server has been shut down");
     Ok(())
 }
 
@@ -265,15 +331,14 @@ pub async fn start_background_server(
     let base_url_for_server = base_url.clone();
     let config_arc = Arc::new(config.clone());
 
-    use crate::terminal::message::write;
     let server_handle = tokio::spawn(async move {
         if let Err(e) = run_server(&base_url_for_server, config_arc, shutdown_rx).await {
-            write::error(format!("Server error: {}", e)).ok();
+            tracing::error!(error = %e, "server error");
         }
     });
 
     wait_for_server(&base_url).await?;
-    write::info("Background server started".green()).ok();
+    tracing::info!(%base_url, "background server started");
 
     Ok((server_handle, shutdown_tx))
 }
\ No newline at end of file
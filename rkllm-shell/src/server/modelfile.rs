@@ -0,0 +1,339 @@
+//! Modelfiles, and the request defaults they give a model
+//!
+//! A Modelfile derives a model from another, as in Ollama:
+//!
+//! ```text
+//! FROM qwen2.5-3b:w8a8
+//! PARAMETER temperature 0.6
+//! PARAMETER stop "<|User|>"
+//! SYSTEM """You are a terse assistant."""
+//! MESSAGE user Hello
+//! MESSAGE assistant Hi.
+//! ```
+//!
+//! `FROM` names a model file or directory, a stored model, or a repository
+//! to pull. `/api/create` keeps the rest in the new model's manifest, and
+//! chat and generate requests for it start from those values.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::server::api_models::{ChatCompletionRequest, ChatCompletionRequestMessage, GenerateRequest, ModelOptions, Role};
+use crate::server::apis::error::ApiError;
+use crate::server::store::{Message, ResolvedModel};
+
+const INTEGER_PARAMETERS: &[&str] = &["num_ctx", "repeat_last_n", "seed", "num_predict", "top_k"];
+const FLOAT_PARAMETERS: &[&str] = &["repeat_penalty", "temperature", "top_p", "min_p"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Modelfile {
+    pub from: String,
+    /// `PARAMETER` lines in order; `stop` may repeat
+    pub parameters: Vec<(String, String)>,
+    pub template: Option<String>,
+    pub system: Option<String>,
+    pub adapters: Vec<String>,
+    pub messages: Vec<Message>,
+    pub license: Vec<String>,
+}
+
+impl Modelfile {
+    pub fn parse(text: &str) -> Result<Self, ApiError> {
+        let mut modelfile = Self::default();
+        let mut from = None;
+        let mut lines = text.lines().enumerate();
+        while let Some((index, line)) = lines.next() {
+            let number = index + 1;
+            let invalid = |why: String| ApiError::InvalidRequest(format!("modelfile: line {}: {}", number, why));
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (instruction, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let instruction = instruction.to_ascii_uppercase();
+            if rest.trim().is_empty() {
+                return Err(invalid(format!("{} needs a value", instruction)));
+            }
+            let raw = read_value(rest.trim(), &mut lines).ok_or_else(|| invalid("unterminated \"\"\"".to_string()))?;
+            let value = unquote(&raw);
+            match instruction.as_str() {
+                "FROM" if from.is_some() => return Err(invalid("FROM given twice".to_string())),
+                "FROM" => from = Some(value),
+                "PARAMETER" => {
+                    let (key, raw) = split_word(&raw).ok_or_else(|| invalid("expected PARAMETER <name> <value>".to_string()))?;
+                    let raw = unquote(raw);
+                    parameter_value(key, &raw).map_err(invalid)?;
+                    modelfile.parameters.push((key.to_string(), raw));
+                }
+                "TEMPLATE" => modelfile.template = Some(value),
+                "SYSTEM" => modelfile.system = Some(value),
+                "ADAPTER" => modelfile.adapters.push(value),
+                "LICENSE" => modelfile.license.push(value),
+                "MESSAGE" => {
+                    let (role, content) = split_word(&raw).ok_or_else(|| invalid("expected MESSAGE <role> <content>".to_string()))?;
+                    let role = match role.to_ascii_lowercase().as_str() {
+                        "system" => Role::System,
+                        "user" => Role::User,
+                        "assistant" => Role::Assistant,
+                        other => return Err(invalid(format!("unknown role '{}', expected system, user or assistant", other))),
+                    };
+                    modelfile.messages.push(Message {
+                        role,
+                        content: unquote(content),
+                    });
+                }
+                other => return Err(invalid(format!("unknown instruction '{}'", other))),
+            }
+        }
+        modelfile.from = from.ok_or_else(|| ApiError::InvalidRequest("modelfile: missing FROM".to_string()))?;
+        Ok(modelfile)
+    }
+
+    /// The Modelfile that recreates a stored model. `FROM` names the model
+    /// itself, so the result can be edited into one derived from it; a
+    /// template or adapters it has come along through `FROM`.
+    pub fn from_model(model: &ResolvedModel) -> Self {
+        let manifest = &model.manifest;
+        let mut parameters = Vec::new();
+        for (key, value) in &manifest.parameters {
+            match value {
+                Value::Array(values) => {
+                    parameters.extend(values.iter().map(|v| (key.clone(), v.as_str().unwrap_or_default().to_string())))
+                }
+                Value::String(text) => parameters.push((key.clone(), text.clone())),
+                other => parameters.push((key.clone(), other.to_string())),
+            }
+        }
+        Self {
+            from: model.name.to_string(),
+            parameters,
+            template: None,
+            system: manifest.system.clone(),
+            adapters: Vec::new(),
+            messages: manifest.messages.clone(),
+            license: manifest.license.clone(),
+        }
+    }
+
+    /// The `PARAMETER`s as manifest parameters: numbers, and `stop` as a list.
+    pub fn parameter_map(&self) -> Result<BTreeMap<String, Value>, ApiError> {
+        let mut map = BTreeMap::new();
+        for (key, raw) in &self.parameters {
+            let value = parameter_value(key, raw).map_err(|e| ApiError::InvalidRequest(format!("modelfile: {}", e)))?;
+            if key == "stop" {
+                let stops = map.entry(key.clone()).or_insert_with(|| Value::Array(Vec::new()));
+                if let Value::Array(stops) = stops {
+                    stops.push(value);
+                }
+            } else {
+                map.insert(key.clone(), value);
+            }
+        }
+        Ok(map)
+    }
+
+    /// Makes relative `FROM` and `ADAPTER` paths that exist under `base`
+    /// absolute, so the server finds them whatever its working directory.
+    pub fn resolve_paths(&mut self, base: &Path) {
+        let resolve = |path: &mut String| {
+            let candidate = base.join(&*path);
+            if Path::new(path.as_str()).is_relative() && candidate.exists() {
+                if let Ok(absolute) = candidate.canonicalize() {
+                    *path = absolute.display().to_string();
+                }
+            }
+        };
+        resolve(&mut self.from);
+        self.adapters.iter_mut().for_each(resolve);
+    }
+}
+
+impl fmt::Display for Modelfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "FROM {}", self.from)?;
+        for adapter in &self.adapters {
+            writeln!(f, "ADAPTER {}", adapter)?;
+        }
+        if let Some(template) = &self.template {
+            writeln!(f, "TEMPLATE {}", quote(template))?;
+        }
+        if let Some(system) = &self.system {
+            writeln!(f, "SYSTEM {}", quote(system))?;
+        }
+        for (key, value) in &self.parameters {
+            writeln!(f, "PARAMETER {} {}", key, quote(value))?;
+        }
+        for message in &self.messages {
+            let role = match message.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                Role::Tool => "tool",
+            };
+            writeln!(f, "MESSAGE {} {}", role, quote(&message.content))?;
+        }
+        for license in &self.license {
+            writeln!(f, "LICENSE {}", quote(license))?;
+        }
+        Ok(())
+    }
+}
+
+/// The text after an instruction, read on to the closing `"""` when it
+/// opens a multi-line value. None when that is never closed.
+fn read_value<'a>(rest: &str, lines: &mut impl Iterator<Item = (usize, &'a str)>) -> Option<String> {
+    let mut value = rest.to_string();
+    if rest.matches("\"\"\"").count() % 2 == 0 {
+        return Some(value);
+    }
+    for (_, line) in lines {
+        value.push('\n');
+        value.push_str(line);
+        if line.contains("\"\"\"") {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn split_word(text: &str) -> Option<(&str, &str)> {
+    let (word, rest) = text.split_once(char::is_whitespace)?;
+    Some((word, rest.trim()))
+}
+
+/// Strips the `"""` or `"` around a value.
+fn unquote(text: &str) -> String {
+    let text = text.trim();
+    for quote in ["\"\"\"", "\""] {
+        if text.len() >= 2 * quote.len() {
+            if let Some(inner) = text.strip_prefix(quote).and_then(|t| t.strip_suffix(quote)) {
+                return inner.to_string();
+            }
+        }
+    }
+    text.to_string()
+}
+
+fn quote(value: &str) -> String {
+    if value.contains('\n') || value.contains('"') {
+        format!("\"\"\"{}\"\"\"", value)
+    } else if value.is_empty() || value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// The lowest and, if bounded, highest value of a numeric parameter, as
+/// request validation allows them. `num_ctx` is also held to the server's
+/// `limits.max_num_ctx` when the model is created and used.
+fn parameter_range(key: &str) -> (f64, Option<f64>) {
+    match key {
+        "num_ctx" => (1.0, None),
+        "top_k" => (0.0, None),
+        "num_predict" | "repeat_last_n" => (-1.0, None),
+        "temperature" => (0.0, Some(2.0)),
+        "top_p" | "min_p" => (0.0, Some(1.0)),
+        "repeat_penalty" => (0.0, Some(10.0)),
+        _ => (f64::NEG_INFINITY, None),
+    }
+}
+
+/// A `PARAMETER` value typed like the request option it sets.
+fn parameter_value(key: &str, raw: &str) -> Result<Value, String> {
+    let number = if INTEGER_PARAMETERS.contains(&key) {
+        raw.parse::<i32>()
+            .map(f64::from)
+            .map_err(|_| format!("{} must be an integer, got '{}'", key, raw))?
+    } else if FLOAT_PARAMETERS.contains(&key) {
+        raw.parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("{} must be a number, got '{}'", key, raw))?
+    } else if key == "stop" {
+        return Ok(Value::from(raw));
+    } else {
+        let mut known: Vec<&str> = INTEGER_PARAMETERS.iter().chain(FLOAT_PARAMETERS).copied().collect();
+        known.push("stop");
+        return Err(format!("unknown parameter '{}', expected one of {}", key, known.join(", ")));
+    };
+    match parameter_range(key) {
+        (min, Some(max)) if !(min..=max).contains(&number) => {
+            Err(format!("{} must be between {} and {}, got {}", key, min, max, raw))
+        }
+        (min, _) if number < min => Err(format!("{} must be at least {}, got {}", key, min, raw)),
+        _ if INTEGER_PARAMETERS.contains(&key) => Ok(Value::from(number as i64)),
+        _ => Ok(Value::from(number)),
+    }
+}
+
+/// Sets the options a request did not give to the model's parameters.
+pub fn apply_parameters(parameters: &BTreeMap<String, Value>, options: &mut ModelOptions) {
+    fn set<T>(field: &mut T, given: bool, value: Option<T>) {
+        if let (false, Some(value)) = (given, value) {
+            *field = value;
+        }
+    }
+    for (key, value) in parameters {
+        let given = options.is_given(key);
+        let int = value.as_i64().map(|v| v as i32);
+        let float = value.as_f64().map(|v| v as f32);
+        match key.as_str() {
+            "num_ctx" => set(&mut options.num_ctx, given, int),
+            "repeat_last_n" => set(&mut options.repeat_last_n, given, int),
+            "seed" => set(&mut options.seed, given, int),
+            "num_predict" => set(&mut options.num_predict, given, int),
+            "top_k" => set(&mut options.top_k, given, int),
+            "repeat_penalty" => set(&mut options.repeat_penalty, given, float),
+            "temperature" => set(&mut options.temperature, given, float),
+            "top_p" => set(&mut options.top_p, given, float),
+            "min_p" => set(&mut options.min_p, given, float),
+            "stop" => {
+                let stops = value.as_array().map(|stops| {
+                    stops.iter().filter_map(|s| s.as_str().map(str::to_string)).collect()
+                });
+                set(&mut options.stop, given, stops);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Gives a chat the model's parameters, its system prompt unless the chat
+/// has one, and its `MESSAGE`s ahead of the chat's own.
+pub fn apply_chat_defaults(model: &ResolvedModel, request: &mut ChatCompletionRequest) {
+    let manifest = &model.manifest;
+    apply_parameters(&manifest.parameters, &mut request.options);
+    let message = |role: Role, content: &str| ChatCompletionRequestMessage {
+        role,
+        content: content.to_string(),
+        thinking: None,
+        images: None,
+        videos: None,
+    };
+    let mut preset = Vec::new();
+    if let Some(system) = &manifest.system {
+        if !request.messages.iter().any(|m| m.role == Role::System) {
+            preset.push(message(Role::System, system));
+        }
+    }
+    preset.extend(manifest.messages.iter().map(|m| message(m.role.clone(), &m.content)));
+    request.messages.splice(0..0, preset);
+}
+
+/// Gives a completion the model's parameters and system prompt where the
+/// request has none.
+pub fn apply_generate_defaults(model: &ResolvedModel, request: &mut GenerateRequest) {
+    let manifest = &model.manifest;
+    apply_parameters(&manifest.parameters, &mut request.options);
+    if request.system.is_none() {
+        request.system = manifest.system.clone();
+    }
+}
+
+#[cfg(test)]
+#[path = "modelfile_test.rs"]
+mod tests;
@@ -0,0 +1,179 @@
+//! Tests for Modelfile parsing and the defaults it gives requests
+
+use super::*;
+use crate::server::store::{Layer, Manifest, ModelName};
+use crate::server::test_helpers::{ChatRequestBuilder, GenerateRequestBuilder};
+use std::path::PathBuf;
+
+const MODELFILE: &str = r#"
+# A terse assistant
+from ./Qwen2.5-3B_W8A8_RK3588.rkllm
+PARAMETER temperature 0.6
+PARAMETER num_ctx 4096
+PARAMETER stop "<|User|>"
+PARAMETER stop "<|System|>"
+TEMPLATE """<|System|>{{ .System }}
+<|User|>{{ .Prompt }}
+<|Assistant|>"""
+SYSTEM You are a terse assistant.
+ADAPTER ./style_lora.rkllm
+MESSAGE user "Is the sky blue?"
+MESSAGE assistant """Yes.
+Usually."""
+LICENSE """Apache 2.0"""
+"#;
+
+fn model(manifest: Manifest) -> ResolvedModel {
+    ResolvedModel {
+        name: ModelName::parse("terse").unwrap(),
+        manifest,
+        model_path: PathBuf::from("/store/blobs/sha256-aa"),
+        tokenizer_path: None,
+        vision_encoder_path: None,
+    }
+}
+
+fn manifest_from(modelfile: &Modelfile) -> Manifest {
+    let mut manifest = Manifest::new(Layer {
+        digest: "sha256:aa".to_string(),
+        size: 1,
+        file: "model.rkllm".to_string(),
+    });
+    manifest.parameters = modelfile.parameter_map().unwrap();
+    manifest.template = modelfile.template.clone();
+    manifest.system = modelfile.system.clone();
+    manifest.messages = modelfile.messages.clone();
+    manifest.license = modelfile.license.clone();
+    manifest
+}
+
+#[test]
+fn test_parse_modelfile() {
+    let modelfile = Modelfile::parse(MODELFILE).unwrap();
+    assert_eq!(modelfile.from, "./Qwen2.5-3B_W8A8_RK3588.rkllm");
+    assert_eq!(modelfile.parameters.len(), 4);
+    assert_eq!(modelfile.parameters[2], ("stop".to_string(), "<|User|>".to_string()));
+    assert_eq!(
+        modelfile.template.as_deref(),
+        Some("<|System|>{{ .System }}\n<|User|>{{ .Prompt }}\n<|Assistant|>")
+    );
+    assert_eq!(modelfile.system.as_deref(), Some("You are a terse assistant."));
+    assert_eq!(modelfile.adapters, vec!["./style_lora.rkllm"]);
+    assert_eq!(modelfile.messages[0].content, "Is the sky blue?");
+    assert_eq!(modelfile.messages[1].role, Role::Assistant);
+    assert_eq!(modelfile.messages[1].content, "Yes.\nUsually.");
+    assert_eq!(modelfile.license, vec!["Apache 2.0"]);
+
+    let parameters = modelfile.parameter_map().unwrap();
+    assert_eq!(parameters["num_ctx"], Value::from(4096));
+    assert_eq!(parameters["stop"], serde_json::json!(["<|User|>", "<|System|>"]));
+}
+
+#[test]
+fn test_rejects_invalid_modelfiles() {
+    for (text, error) in [
+        ("PARAMETER temperature 0.5", "missing FROM"),
+        ("FROM a\nFROM b", "line 2: FROM given twice"),
+        ("FROM a\nPARAMETER top_k high", "top_k must be an integer"),
+        ("FROM a\nPARAMETER top_k -5", "top_k must be at least 0, got -5"),
+        ("FROM a\nPARAMETER num_ctx 99999999999", "num_ctx must be an integer"),
+        ("FROM a\nPARAMETER temperature 3", "temperature must be between 0 and 2, got 3"),
+        ("FROM a\nPARAMETER top_p NaN", "top_p must be a number"),
+        ("FROM a\nPARAMETER mirostat 1", "unknown parameter 'mirostat'"),
+        ("FROM a\nMESSAGE tool hi", "unknown role 'tool'"),
+        ("FROM a\nSYSTEM \"\"\"never closed", "line 2: unterminated"),
+        ("FROM a\nQUANTIZE q4", "unknown instruction 'QUANTIZE'"),
+        ("FROM", "FROM needs a value"),
+    ] {
+        let err = Modelfile::parse(text).unwrap_err().to_string();
+        assert!(err.contains(error), "{:?}: {}", text, err);
+    }
+}
+
+#[test]
+fn test_reconstructed_modelfile_parses_back() {
+    let parsed = Modelfile::parse(MODELFILE).unwrap();
+    let shown = Modelfile::from_model(&model(manifest_from(&parsed)));
+    assert_eq!(shown.from, "terse:latest");
+
+    // The template comes along through FROM; repeating it would be rejected.
+    let text = shown.to_string();
+    assert!(text.starts_with("FROM terse:latest\nSYSTEM "));
+    assert!(!text.contains("TEMPLATE"));
+    assert!(text.contains("PARAMETER stop <|User|>\nPARAMETER stop <|System|>\n"));
+    assert_eq!(Modelfile::parse(&text).unwrap(), shown);
+}
+
+#[test]
+fn test_resolve_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("model.rkllm"), b"model").unwrap();
+    let mut modelfile = Modelfile::parse("FROM ./model.rkllm\nADAPTER missing.rkllm").unwrap();
+    modelfile.resolve_paths(dir.path());
+    assert_eq!(
+        PathBuf::from(&modelfile.from),
+        dir.path().join("model.rkllm").canonicalize().unwrap()
+    );
+    assert_eq!(modelfile.adapters, vec!["missing.rkllm"]);
+
+    let mut stored = Modelfile::parse("FROM qwen:w8a8").unwrap();
+    stored.resolve_paths(dir.path());
+    assert_eq!(stored.from, "qwen:w8a8");
+}
+
+#[test]
+fn test_chat_defaults() {
+    let model = model(manifest_from(&Modelfile::parse(MODELFILE).unwrap()));
+
+    let mut request = ChatRequestBuilder::new("terse").user("Hi").build();
+    request.options = serde_json::from_value(serde_json::json!({"top_k": 5})).unwrap();
+    apply_chat_defaults(&model, &mut request);
+    assert_eq!(request.options.temperature, 0.6);
+    assert_eq!(request.options.num_ctx, 4096);
+    assert_eq!(request.options.top_k, 5);
+    assert_eq!(request.options.stop, vec!["<|User|>", "<|System|>"]);
+    let roles: Vec<Role> = request.messages.iter().map(|m| m.role.clone()).collect();
+    assert_eq!(roles, vec![Role::System, Role::User, Role::Assistant, Role::User]);
+    assert_eq!(request.messages[0].content, "You are a terse assistant.");
+
+    // Options the request sets, and its own system prompt, win; even ones
+    // set to their default value.
+    let mut request = ChatRequestBuilder::new("terse").system("Be verbose.").user("Hi").build();
+    request.options =
+        serde_json::from_value(serde_json::json!({"temperature": 0.2, "num_ctx": 2048, "stop": []})).unwrap();
+    apply_chat_defaults(&model, &mut request);
+    assert_eq!(request.options.temperature, 0.2);
+    assert_eq!(request.options.num_ctx, 2048);
+    assert!(request.options.stop.is_empty());
+    assert_eq!(request.messages.len(), 4);
+    assert_eq!(request.messages[2].content, "Be verbose.");
+}
+
+#[test]
+fn test_generate_defaults() {
+    let model = model(manifest_from(&Modelfile::parse(MODELFILE).unwrap()));
+    let mut request = GenerateRequestBuilder::new("terse", "Hi").build();
+    apply_generate_defaults(&model, &mut request);
+    assert_eq!(request.system.as_deref(), Some("You are a terse assistant."));
+    assert_eq!(request.options.num_ctx, 4096);
+
+    let mut request = GenerateRequestBuilder::new("terse", "Hi").system("Be verbose.").build();
+    apply_generate_defaults(&model, &mut request);
+    assert_eq!(request.system.as_deref(), Some("Be verbose."));
+}
+
+#[test]
+fn test_options_left_out_take_defaults() {
+    let options: ModelOptions = serde_json::from_value(serde_json::json!({"temperature": 0.8})).unwrap();
+    assert!(options.is_given("temperature"));
+    assert!(!options.is_given("top_k"));
+    assert_eq!(options.top_k, crate::server::defaults::default_top_k());
+
+    let mut parameters = BTreeMap::new();
+    parameters.insert("temperature".to_string(), Value::from(0.6));
+    parameters.insert("top_k".to_string(), Value::from(10));
+    let mut options = options;
+    apply_parameters(&parameters, &mut options);
+    assert_eq!(options.temperature, 0.8);
+    assert_eq!(options.top_k, 10);
+}
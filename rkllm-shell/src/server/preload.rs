@@ -0,0 +1,130 @@
+//! Model preloading at server start
+//!
+//! Loads the models listed under `preload` in `config.yaml` so the first
+//! request does not pay the `rkllm_init` cost, and tracks progress so the
+//! readiness endpoint can report "not ready" until every preload finished.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::PreloadModel;
+use crate::server::api_models::GenerateRequest;
+use crate::server::modelfile;
+use crate::server::rkllm_runtime::{CompletionRequest, RkllmRuntime};
+use crate::server::store::ResolvedModel;
+
+/// Progress of a single preload entry
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case", tag = "state", content = "error")]
+pub enum PreloadState {
+    Pending,
+    Loading,
+    WarmingUp,
+    Loaded,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PreloadStatus {
+    pub model: String,
+    #[serde(flatten)]
+    pub state: PreloadState,
+}
+
+/// Shared preload progress, cloned into `AppState`
+#[derive(Clone, Default)]
+pub struct Preloader {
+    statuses: Arc<Mutex<Vec<PreloadStatus>>>,
+    complete: Arc<AtomicBool>,
+}
+
+impl Preloader {
+    /// True once every configured model was either loaded or failed
+    pub fn is_complete(&self) -> bool {
+        self.complete.load(Ordering::Acquire)
+    }
+
+    pub fn statuses(&self) -> Vec<PreloadStatus> {
+        self.statuses.lock().unwrap().clone()
+    }
+
+    fn set_state(&self, index: usize, state: PreloadState) {
+        if let Some(status) = self.statuses.lock().unwrap().get_mut(index) {
+            status.state = state;
+        }
+    }
+
+    /// Loads the configured models one after another in a background task.
+    /// Models are loaded sequentially because the NPU only fits so much at once.
+    pub fn spawn(&self, runtime: RkllmRuntime, models: Vec<PreloadModel>) -> tokio::task::JoinHandle<()> {
+        *self.statuses.lock().unwrap() = models
+            .iter()
+            .map(|m| PreloadStatus {
+                model: m.model.clone(),
+                state: PreloadState::Pending,
+            })
+            .collect();
+
+        let preloader = self.clone();
+        tokio::spawn(async move {
+            for (index, entry) in models.into_iter().enumerate() {
+                let state = match preload_model(&preloader, index, &runtime, &entry).await {
+                    Ok(()) => {
+                        tracing::info!(model = %entry.model, "preloaded model");
+                        PreloadState::Loaded
+                    }
+                    Err(e) => {
+                        tracing::error!(model = %entry.model, error = %e, "failed to preload model");
+                        PreloadState::Failed(e)
+                    }
+                };
+                preloader.set_state(index, state);
+            }
+            preloader.complete.store(true, Ordering::Release);
+        })
+    }
+}
+
+/// Builds the request a preload entry stands for, so the resulting model key
+/// matches later requests made with the same options. The model's own
+/// parameters fill in what the entry leaves out, as they do for requests.
+pub fn preload_request(entry: &PreloadModel, stored: &ResolvedModel, prompt: String) -> CompletionRequest {
+    let mut request = GenerateRequest {
+        model: entry.model.clone(),
+        prompt,
+        keep_alive: entry.keep_alive,
+        options: entry.options.clone(),
+        ..Default::default()
+    };
+    modelfile::apply_generate_defaults(stored, &mut request);
+    CompletionRequest::Generate(request)
+}
+
+async fn preload_model(
+    preloader: &Preloader,
+    index: usize,
+    runtime: &RkllmRuntime,
+    entry: &PreloadModel,
+) -> Result<(), String> {
+    preloader.set_state(index, PreloadState::Loading);
+    let prompt = entry.warmup.clone().unwrap_or_default();
+    let stored = runtime.resolve(&entry.model).await.map_err(|e| e.to_string())?;
+    let model = runtime
+        .get_request_model(&preload_request(entry, &stored, prompt.clone()))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !prompt.is_empty() {
+        preloader.set_state(index, PreloadState::WarmingUp);
+        let mut rx = model.run_inference(vec![prompt]);
+        while rx.recv().await.is_some() {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "preload_test.rs"]
+mod tests;
@@ -0,0 +1,31 @@
+//! Tests for model preloading
+
+use super::*;
+use crate::server::store::{Layer, Manifest, ModelName};
+use std::path::PathBuf;
+
+#[test]
+fn test_preload_request_takes_the_model_parameters() {
+    let mut manifest = Manifest::new(Layer {
+        digest: "sha256:aa".to_string(),
+        size: 1,
+        file: "model.rkllm".to_string(),
+    });
+    manifest.parameters = serde_json::from_value(serde_json::json!({"num_ctx": 2048, "temperature": 0.9})).unwrap();
+    let stored = ResolvedModel {
+        name: ModelName::parse("terse").unwrap(),
+        manifest,
+        model_path: PathBuf::from("/store/blobs/sha256-aa"),
+        tokenizer_path: None,
+        vision_encoder_path: None,
+    };
+    let entry: PreloadModel =
+        serde_json::from_value(serde_json::json!({"model": "terse", "options": {"temperature": 0.2}})).unwrap();
+
+    // The same options a request for the model would run with.
+    let CompletionRequest::Generate(request) = preload_request(&entry, &stored, String::new()) else {
+        panic!("expected a generate request");
+    };
+    assert_eq!(request.options.num_ctx, 2048);
+    assert_eq!(request.options.temperature, 0.2);
+}
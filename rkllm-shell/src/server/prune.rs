@@ -0,0 +1,174 @@
+//! Reclaiming disk space from abandoned downloads
+//!
+//! `/api/prune` deletes the `.partial` files of pulls that are not running,
+//! under `models_path` and the registries' `cache_dir`s, and garbage-collects
+//! the Hugging Face cache: blobs no snapshot refers to, interrupted
+//! `.incomplete` downloads, and snapshot links whose blob a pull moved away.
+//! The cache is shared with other tools, so only the repositories this
+//! server has pulled are collected.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// `.incomplete` blobs modified more recently than this may belong to a
+/// download still running in another process.
+pub const INCOMPLETE_SETTLE: Duration = Duration::from_secs(10 * 60);
+
+/// Where `prune` looks for garbage
+#[derive(Debug, Clone, Default)]
+pub struct PruneTargets {
+    /// Searched recursively for `.partial` files
+    pub partial_dirs: Vec<PathBuf>,
+    pub hf_cache: Option<PathBuf>,
+    /// Repositories (`owner--repo`) this server has pulled; other
+    /// repositories in the Hugging Face cache are left alone
+    pub hf_repos: Vec<String>,
+    /// Directory names (`owner--repo`) of pulls in progress, whose partial
+    /// files are kept
+    pub busy: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PruneReport {
+    pub removed: Vec<PathBuf>,
+    /// Bytes freed; files with other hard links free nothing
+    pub reclaimed_bytes: u64,
+}
+
+impl PruneReport {
+    fn remove(&mut self, path: &Path, dry_run: bool) -> io::Result<()> {
+        let metadata = fs::symlink_metadata(path)?;
+        if !dry_run {
+            fs::remove_file(path)?;
+        }
+        if metadata.is_file() && metadata.nlink() == 1 {
+            self.reclaimed_bytes += metadata.len();
+        }
+        self.removed.push(path.to_path_buf());
+        Ok(())
+    }
+}
+
+/// Deletes what `targets` leave behind, or with `dry_run` only reports it.
+pub fn prune(targets: &PruneTargets, dry_run: bool) -> io::Result<PruneReport> {
+    let mut report = PruneReport::default();
+    for dir in &targets.partial_dirs {
+        // A `cache_dir` inside `models_path` is searched once.
+        if targets.partial_dirs.iter().any(|other| other != dir && dir.starts_with(other)) {
+            continue;
+        }
+        prune_partials(dir, &targets.busy, dry_run, &mut report)?;
+    }
+    if let Some(cache) = &targets.hf_cache {
+        prune_hf_cache(cache, &targets.hf_repos, dry_run, &mut report)?;
+    }
+    Ok(report)
+}
+
+/// The repositories staged under the store's pull directory, which every
+/// pull creates and keeps.
+pub fn pulled_repos(pulls_dir: &Path) -> io::Result<Vec<String>> {
+    let mut repos = Vec::new();
+    for entry in entries(pulls_dir)? {
+        if entry.file_type()?.is_dir() {
+            repos.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    Ok(repos)
+}
+
+/// Entries of `dir`, or none when it does not exist.
+fn entries(dir: &Path) -> io::Result<Vec<fs::DirEntry>> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries.collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn prune_partials(dir: &Path, busy: &[String], dry_run: bool, report: &mut PruneReport) -> io::Result<()> {
+    let in_use = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| busy.iter().any(|b| b == name));
+    for entry in entries(dir)? {
+        let path = entry.path();
+        // Symlinks are not followed out of the directory.
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            prune_partials(&path, busy, dry_run, report)?;
+        } else if file_type.is_file() && !in_use && path.extension().is_some_and(|ext| ext == "partial") {
+            report.remove(&path, dry_run)?;
+        }
+    }
+    Ok(())
+}
+
+/// The cache holds `models--owner--repo/blobs/<sha>` and
+/// `models--owner--repo/snapshots/<commit>/<file>` links to those blobs.
+fn prune_hf_cache(cache: &Path, repos: &[String], dry_run: bool, report: &mut PruneReport) -> io::Result<()> {
+    for repo in entries(cache)? {
+        let pulled = repo
+            .file_name()
+            .to_string_lossy()
+            .strip_prefix("models--")
+            .is_some_and(|name| repos.iter().any(|r| r == name));
+        if !pulled || !repo.file_type()?.is_dir() {
+            continue;
+        }
+        let blobs = repo.path().join("blobs");
+        let mut referenced = HashSet::new();
+        prune_snapshots(&repo.path().join("snapshots"), &mut referenced, dry_run, report)?;
+        for blob in entries(&blobs)? {
+            let name = blob.file_name();
+            let name = name.to_string_lossy();
+            if !blob.file_type()?.is_file() {
+                continue;
+            }
+            let garbage = if name.ends_with(".incomplete") {
+                let modified = blob.metadata()?.modified()?;
+                SystemTime::now().duration_since(modified).unwrap_or_default() >= INCOMPLETE_SETTLE
+            } else {
+                !referenced.contains(name.as_ref())
+            };
+            if garbage {
+                report.remove(&blob.path(), dry_run)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects the blob names snapshot links point at, removing dangling links.
+fn prune_snapshots(
+    dir: &Path,
+    referenced: &mut HashSet<String>,
+    dry_run: bool,
+    report: &mut PruneReport,
+) -> io::Result<()> {
+    for entry in entries(dir)? {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            prune_snapshots(&path, referenced, dry_run, report)?;
+        } else if file_type.is_symlink() {
+            let target = dir.join(fs::read_link(&path)?);
+            if target.exists() {
+                if let Some(name) = target.file_name() {
+                    referenced.insert(name.to_string_lossy().into_owned());
+                }
+            } else {
+                report.remove(&path, dry_run)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "prune_test.rs"]
+mod tests;
@@ -0,0 +1,144 @@
+//! Tests for pruning abandoned downloads
+
+use std::os::unix::fs::symlink;
+
+use super::*;
+
+/// A Hugging Face cache with one snapshot linking `kept`, a blob nothing links,
+/// an interrupted download, one still running and a link whose blob was moved
+/// away. Another tool's repository holds an orphan too.
+fn hf_cache(root: &Path) -> PathBuf {
+    let repo = root.join("hub").join("models--user--model");
+    fs::create_dir_all(repo.join("blobs")).unwrap();
+    fs::create_dir_all(repo.join("snapshots").join("abc123")).unwrap();
+    fs::write(repo.join("blobs").join("kept"), b"1234").unwrap();
+    fs::write(repo.join("blobs").join("orphan"), b"12345678").unwrap();
+    fs::write(repo.join("blobs").join("next.incomplete"), b"12").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(repo.join("blobs").join("next.incomplete"))
+        .unwrap()
+        .set_modified(SystemTime::now() - INCOMPLETE_SETTLE)
+        .unwrap();
+    fs::write(repo.join("blobs").join("running.incomplete"), b"1").unwrap();
+    symlink("../../blobs/kept", repo.join("snapshots").join("abc123").join("model.rkllm")).unwrap();
+    symlink("../../blobs/moved", repo.join("snapshots").join("abc123").join("tokenizer.json")).unwrap();
+
+    let other = root.join("hub").join("models--someone--else");
+    fs::create_dir_all(other.join("blobs")).unwrap();
+    fs::write(other.join("blobs").join("orphan"), b"1234").unwrap();
+    root.join("hub")
+}
+
+fn pulled() -> Vec<String> {
+    vec!["user--model".to_string()]
+}
+
+fn models(root: &Path) -> PathBuf {
+    let models = root.join("models");
+    fs::create_dir_all(models.join("user--idle")).unwrap();
+    fs::create_dir_all(models.join("user--busy")).unwrap();
+    fs::write(models.join("user--idle").join("model.rkllm.partial"), b"123").unwrap();
+    fs::write(models.join("user--idle").join("model.rkllm"), b"123").unwrap();
+    fs::write(models.join("user--busy").join("model.rkllm.partial"), b"123").unwrap();
+    models
+}
+
+fn names(report: &PruneReport) -> Vec<String> {
+    let mut names: Vec<String> = report
+        .removed
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_prune_removes_orphans_and_idle_partials() {
+    let root = tempfile::tempdir().unwrap();
+    let targets = PruneTargets {
+        partial_dirs: vec![models(root.path())],
+        hf_cache: Some(hf_cache(root.path())),
+        hf_repos: pulled(),
+        busy: vec!["user--busy".to_string()],
+    };
+
+    let report = prune(&targets, false).unwrap();
+    assert_eq!(
+        names(&report),
+        vec!["model.rkllm.partial", "next.incomplete", "orphan", "tokenizer.json"]
+    );
+    assert_eq!(report.reclaimed_bytes, 3 + 2 + 8);
+    assert!(report.removed.iter().all(|p| fs::symlink_metadata(p).is_err()));
+    assert!(root.path().join("models/user--busy/model.rkllm.partial").exists());
+    assert!(root.path().join("models/user--idle/model.rkllm").exists());
+    assert!(root.path().join("hub/models--user--model/blobs/kept").exists());
+    assert!(root.path().join("hub/models--user--model/blobs/running.incomplete").exists());
+    assert!(root.path().join("hub/models--someone--else/blobs/orphan").exists());
+
+    assert_eq!(prune(&targets, false).unwrap(), PruneReport::default());
+}
+
+#[test]
+fn test_dry_run_keeps_files() {
+    let root = tempfile::tempdir().unwrap();
+    let targets = PruneTargets {
+        partial_dirs: vec![models(root.path())],
+        hf_cache: Some(hf_cache(root.path())),
+        hf_repos: pulled(),
+        busy: Vec::new(),
+    };
+
+    let report = prune(&targets, true).unwrap();
+    assert_eq!(report.removed.len(), 5);
+    assert!(report.removed.iter().all(|p| fs::symlink_metadata(p).is_ok()));
+    assert_eq!(prune(&targets, true).unwrap(), report);
+}
+
+#[test]
+fn test_hard_linked_files_reclaim_nothing() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = hf_cache(root.path());
+    let orphan = cache.join("models--user--model/blobs/orphan");
+    fs::hard_link(&orphan, root.path().join("model.rkllm")).unwrap();
+    let targets = PruneTargets {
+        hf_cache: Some(cache),
+        hf_repos: pulled(),
+        ..PruneTargets::default()
+    };
+
+    let report = prune(&targets, false).unwrap();
+    assert!(report.removed.contains(&orphan));
+    assert_eq!(report.reclaimed_bytes, 2);
+    assert_eq!(fs::read(root.path().join("model.rkllm")).unwrap(), b"12345678");
+}
+
+#[test]
+fn test_nested_and_missing_dirs() {
+    let root = tempfile::tempdir().unwrap();
+    let models = models(root.path());
+    let targets = PruneTargets {
+        partial_dirs: vec![models.clone(), models.join("user--idle"), root.path().join("missing")],
+        hf_cache: Some(root.path().join("no-cache")),
+        hf_repos: pulled(),
+        busy: Vec::new(),
+    };
+    assert_eq!(prune(&targets, false).unwrap().removed.len(), 2);
+}
+
+#[test]
+fn test_only_pulled_repos_are_collected() {
+    let root = tempfile::tempdir().unwrap();
+    let pulls = root.path().join(".pulls");
+    fs::create_dir_all(pulls.join("user--model")).unwrap();
+    fs::write(pulls.join("stray-file"), b"").unwrap();
+    assert_eq!(pulled_repos(&pulls).unwrap(), pulled());
+    assert!(pulled_repos(&root.path().join("missing")).unwrap().is_empty());
+
+    let targets = PruneTargets {
+        hf_cache: Some(hf_cache(root.path())),
+        ..PruneTargets::default()
+    };
+    assert_eq!(prune(&targets, false).unwrap(), PruneReport::default());
+}
@@ -0,0 +1,272 @@
+//! Choosing what `/api/pull` downloads from a repository
+//!
+//! A pull names `owner/repo`, optionally prefixed with `registry://` and
+//! followed by `@revision` and `:variant`, as in `mirror://owner/repo@v2:W8A8`. One `.rkllm` file is picked by the
+//! variant or by the SoC it was built for, unless file globs name the files
+//! outright. Tokenizer, chat template, vision encoder and LoRA files are
+//! fetched alongside it.
+
+use std::path::Path;
+
+use crate::config::PullConfig;
+use crate::server::apis::error::ApiError;
+use crate::server::hub::RepoFile;
+use crate::server::store::{ModelName, DEFAULT_TAG};
+
+/// Where `detect_soc` looks for the board's SoC
+pub const DEVICE_TREE_COMPATIBLE: &str = "/proc/device-tree/compatible";
+
+const COMPANIONS: &[&str] = &[
+    "tokenizer.json",
+    "tokenizer_config.json",
+    "tokenizer.model",
+    "special_tokens_map.json",
+    "chat_template.json",
+    "chat_template.jinja",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullSpec {
+    /// Registry named with `registry://`, instead of the default one
+    pub registry: Option<String>,
+    pub repo: String,
+    pub revision: Option<String>,
+    pub variant: Option<String>,
+}
+
+impl PullSpec {
+    pub fn parse(name: &str) -> Result<Self, ApiError> {
+        let invalid = |why: &str| {
+            ApiError::InvalidRequest(format!(
+                "name: '{}' {}, expected [registry://]owner/repo[@revision][:variant]",
+                name, why
+            ))
+        };
+        let (registry, name) = match name.trim().split_once("://") {
+            Some((registry, rest)) => (Some(registry), rest),
+            None => (None, name.trim()),
+        };
+        if registry.is_some_and(str::is_empty) {
+            return Err(invalid("has an empty registry"));
+        }
+        // A ':' before the last '/' is not a variant separator.
+        let (rest, variant) = match name.rsplit_once(':') {
+            Some((rest, variant)) if !variant.contains('/') => (rest, Some(variant)),
+            _ => (name, None),
+        };
+        let (repo, revision) = match rest.split_once('@') {
+            Some((repo, revision)) => (repo, Some(revision)),
+            None => (rest, None),
+        };
+
+        // Hub repositories are `owner/name`; plain HTTP registries may use bare names.
+        let valid_part = |part: &str| !part.is_empty() && part != "." && part != "..";
+        let parts: Vec<&str> = repo.split('/').collect();
+        if parts.len() > 2 || !parts.iter().all(|part| valid_part(part)) {
+            return Err(invalid("is not a repository id"));
+        }
+        if revision.is_some_and(str::is_empty) {
+            return Err(invalid("has an empty revision"));
+        }
+        if variant.is_some_and(str::is_empty) {
+            return Err(invalid("has an empty variant"));
+        }
+        Ok(Self {
+            registry: registry.map(str::to_string),
+            repo: repo.to_string(),
+            revision: revision.map(str::to_string),
+            variant: variant.map(str::to_string),
+        })
+    }
+
+    pub fn revision(&self) -> &str {
+        self.revision.as_deref().unwrap_or("main")
+    }
+
+    /// The directory under `models_path/.pulls` a pull is staged in, e.g. `owner--repo`
+    pub fn dir_name(&self) -> String {
+        self.repo.replace('/', "--")
+    }
+
+    /// The name a pull is stored under, e.g. `owner/repo:w8a8`
+    pub fn model_name(&self) -> ModelName {
+        let name: Vec<String> = self.repo.split('/').map(ModelName::sanitize).collect();
+        ModelName {
+            name: name.join("/"),
+            tag: self.variant.as_deref().map_or(DEFAULT_TAG.to_string(), ModelName::sanitize),
+        }
+    }
+}
+
+/// The SoC to pick builds for: `pull.soc` when set, otherwise the board's.
+pub fn target_soc(config: &PullConfig) -> Option<String> {
+    match &config.soc {
+        Some(soc) => named_socs(soc).into_iter().next(),
+        None => detect_soc(),
+    }
+}
+
+/// The SoC named in the device tree, e.g. `rk3588` for `rockchip,rk3588s`.
+pub fn detect_soc() -> Option<String> {
+    let compatible = std::fs::read(DEVICE_TREE_COMPATIBLE).ok()?;
+    soc_from_compatible(&compatible)
+}
+
+/// The first Rockchip SoC in a NUL-separated `compatible` list.
+pub fn soc_from_compatible(compatible: &[u8]) -> Option<String> {
+    compatible
+        .split(|&b| b == 0)
+        .filter_map(|entry| std::str::from_utf8(entry).ok())
+        .filter_map(|entry| entry.strip_prefix("rockchip,"))
+        .find_map(|chip| named_socs(chip).into_iter().next())
+}
+
+/// The `rkNNNN` chips a file name mentions, lowercased.
+fn named_socs(name: &str) -> Vec<String> {
+    let lower = name.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+    (0..bytes.len())
+        .filter(|&i| {
+            bytes[i..].starts_with(b"rk")
+                && bytes.len() >= i + 6
+                && bytes[i + 2..i + 6].iter().all(u8::is_ascii_digit)
+        })
+        .map(|i| lower[i..i + 6].to_string())
+        .collect()
+}
+
+/// True unless the file names only chips other than `soc`.
+fn fits_soc(name: &str, soc: Option<&str>) -> bool {
+    let Some(soc) = soc else {
+        return true;
+    };
+    let socs = named_socs(name);
+    socs.is_empty() || socs.iter().any(|named| named == soc)
+}
+
+fn basename(name: &str) -> &str {
+    Path::new(name)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(name)
+}
+
+pub fn is_lora(name: &str) -> bool {
+    name.ends_with(".rkllm") && basename(name).to_ascii_lowercase().contains("lora")
+}
+
+fn is_companion(name: &str) -> bool {
+    COMPANIONS.contains(&basename(name))
+        || name.ends_with(".onnx")
+        || name.ends_with(".rknn")
+        || is_lora(name)
+}
+
+/// `*` matches any run of characters and `?` a single one.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // Let the last `*` swallow one more character.
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn names(files: &[&RepoFile]) -> String {
+    files.iter().map(|f| f.rfilename.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+/// The files of a repository to download for `spec`: the model files chosen
+/// by `globs`, or else by the variant and `soc`, followed by their companions.
+pub fn select_files<'a>(
+    files: &'a [RepoFile],
+    spec: &PullSpec,
+    globs: &[String],
+    soc: Option<&str>,
+) -> Result<Vec<&'a RepoFile>, ApiError> {
+    let selected: Vec<&RepoFile> = if !globs.is_empty() {
+        let selected: Vec<_> = files
+            .iter()
+            .filter(|f| {
+                globs
+                    .iter()
+                    .any(|g| glob_match(g, &f.rfilename) || glob_match(g, basename(&f.rfilename)))
+            })
+            .collect();
+        if selected.is_empty() {
+            return Err(ApiError::ModelNotFound(format!(
+                "No files in {} match {}",
+                spec.repo,
+                globs.join(", ")
+            )));
+        }
+        selected
+    } else {
+        let models: Vec<_> = files
+            .iter()
+            .filter(|f| f.rfilename.ends_with(".rkllm") && !is_lora(&f.rfilename))
+            .collect();
+        if models.is_empty() {
+            return Err(ApiError::ModelNotFound(format!(
+                "No .rkllm files found in repo {}",
+                spec.repo
+            )));
+        }
+        let mut candidates = models.clone();
+        if let Some(variant) = &spec.variant {
+            let variant = variant.to_ascii_lowercase();
+            candidates.retain(|f| basename(&f.rfilename).to_ascii_lowercase().contains(&variant));
+        }
+        candidates.retain(|f| fits_soc(&f.rfilename, soc));
+        match candidates.len() {
+            0 => {
+                let wanted = match (&spec.variant, soc) {
+                    (Some(variant), Some(soc)) => format!("variant '{}' for {}", variant, soc),
+                    (Some(variant), None) => format!("variant '{}'", variant),
+                    (None, Some(soc)) => format!("a build for {}", soc),
+                    (None, None) => "a model".to_string(),
+                };
+                return Err(ApiError::ModelNotFound(format!(
+                    "{} has no {}; available: {}",
+                    spec.repo,
+                    wanted,
+                    names(&models)
+                )));
+            }
+            1 => candidates,
+            _ => {
+                return Err(ApiError::InvalidRequest(format!(
+                    "{} has several matching models: {}; pick one with {}:<variant> or a file glob",
+                    spec.repo,
+                    names(&candidates),
+                    spec.repo
+                )))
+            }
+        }
+    };
+
+    let companions = files.iter().filter(|f| {
+        is_companion(&f.rfilename)
+            && fits_soc(&f.rfilename, soc)
+            && !selected.iter().any(|s| s.rfilename == f.rfilename)
+    });
+    Ok(selected.iter().copied().chain(companions).collect())
+}
+
+#[cfg(test)]
+#[path = "pull_spec_test.rs"]
+mod tests;
@@ -0,0 +1,128 @@
+//! Tests for choosing the files a pull downloads
+
+use super::*;
+
+fn repo(names: &[&str]) -> Vec<RepoFile> {
+    names
+        .iter()
+        .map(|name| RepoFile {
+            rfilename: name.to_string(),
+            size: None,
+            lfs: None,
+            url: String::new(),
+        })
+        .collect()
+}
+
+fn selected(files: &[RepoFile], spec: &str, globs: &[&str], soc: Option<&str>) -> Result<Vec<String>, ApiError> {
+    let spec = PullSpec::parse(spec).unwrap();
+    let globs: Vec<String> = globs.iter().map(|g| g.to_string()).collect();
+    Ok(select_files(files, &spec, &globs, soc)?
+        .into_iter()
+        .map(|f| f.rfilename.clone())
+        .collect())
+}
+
+const QWEN: &[&str] = &[
+    "README.md",
+    "Qwen2-VL-2B_W8A8_RK3588.rkllm",
+    "Qwen2-VL-2B_W4A16_RK3588.rkllm",
+    "Qwen2-VL-2B_W8A8_RK3576.rkllm",
+    "lora_RK3588.rkllm",
+    "vision_rk3576.rknn",
+    "vision_rk3588.rknn",
+    "tokenizer.json",
+    "configs/chat_template.jinja",
+];
+
+#[test]
+fn test_parse_spec() {
+    let spec = PullSpec::parse("owner/repo@v2:W8A8").unwrap();
+    assert_eq!(spec.registry, None);
+    assert_eq!(spec.repo, "owner/repo");
+    assert_eq!(spec.revision(), "v2");
+    assert_eq!(spec.variant.as_deref(), Some("W8A8"));
+    assert_eq!(spec.dir_name(), "owner--repo");
+    assert_eq!(spec.model_name().to_string(), "owner/repo:w8a8");
+
+    let spec = PullSpec::parse("owner/repo").unwrap();
+    assert_eq!(spec.model_name().to_string(), "owner/repo:latest");
+    assert_eq!((spec.revision(), spec.variant), ("main", None));
+
+    let spec = PullSpec::parse("mirror://qwen2-vl:W8A8").unwrap();
+    assert_eq!(spec.registry.as_deref(), Some("mirror"));
+    assert_eq!(spec.repo, "qwen2-vl");
+    assert_eq!(spec.variant.as_deref(), Some("W8A8"));
+
+    for bad in ["owner/", "owner/repo/extra", "../repo", "owner/repo@", "owner/repo:", "://owner/repo"] {
+        assert!(PullSpec::parse(bad).is_err(), "{} should be rejected", bad);
+    }
+}
+
+#[test]
+fn test_soc_from_compatible() {
+    assert_eq!(
+        soc_from_compatible(b"radxa,rock-5b\0rockchip,rk3588s\0"),
+        Some("rk3588".to_string())
+    );
+    assert_eq!(soc_from_compatible(b"raspberrypi,4-model-b\0brcm,bcm2711\0"), None);
+    assert_eq!(target_soc(&PullConfig { soc: Some("RK3576".into()) }), Some("rk3576".to_string()));
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("*.rkllm", "model_W8A8.rkllm"));
+    assert!(glob_match("*W8A8*", "model_W8A8.rkllm"));
+    assert!(glob_match("model_W?A8.rkllm", "model_W8A8.rkllm"));
+    assert!(!glob_match("*.rkllm", "model.rkllm.partial"));
+    assert!(!glob_match("model", "model_W8A8.rkllm"));
+}
+
+#[test]
+fn test_soc_and_variant_pick_one_model_with_companions() {
+    let files = repo(QWEN);
+    assert_eq!(
+        selected(&files, "owner/qwen:w8a8", &[], Some("rk3588")).unwrap(),
+        vec![
+            "Qwen2-VL-2B_W8A8_RK3588.rkllm",
+            "lora_RK3588.rkllm",
+            "vision_rk3588.rknn",
+            "tokenizer.json",
+            "configs/chat_template.jinja",
+        ]
+    );
+    assert_eq!(
+        selected(&files, "owner/qwen", &[], Some("rk3576")).unwrap()[0],
+        "Qwen2-VL-2B_W8A8_RK3576.rkllm"
+    );
+}
+
+#[test]
+fn test_ambiguous_and_missing_variants() {
+    let files = repo(QWEN);
+    let err = selected(&files, "owner/qwen", &[], Some("rk3588")).unwrap_err();
+    assert!(matches!(err, ApiError::InvalidRequest(msg) if msg.contains("W4A16_RK3588") && msg.contains("W8A8_RK3588")));
+
+    let err = selected(&files, "owner/qwen:W4A16", &[], Some("rk3576")).unwrap_err();
+    assert!(matches!(err, ApiError::ModelNotFound(msg) if msg.contains("variant 'W4A16' for rk3576")));
+}
+
+#[test]
+fn test_globs_select_files_outright() {
+    let files = repo(QWEN);
+    assert_eq!(
+        selected(&files, "owner/qwen:ignored", &["*W4A16*"], None).unwrap(),
+        vec![
+            "Qwen2-VL-2B_W4A16_RK3588.rkllm",
+            "lora_RK3588.rkllm",
+            "vision_rk3576.rknn",
+            "vision_rk3588.rknn",
+            "tokenizer.json",
+            "configs/chat_template.jinja",
+        ]
+    );
+    assert!(matches!(
+        selected(&files, "owner/qwen", &["*.gguf"], None),
+        Err(ApiError::ModelNotFound(_))
+    ));
+}
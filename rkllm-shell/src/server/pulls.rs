@@ -0,0 +1,71 @@
+//! Tracking of `/api/pull` downloads in progress
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PullProgress {
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<u64>,
+}
+
+/// Pulls currently in progress, keyed by repository id
+#[derive(Clone, Default)]
+pub struct PullTracker(Arc<Mutex<HashMap<String, PullProgress>>>);
+
+impl PullTracker {
+    /// Registers a pull; it is forgotten again when the returned guard drops.
+    /// None when a pull of `name` is already running.
+    pub fn start(&self, name: &str) -> Option<PullGuard> {
+        let mut pulls = self.0.lock().unwrap();
+        if pulls.contains_key(name) {
+            return None;
+        }
+        pulls.insert(
+            name.to_string(),
+            PullProgress {
+                name: name.to_string(),
+                started_at: Utc::now(),
+                status: "pulling manifest".to_string(),
+                total: None,
+                completed: None,
+            },
+        );
+        Some(PullGuard {
+            tracker: self.clone(),
+            name: name.to_string(),
+        })
+    }
+
+    pub fn update(&self, name: &str, status: impl Into<String>, total: Option<u64>, completed: Option<u64>) {
+        if let Some(progress) = self.0.lock().unwrap().get_mut(name) {
+            progress.status = status.into();
+            progress.total = total;
+            progress.completed = completed;
+        }
+    }
+
+    pub fn list(&self) -> Vec<PullProgress> {
+        self.0.lock().unwrap().values().cloned().collect()
+    }
+}
+
+pub struct PullGuard {
+    tracker: PullTracker,
+    name: String,
+}
+
+impl Drop for PullGuard {
+    fn drop(&mut self) {
+        self.tracker.0.lock().unwrap().remove(&self.name);
+    }
+}
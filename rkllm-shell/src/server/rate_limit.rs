@@ -0,0 +1,399 @@
+//! Per-client rate limiting and generated-token quotas
+//!
+//! Requests are limited by a token bucket per client and route group, where
+//! the client is the authenticated API key or, without one, the peer address.
+//! Keys with a `quota` may only generate so many tokens per day or month; the
+//! usage is persisted to `quota_usage.json` in the config directory.
+//! Responses carry OpenAI-style `x-ratelimit-*` headers, and rejections are
+//! `429` with `Retry-After`.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::config::{BucketConfig, QuotaPeriod, RateLimitConfig, Scope, TokenQuota};
+use crate::server::apis::error::ApiError;
+use crate::server::auth::{required_scope, AuthenticatedKey};
+
+/// How often idle buckets are dropped from the limiter
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum RouteGroup {
+    Inference,
+    Admin,
+}
+
+impl RouteGroup {
+    fn for_path(path: &str) -> Option<Self> {
+        match required_scope(path)? {
+            Scope::Inference | Scope::Agent => Some(RouteGroup::Inference),
+            Scope::ModelAdmin => Some(RouteGroup::Admin),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Outcome of taking a request from a bucket
+struct BucketState {
+    limit: u32,
+    remaining: u32,
+    /// Until the bucket is full again (allowed) or holds a request (rejected)
+    reset: Duration,
+}
+
+impl Bucket {
+    /// Whether the bucket has refilled by `now`; such a bucket is the same
+    /// as a new one and can be dropped.
+    fn is_full(&self, config: BucketConfig, now: Instant) -> bool {
+        let per_second = config.requests_per_minute.max(1) as f64 / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * per_second >= config.burst.max(1) as f64
+    }
+
+    fn take(&mut self, config: BucketConfig, now: Instant) -> Result<BucketState, BucketState> {
+        let capacity = config.burst.max(1) as f64;
+        let per_second = config.requests_per_minute.max(1) as f64 / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second).min(capacity);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(BucketState {
+                limit: config.burst.max(1),
+                remaining: self.tokens.floor() as u32,
+                reset: Duration::from_secs_f64((capacity - self.tokens) / per_second),
+            })
+        } else {
+            Err(BucketState {
+                limit: config.burst.max(1),
+                remaining: 0,
+                reset: Duration::from_secs_f64((1.0 - self.tokens) / per_second),
+            })
+        }
+    }
+}
+
+/// Identifies the current quota window, e.g. `2026-10-18` or `2026-10`.
+fn period_id(period: QuotaPeriod, now: DateTime<Utc>) -> String {
+    match period {
+        QuotaPeriod::Daily => now.format("%Y-%m-%d").to_string(),
+        QuotaPeriod::Monthly => now.format("%Y-%m").to_string(),
+    }
+}
+
+/// When the current quota window ends (UTC).
+fn period_end(period: QuotaPeriod, now: DateTime<Utc>) -> DateTime<Utc> {
+    let next = match period {
+        QuotaPeriod::Daily => now.date_naive().succ_opt(),
+        QuotaPeriod::Monthly => {
+            let (year, month) = if now.month() == 12 {
+                (now.year() + 1, 1)
+            } else {
+                (now.year(), now.month() + 1)
+            };
+            NaiveDate::from_ymd_opt(year, month, 1)
+        }
+    };
+    next.and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|d| Utc.from_utc_datetime(&d))
+        .unwrap_or(now)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct QuotaUsage {
+    period: String,
+    tokens: u64,
+}
+
+/// Generated tokens per key, persisted across restarts
+#[derive(Clone, Default)]
+pub struct QuotaStore {
+    path: Option<PathBuf>,
+    usage: Arc<Mutex<HashMap<String, QuotaUsage>>>,
+    /// Keeps concurrent writes of the file in order
+    write_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl QuotaStore {
+    /// Loads previous usage from `path`; a missing or corrupt file starts empty.
+    pub fn open(path: PathBuf) -> Self {
+        let usage = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            path: Some(path),
+            usage: Arc::new(Mutex::new(usage)),
+            write_lock: Arc::default(),
+        }
+    }
+
+    /// Tokens `key` generated in the current window.
+    pub fn used(&self, key: &str, period: QuotaPeriod, now: DateTime<Utc>) -> u64 {
+        let current = period_id(period, now);
+        self.usage
+            .lock()
+            .unwrap()
+            .get(key)
+            .filter(|u| u.period == current)
+            .map_or(0, |u| u.tokens)
+    }
+
+    pub async fn charge(&self, key: &str, period: QuotaPeriod, tokens: u64) {
+        let current = period_id(period, Utc::now());
+        // Snapshot under the write lock so a later charge is never
+        // overwritten by an earlier one.
+        let _write = self.write_lock.lock().await;
+        let snapshot = {
+            let mut usage = self.usage.lock().unwrap();
+            let entry = usage.entry(key.to_string()).or_default();
+            if entry.period != current {
+                *entry = QuotaUsage {
+                    period: current,
+                    tokens: 0,
+                };
+            }
+            entry.tokens += tokens;
+            serde_json::to_vec_pretty(&*usage)
+        };
+        if let (Some(path), Ok(bytes)) = (&self.path, snapshot) {
+            if let Err(e) = tokio::fs::write(path, bytes).await {
+                tracing::warn!(error = %e, path = %path.display(), "failed to persist quota usage");
+            }
+        }
+    }
+}
+
+/// Request extension charging generated tokens to the caller's quota
+#[derive(Clone)]
+pub struct TokenUsage {
+    store: QuotaStore,
+    key: String,
+    period: QuotaPeriod,
+}
+
+impl TokenUsage {
+    /// Forwards tokens from `rx`, charging them once the stream ends or the
+    /// consumer goes away.
+    pub fn track(&self, mut rx: UnboundedReceiver<String>) -> UnboundedReceiver<String> {
+        let (tx, out) = mpsc::unbounded_channel();
+        let usage = self.clone();
+        tokio::spawn(async move {
+            let mut tokens = 0u64;
+            while let Some(token) = rx.recv().await {
+                tokens += 1;
+                if tx.send(token).is_err() {
+                    break;
+                }
+            }
+            usage.charge(tokens).await;
+        });
+        out
+    }
+
+    /// Charges `tokens` generated outside a token stream.
+    pub async fn charge(&self, tokens: u64) {
+        self.store.charge(&self.key, self.period, tokens).await;
+    }
+}
+
+/// Charges the tokens streamed through `rx` to `usage`, when the request has one.
+pub fn track_usage(usage: Option<&TokenUsage>, rx: UnboundedReceiver<String>) -> UnboundedReceiver<String> {
+    match usage {
+        Some(usage) => usage.track(rx),
+        None => rx,
+    }
+}
+
+/// Shared limiter state, cloned into the middleware
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Arc<Mutex<Buckets>>,
+    quotas: QuotaStore,
+}
+
+struct Buckets {
+    by_client: HashMap<(RouteGroup, String), Bucket>,
+    last_sweep: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig, quotas: QuotaStore) -> Self {
+        Self {
+            config,
+            buckets: Arc::new(Mutex::new(Buckets {
+                by_client: HashMap::new(),
+                last_sweep: Instant::now(),
+            })),
+            quotas,
+        }
+    }
+
+    fn bucket_config(&self, group: RouteGroup) -> Option<BucketConfig> {
+        match group {
+            RouteGroup::Inference => self.config.inference,
+            RouteGroup::Admin => self.config.admin,
+        }
+    }
+
+    fn take(&self, group: RouteGroup, client: &str) -> Option<Result<BucketState, BucketState>> {
+        let config = self.bucket_config(group)?;
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if now.saturating_duration_since(buckets.last_sweep) >= SWEEP_INTERVAL {
+            self.sweep(&mut buckets, now);
+        }
+        let bucket = buckets
+            .by_client
+            .entry((group, client.to_string()))
+            .or_insert_with(|| Bucket {
+                tokens: config.burst.max(1) as f64,
+                updated: now,
+            });
+        Some(bucket.take(config, now))
+    }
+
+    /// Drops the buckets of clients that have been idle long enough to refill,
+    /// so the map does not grow with every client ever seen.
+    fn sweep(&self, buckets: &mut Buckets, now: Instant) {
+        buckets.by_client.retain(|(group, _), bucket| {
+            self.bucket_config(*group)
+                .is_some_and(|config| !bucket.is_full(config, now))
+        });
+        buckets.last_sweep = now;
+    }
+}
+
+/// Formats a duration the way OpenAI's `x-ratelimit-reset-*` headers do,
+/// e.g. `250ms`, `12s` or `6m0s`.
+fn format_reset(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis < 1000 {
+        return format!("{}ms", millis);
+    }
+    let secs = duration.as_secs_f64().ceil() as u64;
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{}s", m, s),
+        (h, m, s) => format!("{}h{}m{}s", h, m, s),
+    }
+}
+
+fn set_header(headers: &mut HeaderMap, name: &'static str, value: String) {
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        headers.insert(HeaderName::from_static(name), value);
+    }
+}
+
+fn request_headers(headers: &mut HeaderMap, state: &BucketState) {
+    set_header(headers, "x-ratelimit-limit-requests", state.limit.to_string());
+    set_header(headers, "x-ratelimit-remaining-requests", state.remaining.to_string());
+    set_header(headers, "x-ratelimit-reset-requests", format_reset(state.reset));
+}
+
+fn token_headers(headers: &mut HeaderMap, quota: TokenQuota, used: u64, reset: Duration) {
+    set_header(headers, "x-ratelimit-limit-tokens", quota.tokens.to_string());
+    set_header(
+        headers,
+        "x-ratelimit-remaining-tokens",
+        quota.tokens.saturating_sub(used).to_string(),
+    );
+    set_header(headers, "x-ratelimit-reset-tokens", format_reset(reset));
+}
+
+fn too_many_requests(message: String, retry_after: Duration) -> Response {
+    let mut response = ApiError::RateLimitExceeded(message).into_response();
+    let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    set_header(response.headers_mut(), "retry-after", secs.to_string());
+    response
+}
+
+/// Middleware enforcing request rates and token quotas. Runs inside the auth
+/// middleware so it can key limits by the authenticated key.
+pub async fn limit_requests(State(limiter): State<RateLimiter>, mut request: Request, next: Next) -> Response {
+    let Some(group) = RouteGroup::for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let key = request.extensions().get::<AuthenticatedKey>().cloned();
+    let client = match &key {
+        Some(key) => format!("key:{}", key.name),
+        None => request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| format!("ip:{}", addr.ip()))
+            .unwrap_or_else(|| "unknown".to_string()),
+    };
+
+    let bucket = match limiter.take(group, &client) {
+        Some(Err(state)) => {
+            tracing::warn!(%client, "request rate limit exceeded");
+            let mut response = too_many_requests(
+                format!("Rate limit of {} requests reached, retry later", state.limit),
+                state.reset,
+            );
+            request_headers(response.headers_mut(), &state);
+            return response;
+        }
+        Some(Ok(state)) => Some(state),
+        None => None,
+    };
+
+    let quota = match (&key, group) {
+        (Some(key), RouteGroup::Inference) => key.quota.map(|quota| (key.name.clone(), quota)),
+        _ => None,
+    };
+    let mut quota_headers = None;
+    if let Some((name, quota)) = quota {
+        let now = Utc::now();
+        let used = limiter.quotas.used(&name, quota.period, now);
+        let reset = (period_end(quota.period, now) - now).to_std().unwrap_or_default();
+        if used >= quota.tokens {
+            tracing::warn!(key = %name, used, "token quota exhausted");
+            let mut response = too_many_requests(
+                format!("Token quota of {} exhausted for key '{}'", quota.tokens, name),
+                reset,
+            );
+            token_headers(response.headers_mut(), quota, used, reset);
+            return response;
+        }
+        request.extensions_mut().insert(TokenUsage {
+            store: limiter.quotas.clone(),
+            key: name,
+            period: quota.period,
+        });
+        quota_headers = Some((quota, used, reset));
+    }
+
+    let mut response = next.run(request).await;
+    if let Some(state) = &bucket {
+        request_headers(response.headers_mut(), state);
+    }
+    if let Some((quota, used, reset)) = quota_headers {
+        token_headers(response.headers_mut(), quota, used, reset);
+    }
+    response
+}
+
+#[cfg(test)]
+#[path = "rate_limit_test.rs"]
+mod tests;
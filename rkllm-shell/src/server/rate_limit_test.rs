@@ -0,0 +1,143 @@
+use super::*;
+use crate::config::ApiKeyConfig;
+use crate::server::auth::{hash_key, require_api_key, ApiKeys, API_KEY_HEADER};
+use axum::{http::StatusCode, routing::post, Router};
+use axum_test::TestServer;
+
+fn test_server(config: RateLimitConfig, quotas: QuotaStore, quota: Option<TokenQuota>) -> TestServer {
+    let keys = ApiKeys::new(
+        vec![ApiKeyConfig {
+            name: "notebook".to_string(),
+            sha256: hash_key("secret"),
+            scopes: vec![Scope::Inference, Scope::ModelAdmin],
+            quota,
+        }],
+        true,
+    );
+    let app = Router::new()
+        .route("/api/generate", post(|| async { "generated" }))
+        .route("/api/pull", post(|| async { "pulled" }))
+        .layer(axum::middleware::from_fn_with_state(
+            RateLimiter::new(config, quotas),
+            limit_requests,
+        ))
+        .layer(axum::middleware::from_fn_with_state(keys, require_api_key));
+    TestServer::new(app)
+}
+
+#[test]
+fn test_format_reset() {
+    assert_eq!(format_reset(Duration::from_millis(250)), "250ms");
+    assert_eq!(format_reset(Duration::from_secs(12)), "12s");
+    assert_eq!(format_reset(Duration::from_secs(360)), "6m0s");
+    assert_eq!(format_reset(Duration::from_secs(3723)), "1h2m3s");
+}
+
+#[test]
+fn test_period_end() {
+    let now = Utc.with_ymd_and_hms(2026, 12, 31, 15, 0, 0).unwrap();
+    assert_eq!(
+        period_end(QuotaPeriod::Daily, now),
+        Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+        period_end(QuotaPeriod::Monthly, now),
+        Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_bucket_rejects_after_burst() {
+    let config = RateLimitConfig {
+        inference: Some(BucketConfig {
+            requests_per_minute: 1,
+            burst: 2,
+        }),
+        admin: None,
+    };
+    let server = test_server(config, QuotaStore::default(), None);
+
+    for remaining in ["1", "0"] {
+        let response = server.post("/api/generate").add_header(API_KEY_HEADER, "secret").await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        assert_eq!(response.header("x-ratelimit-limit-requests"), "2");
+        assert_eq!(response.header("x-ratelimit-remaining-requests"), remaining);
+    }
+
+    let response = server.post("/api/generate").add_header(API_KEY_HEADER, "secret").await;
+    assert_eq!(response.status_code(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.header("retry-after").to_str().unwrap().parse::<u64>().unwrap() >= 1);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["type"], "rate_limit_exceeded");
+
+    // Admin routes have their own (here: unlimited) group.
+    let response = server.post("/api/pull").add_header(API_KEY_HEADER, "secret").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[test]
+fn test_idle_buckets_are_swept() {
+    let config = RateLimitConfig {
+        inference: Some(BucketConfig {
+            requests_per_minute: 60,
+            burst: 2,
+        }),
+        admin: None,
+    };
+    let limiter = RateLimiter::new(config, QuotaStore::default());
+    assert!(limiter.take(RouteGroup::Inference, "ip:10.0.0.1").unwrap().is_ok());
+    assert!(limiter.take(RouteGroup::Inference, "ip:10.0.0.2").unwrap().is_ok());
+
+    let mut buckets = limiter.buckets.lock().unwrap();
+    let now = buckets.last_sweep;
+    // Still refilling: both kept.
+    limiter.sweep(&mut buckets, now);
+    assert_eq!(buckets.by_client.len(), 2);
+    // A second later both are full again, the same as new ones.
+    limiter.sweep(&mut buckets, now + Duration::from_secs(2));
+    assert!(buckets.by_client.is_empty());
+}
+
+#[tokio::test]
+async fn test_exhausted_quota_is_rejected() {
+    let quotas = QuotaStore::default();
+    let quota = TokenQuota {
+        tokens: 10,
+        period: QuotaPeriod::Daily,
+    };
+    let server = test_server(RateLimitConfig::default(), quotas.clone(), Some(quota));
+
+    let response = server.post("/api/generate").add_header(API_KEY_HEADER, "secret").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.header("x-ratelimit-remaining-tokens"), "10");
+
+    quotas.charge("notebook", QuotaPeriod::Daily, 10).await;
+
+    let response = server.post("/api/generate").add_header(API_KEY_HEADER, "secret").await;
+    assert_eq!(response.status_code(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.header("x-ratelimit-remaining-tokens"), "0");
+    assert!(!response.header("retry-after").is_empty());
+}
+
+#[tokio::test]
+async fn test_token_usage_is_charged_and_persisted() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("quota_usage.json");
+    let usage = TokenUsage {
+        store: QuotaStore::open(path.clone()),
+        key: "notebook".to_string(),
+        period: QuotaPeriod::Monthly,
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut out = usage.track(rx);
+    for token in ["a", "b", "c"] {
+        tx.send(token.to_string()).unwrap();
+    }
+    drop(tx);
+    // The forwarder charges before closing its own channel.
+    while out.recv().await.is_some() {}
+
+    let reopened = QuotaStore::open(path);
+    assert_eq!(reopened.used("notebook", QuotaPeriod::Monthly, Utc::now()), 3);
+}
@@ -0,0 +1,74 @@
+//! Per-request tracing spans and `X-Request-Id` propagation
+//!
+//! Every request gets an id (taken from the client's `X-Request-Id` header or
+//! generated), which is recorded on the request span and echoed back in the
+//! response. Handlers fill in the `model` field once they parsed the body.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, Response},
+    Router,
+};
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::Span;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+fn make_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.to_string())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        route = request.uri().path(),
+        client,
+        model = tracing::field::Empty,
+    )
+}
+
+fn on_response(response: &Response<Body>, latency: Duration, _span: &Span) {
+    tracing::info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        "request finished"
+    );
+}
+
+/// Records the model a request targets on the current request span.
+pub fn record_model(model: &str) {
+    Span::current().record("model", model);
+}
+
+/// Wraps `router` so each request runs inside a span carrying its request id.
+pub fn layer(router: Router) -> Router {
+    // Layers run outermost-last: the id is set first, then traced, then
+    // copied onto the response.
+    router
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_span)
+                .on_response(on_response),
+        )
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+}
+
+#[cfg(test)]
+#[path = "request_log_test.rs"]
+mod tests;
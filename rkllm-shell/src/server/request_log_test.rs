@@ -0,0 +1,31 @@
+use super::*;
+use axum::routing::get;
+use axum_test::TestServer;
+
+fn test_app() -> Router {
+    layer(Router::new().route("/ping", get(|| async { "pong" })))
+}
+
+#[tokio::test]
+async fn test_request_id_is_echoed() {
+    let server = TestServer::new(test_app());
+
+    let response = server
+        .get("/ping")
+        .add_header(REQUEST_ID_HEADER, "client-chosen-id")
+        .await;
+    assert_eq!(response.header(REQUEST_ID_HEADER), "client-chosen-id");
+}
+
+#[tokio::test]
+async fn test_request_id_is_generated() {
+    let server = TestServer::new(test_app());
+
+    let response = server.get("/ping").await;
+    let id = response.header(REQUEST_ID_HEADER);
+    assert!(!id.is_empty());
+
+    // Each request gets its own id.
+    let other = server.get("/ping").await.header(REQUEST_ID_HEADER);
+    assert_ne!(id, other);
+}
@@ -3,8 +3,9 @@
 use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex, OnceLock};
-use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, SystemTime};
 
 use rkllm_api_sys::{
     rkllm_createDefaultParam, rkllm_destroy, rkllm_init, rkllm_run, rkllm_set_chat_template,
@@ -16,7 +17,19 @@ use rkllm_api_sys::{
 };
 
 use crate::server::api_models::{ChatCompletionRequest, GenerateRequest};
-use crate::server::vision::{VisionEncoder, StubVisionEncoder, VisionEncoderConfig, build_multimodal_input};
+use crate::server::metrics::Metrics;
+use crate::server::store::{ModelName, ModelStore, ResolvedModel, StoreError, IMPORT_SETTLE};
+use crate::server::tokenizer::TokenizerCache;
+use tracing::Instrument;
+use image::DynamicImage;
+
+use crate::config::VisionModelConfig;
+use crate::server::runtime_trait::RuntimeError;
+use crate::server::vision::{self, VisionEncoder, build_multimodal_input};
+
+/// How often [`RkllmRuntime::unload_and_wait`] checks whether the unloaded
+/// handles have been destroyed.
+const UNLOAD_POLL: Duration = Duration::from_millis(50);
 
 pub enum CompletionRequest {
     Generate(GenerateRequest),
@@ -33,12 +46,22 @@ impl CompletionRequest {
 }
 
 // ---------------------------------------------------------------------------
-// Thread-safe wrapper around the raw LLMHandle pointer
+// Thread-safe wrapper around the raw LLMHandle pointer; destroys it on drop.
 // ---------------------------------------------------------------------------
 
 #[derive(Debug)]
 pub struct ThreadSafeLLMHandle(LLMHandle);
 
+impl Drop for ThreadSafeLLMHandle {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe {
+                rkllm_destroy(self.0);
+            }
+        }
+    }
+}
+
 unsafe impl Send for ThreadSafeLLMHandle {}
 unsafe impl Sync for ThreadSafeLLMHandle {}
 
@@ -56,46 +79,164 @@ impl ThreadSafeLLMHandle {
 struct RawHandleSend(LLMHandle);
 unsafe impl Send for RawHandleSend {}
 
+// Passed to `rkllm_run` as userdata. The callback owns it and frees it when
+// the run finishes; the caller frees it when `rkllm_run` fails to start.
+struct InferenceContext {
+    sender: tokio::sync::mpsc::UnboundedSender<String>,
+    metrics: Metrics,
+}
+
+impl InferenceContext {
+    fn into_userdata(self) -> usize {
+        Box::into_raw(Box::new(self)) as usize
+    }
+
+    /// Reclaims ownership of a context previously leaked by `into_userdata`.
+    unsafe fn from_userdata(userdata: *mut ::std::os::raw::c_void) -> Box<InferenceContext> {
+        Box::from_raw(userdata as *mut InferenceContext)
+    }
+}
+
 // ---------------------------------------------------------------------------
-// RkllmModel — owns the native handle; destroys it on drop.
+// ModelActivity — serialises `rkllm_run` calls on one handle and counts them.
+// ---------------------------------------------------------------------------
+
+#[derive(Default)]
+pub struct ModelActivity {
+    run_lock: Mutex<()>,
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+}
+
+impl ModelActivity {
+    /// Runs `f` once no other inference is running on the same handle.
+    fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        let _guard = self.run_lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let result = f();
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// RkllmModel — shares the native handle with the inferences running on it,
+// so unloading a model mid-run destroys the handle once the run ends.
 // ---------------------------------------------------------------------------
 
 
 pub struct RkllmModel {
-    handle: ThreadSafeLLMHandle,
-    // Vision encoder for multimodal support (optional, created on first use)
+    handle: Arc<ThreadSafeLLMHandle>,
+    // Vision encoder for multimodal support, if configured (created on first use)
+    vision: Option<VisionModelConfig>,
     vision_encoder: OnceLock<Arc<dyn VisionEncoder>>,
+    // Name in the model store
+    name: ModelName,
     // Path to the model file (for tracking)
     model_path: String,
-}
-
-impl Drop for RkllmModel {
-    fn drop(&mut self) {
-        let h = self.handle.as_llm_handle();
-        if !h.is_null() {
-            unsafe {
-                rkllm_destroy(h);
-            }
-        }
-    }
+    tokenizer_path: Option<PathBuf>,
+    loaded_at: SystemTime,
+    // Last time a request picked the model; resets with its keep-alive
+    last_used: Mutex<SystemTime>,
+    activity: Arc<ModelActivity>,
+    metrics: Metrics,
 }
 
 impl RkllmModel {
     /// Creates a new RkllmModel with an optional vision encoder for multimodal support
-    pub fn new(handle: ThreadSafeLLMHandle, model_path: String) -> Self {
+    pub fn new(
+        handle: ThreadSafeLLMHandle,
+        model: &ResolvedModel,
+        vision: Option<VisionModelConfig>,
+        metrics: Metrics,
+    ) -> Self {
         Self {
-            handle,
+            handle: Arc::new(handle),
+            vision,
             vision_encoder: OnceLock::new(),
-            model_path,
+            name: model.name.clone(),
+            model_path: model.model_path.to_string_lossy().into_owned(),
+            tokenizer_path: model.tokenizer_path.clone(),
+            loaded_at: SystemTime::now(),
+            last_used: Mutex::new(SystemTime::now()),
+            activity: Arc::new(ModelActivity::default()),
+            metrics,
         }
     }
 
-    /// Get or initialize the vision encoder for multimodal support
-    fn vision_encoder(&self) -> Arc<dyn VisionEncoder> {
-        self.vision_encoder.get_or_init(|| {
-            let config = VisionEncoderConfig::default();
-            Arc::new(StubVisionEncoder::new(config))
-        }).clone()
+    fn inference_context(
+        &self,
+        sender: tokio::sync::mpsc::UnboundedSender<String>,
+    ) -> InferenceContext {
+        InferenceContext {
+            sender,
+            metrics: self.metrics.clone(),
+        }
+    }
+
+    pub fn loaded_at(&self) -> SystemTime {
+        self.loaded_at
+    }
+
+    pub fn last_used(&self) -> SystemTime {
+        *self.last_used.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn touch(&self) {
+        *self.last_used.lock().unwrap_or_else(|e| e.into_inner()) = SystemTime::now();
+    }
+
+    pub fn name(&self) -> &ModelName {
+        &self.name
+    }
+
+    pub fn model_path(&self) -> &Path {
+        Path::new(&self.model_path)
+    }
+
+    /// The tokenizer stored with the model, if any
+    pub fn tokenizer_path(&self) -> Option<&Path> {
+        self.tokenizer_path.as_deref()
+    }
+
+    pub fn activity(&self) -> &ModelActivity {
+        &self.activity
+    }
+
+    /// Get or load the vision encoder for multimodal support. Loading runs in
+    /// a blocking thread, as optimising an ONNX model takes a while.
+    pub async fn vision_encoder(&self) -> Result<Arc<dyn VisionEncoder>, RuntimeError> {
+        if let Some(encoder) = self.vision_encoder.get() {
+            return Ok(encoder.clone());
+        }
+        let Some(config) = self.vision.clone() else {
+            return Err(RuntimeError::VisionUnavailable(format!(
+                "model '{}' does not accept images: no vision encoder is configured for it",
+                self.name
+            )));
+        };
+        let started = std::time::Instant::now();
+        let encoder = tokio::task::spawn_blocking(move || vision::load_encoder(&config))
+            .await
+            .map_err(|e| RuntimeError::Internal(format!("spawn_blocking panicked: {}", e)))?
+            .map_err(|e| RuntimeError::LoadFailed(format!("vision encoder: {:#}", e)))?;
+        tracing::info!(
+            model = %self.model_path,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "vision encoder loaded"
+        );
+        // A concurrent request may have loaded it too; keep the first.
+        Ok(self.vision_encoder.get_or_init(|| encoder).clone())
     }
 
     /// Runs inference in a blocking thread so the tokio executor is never stalled.
@@ -103,25 +244,49 @@ impl RkllmModel {
     pub fn run_inference(
         &self,
         messages: Vec<String>,
+    ) -> tokio::sync::mpsc::UnboundedReceiver<String> {
+        self.run_inference_with_thinking(messages, false)
+    }
+
+    /// Like `run_inference`, letting reasoning models think first when
+    /// `enable_thinking` is set.
+    pub fn run_inference_with_thinking(
+        &self,
+        messages: Vec<String>,
+        enable_thinking: bool,
     ) -> tokio::sync::mpsc::UnboundedReceiver<String> {
         let combined_msg = messages.join("\n");
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<String>();
 
-        // Convert the handle and sender box-pointer to `usize` so the closure
+        // The closure keeps the handle alive until `rkllm_run` returns, even
+        // if the model is unloaded meanwhile.
+        let handle = self.handle.clone();
+        // Box the context and capture its address as `usize` so the closure
         // is `Send + 'static` (raw pointers are neither).
-        let handle_usize = self.handle.as_llm_handle() as usize;
-        // Box the sender and capture its address as usize.
-        let tx_ptr_usize = Box::into_raw(Box::new(tx)) as usize;
+        let ctx_ptr_usize = self.inference_context(tx).into_userdata();
+        let activity = self.activity.clone();
+        // Created here so it nests under the caller's request span.
+        let span = tracing::info_span!("inference", model = %self.model_path);
 
         tokio::task::spawn_blocking(move || {
-            // Restore typed pointers inside the blocking thread.
-            let handle = handle_usize as LLMHandle;
-            let sender_ptr = tx_ptr_usize as *mut ::std::os::raw::c_void;
+            let _span = span.enter();
+            // Restore the typed pointer inside the blocking thread.
+            let ctx_ptr = ctx_ptr_usize as *mut ::std::os::raw::c_void;
 
-            let msgs_cstr = CString::new(combined_msg).expect("CString::new failed");
+            // Requests are validated, but never let a stray NUL panic the thread.
+            let msgs_cstr = match CString::new(combined_msg) {
+                Ok(cstr) => cstr,
+                Err(e) => {
+                    tracing::error!(error = %e, "prompt contains a NUL byte");
+                    unsafe {
+                        drop(InferenceContext::from_userdata(ctx_ptr));
+                    }
+                    return;
+                }
+            };
             let mut rkllm_input = RKLLMInput {
                 role: std::ptr::null(),
-                enable_thinking: false,
+                enable_thinking,
                 input_type: RKLLMInputType_RKLLM_INPUT_PROMPT,
                 __bindgen_anon_1: RKLLMInput__bindgen_ty_1 {
                     prompt_input: msgs_cstr.as_ptr(),
@@ -136,23 +301,20 @@ impl RkllmModel {
                 max_new_tokens: -1,
             };
 
-            unsafe {
+            activity.run(|| unsafe {
                 let result = rkllm_run(
-                    handle,
+                    handle.as_llm_handle(),
                     &mut rkllm_input,
                     &mut rkllm_infer_params,
-                    sender_ptr,
+                    ctx_ptr,
                 );
 
                 if result != 0 {
-                    // Clean up sender — dropping it closes the channel so the
-                    // receiver sees EOF.
-                    let _sender = Box::from_raw(
-                        sender_ptr
-                            as *mut tokio::sync::mpsc::UnboundedSender<String>,
-                    );
+                    // Clean up the context — dropping its sender closes the
+                    // channel so the receiver sees EOF.
+                    drop(InferenceContext::from_userdata(ctx_ptr));
                 }
-            }
+            });
             // msgs_cstr kept alive until here (past the rkllm_run call).
             drop(msgs_cstr);
         });
@@ -160,41 +322,68 @@ impl RkllmModel {
         rx
     }
 
-    /// Runs multimodal inference with text prompt and base64-encoded images.
+    /// Runs multimodal inference with text prompt, base64-encoded images and
+    /// the frames of each video.
     /// Returns an async-compatible receiver that yields token strings.
-    pub fn run_multimodal_inference(
+    pub async fn run_multimodal_inference(
         &self,
         prompt: String,
         images_base64: Vec<String>,
-    ) -> tokio::sync::mpsc::UnboundedReceiver<String> {
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        videos: Vec<Vec<DynamicImage>>,
+    ) -> Result<tokio::sync::mpsc::UnboundedReceiver<String>, RuntimeError> {
+        self.run_multimodal_inference_with_thinking(prompt, images_base64, videos, false)
+            .await
+    }
 
-        // Get the vision encoder
-        let vision_encoder = self.vision_encoder();
+    /// Like `run_multimodal_inference`, with `enable_thinking` as in
+    /// `run_inference_with_thinking`.
+    pub async fn run_multimodal_inference_with_thinking(
+        &self,
+        prompt: String,
+        images_base64: Vec<String>,
+        videos: Vec<Vec<DynamicImage>>,
+        enable_thinking: bool,
+    ) -> Result<tokio::sync::mpsc::UnboundedReceiver<String>, RuntimeError> {
+        let vision_encoder = self.vision_encoder().await?;
+        let profile = self.vision.as_ref().map(vision::profile).unwrap_or_default();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<String>();
 
-        // Convert the handle and sender box-pointer to `usize` so the closure
-        // is `Send + 'static` (raw pointers are neither).
-        let handle_usize = self.handle.as_llm_handle() as usize;
-        let tx_ptr_usize = Box::into_raw(Box::new(tx)) as usize;
+        // As in `run_inference_with_thinking`, the closure holds the handle.
+        let handle = self.handle.clone();
+        let ctx_ptr_usize = self.inference_context(tx).into_userdata();
+        let activity = self.activity.clone();
+        let span = tracing::info_span!(
+            "inference",
+            model = %self.model_path,
+            images = images_base64.len(),
+            videos = videos.len(),
+        );
 
         tokio::task::spawn_blocking(move || {
-            let handle = handle_usize as LLMHandle;
-            let sender_ptr = tx_ptr_usize as *mut ::std::os::raw::c_void;
-
-            // Build multimodal input using the vision encoder
-            let mut rkllm_input = match build_multimodal_input(&prompt, &images_base64, vision_encoder.as_ref()) {
+            let _span = span.enter();
+            let ctx_ptr = ctx_ptr_usize as *mut ::std::os::raw::c_void;
+
+            // Build multimodal input using the vision encoder. The guard owns
+            // the buffers the input points into, so it must outlive rkllm_run.
+            let built = build_multimodal_input(
+                &prompt,
+                &images_base64,
+                &videos,
+                vision_encoder.as_ref(),
+                &profile,
+            );
+            let mut input_guard = match built {
                 Ok(input) => input,
                 Err(e) => {
-                    eprintln!("Failed to build multimodal input: {}", e);
-                    // Clean up sender on error
+                    tracing::error!(error = %e, "failed to build multimodal input");
+                    // Clean up context on error
                     unsafe {
-                        let _sender = Box::from_raw(
-                            sender_ptr as *mut tokio::sync::mpsc::UnboundedSender<String>,
-                        );
+                        drop(InferenceContext::from_userdata(ctx_ptr));
                     }
                     return;
                 }
             };
+            input_guard.set_enable_thinking(enable_thinking);
 
             let mut rkllm_infer_params = RKLLMInferParam {
                 mode: RKLLMInferMode_RKLLM_INFER_GENERATE,
@@ -205,23 +394,22 @@ impl RkllmModel {
                 max_new_tokens: -1,
             };
 
-            unsafe {
+            activity.run(|| unsafe {
                 let result = rkllm_run(
-                    handle,
-                    &mut rkllm_input,
+                    handle.as_llm_handle(),
+                    input_guard.input_mut(),
                     &mut rkllm_infer_params,
-                    sender_ptr,
+                    ctx_ptr,
                 );
 
                 if result != 0 {
-                    let _sender = Box::from_raw(
-                        sender_ptr as *mut tokio::sync::mpsc::UnboundedSender<String>,
-                    );
+                    drop(InferenceContext::from_userdata(ctx_ptr));
                 }
-            }
+            });
+            drop(input_guard);
         });
 
-        rx
+        Ok(rx)
     }
 }
 
@@ -233,6 +421,23 @@ struct ModelEntry {
     // Note: AbortHandle implements Debug; RkllmModel implements Debug.
     model: Arc<RkllmModel>,
     eviction_handle: tokio::task::AbortHandle,
+    keep_alive: Duration,
+}
+
+impl ModelEntry {
+    fn model_info(&self, key: &str) -> ModelInfo {
+        ModelInfo {
+            key: key.to_string(),
+            model_path: self.model.model_path.clone(),
+            quantization: "W4A16".to_string(),
+            size_bytes: 1024 * 1024 * 500,
+            loaded_at: self.model.loaded_at(),
+            last_used: self.model.last_used(),
+            keep_alive: self.keep_alive,
+            in_flight: self.model.activity().in_flight(),
+            queue_depth: self.model.activity().queue_depth(),
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -243,23 +448,86 @@ struct ModelEntry {
 pub struct RkllmRuntime {
     running_models: Arc<Mutex<HashMap<String, ModelEntry>>>,
     models_path: Arc<PathBuf>,
+    store: ModelStore,
+    metrics: Metrics,
+    tokenizers: TokenizerCache,
+    vision: Arc<Vec<VisionModelConfig>>,
 }
 
 impl RkllmRuntime {
     pub fn new(models_path: PathBuf) -> Self {
         RkllmRuntime {
             running_models: Arc::new(Mutex::new(HashMap::new())),
+            store: ModelStore::new(models_path.clone()),
             models_path: Arc::new(models_path),
+            metrics: Metrics::new(),
+            tokenizers: TokenizerCache::default(),
+            vision: Arc::new(Vec::new()),
         }
     }
 
-    pub fn list_running_models(&self) -> Vec<String> {
+    /// Sets the vision encoders of the multimodal models.
+    pub fn with_vision(mut self, vision: Vec<VisionModelConfig>) -> Self {
+        self.vision = Arc::new(vision);
+        self
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Tokenizers of the models, by model path
+    pub fn tokenizers(&self) -> &TokenizerCache {
+        &self.tokenizers
+    }
+
+    pub fn store(&self) -> &ModelStore {
+        &self.store
+    }
+
+    /// Names of the loaded models; a model loaded with different options
+    /// is listed once.
+    pub fn list_running_models(&self) -> Vec<ModelName> {
         let models = self.running_models.lock().unwrap();
-        // Return only the base model name (first component of the composite key)
-        models
-            .keys()
-            .filter_map(|k| k.splitn(2, '-').next().map(str::to_string))
-            .collect()
+        let mut names: Vec<ModelName> = models.values().map(|entry| entry.model.name.clone()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Unloads every loaded instance of `name`, whatever options it was
+    /// loaded with; returns how many there were. An inference still running
+    /// on an instance keeps its native handle until it ends.
+    pub fn unload(&self, name: &ModelName) -> usize {
+        self.unload_instances(name).len()
+    }
+
+    /// Like [`unload`](Self::unload), but also waits until the inferences
+    /// running on the instances have ended and their handles are destroyed,
+    /// so the model files are no longer in use.
+    pub async fn unload_and_wait(&self, name: &ModelName) -> usize {
+        let handles = self.unload_instances(name);
+        while handles.iter().any(|handle| handle.strong_count() > 0) {
+            tokio::time::sleep(UNLOAD_POLL).await;
+        }
+        handles.len()
+    }
+
+    /// Finds `model` in the store, importing loose files first when it is
+    /// not there yet.
+    pub async fn resolve(&self, model: &str) -> Result<ResolvedModel, RuntimeError> {
+        let store = self.store.clone();
+        let model = model.to_string();
+        tokio::task::spawn_blocking(move || match store.resolve(&model) {
+            Err(StoreError::NotFound(_)) => {
+                store.import_loose(IMPORT_SETTLE)?;
+                store.resolve(&model)
+            }
+            result => result,
+        })
+        .await
+        .map_err(|e| RuntimeError::Internal(format!("spawn_blocking panicked: {}", e)))?
+        .map_err(RuntimeError::from)
     }
 
     /// Returns (or lazily initialises) the model for the given request, then
@@ -267,8 +535,13 @@ impl RkllmRuntime {
     pub async fn get_request_model(
         &self,
         request: &CompletionRequest,
-    ) -> Result<Arc<RkllmModel>, String> {
-        let key = Self::parse_request_model_key(request);
+    ) -> Result<Arc<RkllmModel>, RuntimeError> {
+        let requested = match request {
+            CompletionRequest::Generate(r) => &r.model,
+            CompletionRequest::Chat(r) => &r.model,
+        };
+        let resolved = self.resolve(requested).await?;
+        let key = Self::model_key(&resolved.name, request);
         let keep_alive = request.keep_alive();
 
         // Fast-path: already loaded.
@@ -280,19 +553,33 @@ impl RkllmRuntime {
                 let eviction_handle =
                     self.spawn_eviction_task(key.clone(), keep_alive);
                 entry.eviction_handle = eviction_handle;
+                entry.model.touch();
+                entry.keep_alive = keep_alive;
                 return Ok(entry.model.clone());
             }
         }
 
         // Cold-path: initialise in a blocking thread.
-        let handle = self.init_model_async(request).await?;
-        let model_path = self.get_model_path(
-            match request {
-                CompletionRequest::Generate(r) => &r.model,
-                CompletionRequest::Chat(r) => &r.model,
-            }
-        );
-        let model = Arc::new(RkllmModel::new(ThreadSafeLLMHandle::new(handle), model_path));
+        let load_started = std::time::Instant::now();
+        let span = tracing::info_span!("model_load", key = %key);
+        let handle = self
+            .init_model_async(&resolved.model_path, request)
+            .instrument(span.clone())
+            .await;
+        let elapsed = load_started.elapsed();
+        self.metrics.record_load(elapsed, handle.is_ok());
+        span.in_scope(|| match &handle {
+            Ok(_) => tracing::info!(elapsed_ms = elapsed.as_millis() as u64, "model loaded"),
+            Err(e) => tracing::error!(error = %e, "model load failed"),
+        });
+        let handle = handle.map_err(RuntimeError::LoadFailed)?;
+        let vision = self.vision_config(requested, &resolved);
+        let model = Arc::new(RkllmModel::new(
+            ThreadSafeLLMHandle::new(handle),
+            &resolved,
+            vision,
+            self.metrics.clone(),
+        ));
 
         let eviction_handle = self.spawn_eviction_task(key.clone(), keep_alive);
 
@@ -302,6 +589,7 @@ impl RkllmRuntime {
             models.entry(key).or_insert(ModelEntry {
                 model: model.clone(),
                 eviction_handle,
+                keep_alive,
             });
         }
 
@@ -312,19 +600,51 @@ impl RkllmRuntime {
     // Private helpers
     // -----------------------------------------------------------------------
 
+    /// Takes the instances of `name` out of the map, then drops them with
+    /// the lock released (destroying a handle can take a while).
+    fn unload_instances(&self, name: &ModelName) -> Vec<Weak<ThreadSafeLLMHandle>> {
+        let entries: Vec<(String, ModelEntry)> = {
+            let mut models = self.running_models.lock().unwrap();
+            let keys: Vec<String> = models
+                .iter()
+                .filter(|(_, entry)| &entry.model.name == name)
+                .map(|(key, _)| key.clone())
+                .collect();
+            keys.into_iter()
+                .filter_map(|key| models.remove(&key).map(|entry| (key, entry)))
+                .collect()
+        };
+        entries
+            .into_iter()
+            .map(|(key, entry)| {
+                entry.eviction_handle.abort();
+                let handle = Arc::downgrade(&entry.model.handle);
+                let started = std::time::Instant::now();
+                drop(entry);
+                self.metrics.record_unload(started.elapsed(), "explicit");
+                tracing::info!(%key, "model unloaded");
+                handle
+            })
+            .collect()
+    }
+
     fn spawn_eviction_task(
         &self,
         key: String,
         duration: Duration,
     ) -> tokio::task::AbortHandle {
         let map = self.running_models.clone();
+        let metrics = self.metrics.clone();
         let join = tokio::spawn(async move {
             tokio::time::sleep(duration).await;
-            let mut models = map.lock().unwrap();
-            if let Some(entry) = models.remove(&key) {
-                // Arc<RkllmModel> is dropped here → Drop calls rkllm_destroy
-                // (unless another caller still holds a clone).
+            let entry = map.lock().unwrap().remove(&key);
+            if let Some(entry) = entry {
+                // The handle is destroyed here, outside the lock, unless an
+                // inference still holds it.
+                let started = std::time::Instant::now();
                 drop(entry);
+                metrics.record_unload(started.elapsed(), "keep_alive");
+                tracing::info!(%key, "model evicted after keep-alive expired");
             }
         });
         join.abort_handle()
@@ -334,14 +654,15 @@ impl RkllmRuntime {
     /// stalled by the (potentially long) model-loading operation.
     async fn init_model_async(
         &self,
+        model_path: &Path,
         request: &CompletionRequest,
     ) -> Result<LLMHandle, String> {
-        let (model_name, options) = match request {
-            CompletionRequest::Generate(r) => (&r.model, &r.options),
-            CompletionRequest::Chat(r) => (&r.model, &r.options),
+        let options = match request {
+            CompletionRequest::Generate(r) => &r.options,
+            CompletionRequest::Chat(r) => &r.options,
         };
 
-        let model_path = self.get_model_path(model_name);
+        let model_path = model_path.to_string_lossy().into_owned();
         let top_k = options.top_k;
         let top_p = options.top_p;
         let temperature = options.temperature;
@@ -350,8 +671,8 @@ impl RkllmRuntime {
         let max_context_len = options.num_ctx;
 
         let result = tokio::task::spawn_blocking(move || {
-            let model_path_cstr =
-                CString::new(model_path).expect("CString::new failed");
+            let model_path_cstr = CString::new(model_path)
+                .map_err(|_| "Model path contains a NUL byte".to_string())?;
 
             let mut param = unsafe { rkllm_createDefaultParam() };
             param.model_path = model_path_cstr.as_ptr();
@@ -417,69 +738,46 @@ impl RkllmRuntime {
         Ok(result.0)
     }
 
-    /// Finds the first `.rkllm` file inside a directory.
-    fn find_first_rkllm_file(dir: &Path) -> Option<PathBuf> {
-        if let Ok(entries) = std::fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().and_then(|e| e.to_str()) == Some("rkllm") {
-                    return Some(path);
-                }
-            }
-        }
-        None
-    }
-
-    fn get_model_path(&self, model: &str) -> String {
-        // If `model` already looks like an absolute path, use it directly.
-        let p = Path::new(model);
-        if p.is_absolute() {
-            return model.to_string();
-        }
-
-        let base = self.models_path.as_ref();
-
-        // 1. Exact match as a file (models_path / model).
-        let direct = base.join(model);
-        if direct.is_file() {
-            return direct.to_string_lossy().into_owned();
-        }
-
-        // 2. Exact match as a subdirectory that contains an .rkllm file.
-        if direct.is_dir() {
-            if let Some(entry) = Self::find_first_rkllm_file(&direct) {
-                return entry.to_string_lossy().into_owned();
-            }
-        }
-
-        // 3. Recursive search through all subdirectories for an .rkllm file
-        //    whose filename (stem) contains the model name (case-insensitive).
-        if let Ok(entries) = std::fs::read_dir(base) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    if let Some(rkllm) = Self::find_first_rkllm_file(&path) {
-                        let file_stem = rkllm
-                            .file_stem()
-                            .and_then(|s| s.to_str())
-                            .unwrap_or("")
-                            .to_lowercase();
-                        if file_stem.contains(&model.to_lowercase()) {
-                            return rkllm.to_string_lossy().into_owned();
-                        }
-                    }
-                }
-            }
-        }
-
-        // 4. Fallback: return the direct join (will likely fail at rkllm_init).
-        direct.to_string_lossy().into_owned()
+    /// The vision entry for a model, matched by the requested name, the
+    /// store name or the file name the model was imported from, with its
+    /// encoder path resolved against `models_path`. Without a configured
+    /// path, an ONNX encoder stored with the model is used.
+    fn vision_config(&self, requested: &str, model: &ResolvedModel) -> Option<VisionModelConfig> {
+        let names = [
+            requested.to_string(),
+            model.name.to_string(),
+            model.name.name.clone(),
+            model.manifest.model.file.clone(),
+        ];
+        let stored_encoder = model
+            .vision_encoder_path
+            .clone()
+            .filter(|_| model.manifest.vision_encoder.as_ref().is_some_and(|l| l.file.ends_with(".onnx")));
+        let mut config = match self.vision.iter().find(|v| names.contains(&v.model)) {
+            Some(config) => config.clone(),
+            None => VisionModelConfig {
+                model: model.name.to_string(),
+                encoder: Default::default(),
+                path: Some(stored_encoder.clone()?),
+                profile: Default::default(),
+                input_size: None,
+                embed_dim: None,
+                num_image_tokens: None,
+            },
+        };
+        config.path = config
+            .path
+            .map(|path| self.models_path.join(path))
+            .or(stored_encoder);
+        Some(config)
     }
 
-    fn parse_request_model_key(request: &CompletionRequest) -> String {
-        let (model, options) = match request {
-            CompletionRequest::Generate(req) => (&req.model, &req.options),
-            CompletionRequest::Chat(req) => (&req.model, &req.options),
+    /// Loaded models are keyed by store name and the options they were
+    /// loaded with.
+    fn model_key(model: &ModelName, request: &CompletionRequest) -> String {
+        let options = match request {
+            CompletionRequest::Generate(req) => &req.options,
+            CompletionRequest::Chat(req) => &req.options,
         };
         format!(
             "{}-{}-{}-{}-{}-{}-{}-{}-{}-{}-{}",
@@ -503,12 +801,11 @@ impl RkllmRuntime {
         state: LLMCallState,
     ) -> i32 {
         if userdata.is_null() {
-            eprintln!("Error: userdata is null in callback");
+            tracing::error!("userdata is null in callback");
             return 0;
         }
 
-        let sender_ptr =
-            userdata as *mut tokio::sync::mpsc::UnboundedSender<String>;
+        let ctx_ptr = userdata as *mut InferenceContext;
 
         let (response, should_end) = match state {
             LLMCallState_RKLLM_RUN_FINISH => (String::new(), true),
@@ -537,20 +834,23 @@ impl RkllmRuntime {
 
         if !response.is_empty() {
             unsafe {
-                let sender = &*sender_ptr;
+                let sender = &(*ctx_ptr).sender;
                 if let Err(e) = sender.send(response) {
-                    eprintln!("Failed to send token: {}", e);
+                    tracing::debug!(error = %e, "receiver dropped, discarding token");
                 }
             }
         }
 
         if should_end {
             unsafe {
-                // Drop the sender → closes the channel → receiver sees EOF.
-                let _sender = Box::from_raw(
-                    sender_ptr
-                        as *mut tokio::sync::mpsc::UnboundedSender<String>,
-                );
+                // The final result carries the run's performance stats.
+                if state == LLMCallState_RKLLM_RUN_FINISH && !result.is_null() {
+                    let prefill_tokens = (*result).perf.prefill_tokens;
+                    (*ctx_ptr).metrics.prompt_tokens.inc_by(prefill_tokens.max(0) as u64);
+                }
+                // Drop the context → drops the sender → closes the channel →
+                // receiver sees EOF.
+                drop(InferenceContext::from_userdata(userdata));
             }
             return 1;
         }
@@ -567,7 +867,7 @@ unsafe impl Send for ModelEntry {}
 // ModelRuntime trait implementation for RkllmRuntime
 // ============================================================================
 
-use crate::server::runtime_trait::{ModelHandle, ModelInfo, ModelRuntime};
+use crate::server::runtime_trait::{ModelHandle, ModelInfo, ModelRuntime, RuntimeError, Tokenize};
 use async_trait::async_trait;
 
 /// Wrapper around RkllmModel that implements ModelHandle
@@ -595,12 +895,12 @@ impl ModelHandle for RkllmModelHandle {
         self.inner.run_inference(messages)
     }
 
-    fn run_multimodal_inference(
+    async fn run_multimodal_inference(
         &self,
         prompt: String,
         images: Vec<String>,
-    ) -> tokio::sync::mpsc::UnboundedReceiver<String> {
-        self.inner.run_multimodal_inference(prompt, images)
+    ) -> Result<tokio::sync::mpsc::UnboundedReceiver<String>, RuntimeError> {
+        self.inner.run_multimodal_inference(prompt, images, Vec::new()).await
     }
 
     fn keep_alive(&self) -> Duration {
@@ -613,7 +913,11 @@ impl ModelHandle for RkllmModelHandle {
             model_path: self.model_path.clone(),
             quantization: "W4A16".to_string(), // Default quantization
             size_bytes: 1024 * 1024 * 500,    // Estimated size
-            loaded_at: std::time::SystemTime::now(),
+            loaded_at: self.inner.loaded_at(),
+            last_used: self.inner.last_used(),
+            keep_alive: self.keep_alive,
+            in_flight: self.inner.activity().in_flight(),
+            queue_depth: self.inner.activity().queue_depth(),
         }
     }
 }
@@ -622,15 +926,10 @@ impl ModelHandle for RkllmModelHandle {
 impl ModelRuntime for RkllmRuntime {
     async fn get_or_load_model(&self, request: &CompletionRequest) -> crate::error::Result<Arc<dyn ModelHandle>> {
         // Use the existing get_request_model method
-        let model = self.get_request_model(request).await.map_err(|e| crate::error::Error::Server(e))?;
-        let key = Self::parse_request_model_key(request);
+        let model = self.get_request_model(request).await?;
+        let key = Self::model_key(model.name(), request);
         let keep_alive = request.keep_alive();
-        let model_path = self.get_model_path(
-            match request {
-                CompletionRequest::Generate(r) => &r.model,
-                CompletionRequest::Chat(r) => &r.model,
-            }
-        );
+        let model_path = model.model_path().to_string_lossy().into_owned();
         Ok(Arc::new(RkllmModelHandle::new(model, keep_alive, key, model_path)))
     }
 
@@ -638,25 +937,31 @@ impl ModelRuntime for RkllmRuntime {
         let models = self.running_models.lock().unwrap();
         models
             .iter()
-            .map(|(key, entry)| ModelInfo {
-                key: key.clone(),
-                model_path: entry.model.model_path.clone(),
-                quantization: "W4A16".to_string(),
-                size_bytes: 1024 * 1024 * 500,
-                loaded_at: std::time::SystemTime::now(),
-            })
+            .map(|(key, entry)| entry.model_info(key))
             .collect()
     }
 
     async fn unload_model(&self, model_key: &str) -> crate::error::Result<()> {
-        let mut models = self.running_models.lock().unwrap();
-        if models.remove(model_key).is_some() {
+        let entry = self.running_models.lock().unwrap().remove(model_key);
+        if let Some(entry) = entry {
+            entry.eviction_handle.abort();
+            let started = std::time::Instant::now();
+            drop(entry);
+            self.metrics.record_unload(started.elapsed(), "explicit");
             Ok(())
         } else {
             Err(crate::error::Error::Server(format!("Model not found: {}", model_key)))
         }
     }
 
+    async fn tokenizer(&self, model: &str) -> Result<Arc<dyn Tokenize>, RuntimeError> {
+        let model = self.resolve(model).await?;
+        Ok(self
+            .tokenizers
+            .get(&model.model_path, model.tokenizer_path.as_deref())
+            .await)
+    }
+
     fn models_path(&self) -> &std::path::Path {
         &self.models_path
     }
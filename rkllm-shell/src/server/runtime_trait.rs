@@ -10,12 +10,15 @@ use std::time::Duration;
 
 use crate::error::Result;
 use crate::server::rkllm_runtime::CompletionRequest;
+use crate::server::store::StoreError;
 
 /// Error type for runtime operations
 #[derive(thiserror::Error, Debug)]
 pub enum RuntimeError {
     #[error("Model not found: {0}")]
     ModelNotFound(String),
+    #[error("{0}")]
+    AmbiguousModel(String),
     #[error("Failed to load model: {0}")]
     LoadFailed(String),
     #[error("Inference error: {0}")]
@@ -24,6 +27,10 @@ pub enum RuntimeError {
     AlreadyLoaded(String),
     #[error("Internal error: {0}")]
     Internal(String),
+    #[error("Tokenizer unavailable: {0}")]
+    TokenizerUnavailable(String),
+    #[error("Vision unavailable: {0}")]
+    VisionUnavailable(String),
 }
 
 impl From<RuntimeError> for crate::error::Error {
@@ -32,6 +39,18 @@ impl From<RuntimeError> for crate::error::Error {
     }
 }
 
+impl From<StoreError> for RuntimeError {
+    fn from(e: StoreError) -> Self {
+        match e {
+            StoreError::NotFound(model) => RuntimeError::ModelNotFound(model),
+            e @ (StoreError::Ambiguous { .. } | StoreError::InvalidName(_)) => {
+                RuntimeError::AmbiguousModel(e.to_string())
+            }
+            e => RuntimeError::Internal(e.to_string()),
+        }
+    }
+}
+
 /// Information about a loaded model
 #[derive(Debug, Clone)]
 pub struct ModelInfo {
@@ -40,6 +59,36 @@ pub struct ModelInfo {
     pub quantization: String,
     pub size_bytes: u64,
     pub loaded_at: std::time::SystemTime,
+    /// Last time a request picked this model (resets the keep-alive timer)
+    pub last_used: std::time::SystemTime,
+    pub keep_alive: Duration,
+    /// Inferences currently running on the model
+    pub in_flight: usize,
+    /// Inferences waiting for the running one to finish
+    pub queue_depth: usize,
+}
+
+impl ModelInfo {
+    /// Time left before the model is evicted, as of now
+    pub fn keep_alive_remaining(&self) -> Duration {
+        let idle = self.last_used.elapsed().unwrap_or_default();
+        self.keep_alive.saturating_sub(idle)
+    }
+}
+
+/// A model's tokenizer
+pub trait Tokenize: Send + Sync {
+    /// Token ids for `text`
+    fn encode(&self, text: &str) -> std::result::Result<Vec<u32>, RuntimeError>;
+
+    /// Text for the token ids `ids`
+    fn decode(&self, ids: &[u32]) -> std::result::Result<String, RuntimeError>;
+
+    /// Number of tokens in `text`, estimated when `is_exact` is false
+    fn count(&self, text: &str) -> usize;
+
+    /// False when only estimated counts (and no ids) are available
+    fn is_exact(&self) -> bool;
 }
 
 /// Trait for model handles that can run inference
@@ -48,12 +97,13 @@ pub trait ModelHandle: Send + Sync {
     /// Run inference with text-only messages
     fn run_inference(&self, messages: Vec<String>) -> tokio::sync::mpsc::UnboundedReceiver<String>;
 
-    /// Run inference with multimodal input (prompt + images)
-    fn run_multimodal_inference(
+    /// Run inference with multimodal input (prompt + images); fails before
+    /// streaming when the encoder or images cannot be prepared
+    async fn run_multimodal_inference(
         &self,
         prompt: String,
         images: Vec<String>,
-    ) -> tokio::sync::mpsc::UnboundedReceiver<String>;
+    ) -> std::result::Result<tokio::sync::mpsc::UnboundedReceiver<String>, RuntimeError>;
 
     /// Get the keep-alive duration for this model
     fn keep_alive(&self) -> Duration;
@@ -74,6 +124,9 @@ pub trait ModelRuntime: Send + Sync + 'static {
     /// Unload a specific model by key
     async fn unload_model(&self, model_key: &str) -> Result<()>;
 
+    /// Get the tokenizer of a model, without loading the model itself
+    async fn tokenizer(&self, model: &str) -> std::result::Result<Arc<dyn Tokenize>, RuntimeError>;
+
     /// Get the models directory path
     fn models_path(&self) -> &Path;
 
@@ -0,0 +1,713 @@
+//! Content-addressed local model store
+//!
+//! Files are kept once, in `models_path/blobs/sha256-<hex>`. A manifest in
+//! `models_path/manifests/<name>/<tag>` maps a model name such as
+//! `qwen2.5-1.5b:w8a8` to its `.rkllm` blob, its tokenizer, vision encoder
+//! and LoRA adapters, and the template, system prompt, parameters and
+//! messages its requests default to. `.rkllm` files dropped anywhere else
+//! under `models_path` are imported by the next scan, together with the
+//! companion files next to them. `/api/create` writes manifests for models
+//! derived from others with a Modelfile.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::server::api_models::Role;
+use crate::server::apis::models::sha256_file;
+use crate::server::hub;
+use crate::server::pull_spec;
+
+pub const DEFAULT_TAG: &str = "latest";
+const BLOBS: &str = "blobs";
+const MANIFESTS: &str = "manifests";
+/// Where pulls download to before their files are imported
+pub const PULLS: &str = ".pulls";
+
+/// Loose files modified more recently than this may still be being copied
+/// in; a later scan imports them.
+pub const IMPORT_SETTLE: Duration = Duration::from_secs(30);
+
+#[derive(thiserror::Error, Debug)]
+pub enum StoreError {
+    #[error("Model '{0}' not found")]
+    NotFound(String),
+    #[error("Model name '{}' is ambiguous, it matches {}", .query, .candidates.join(", "))]
+    Ambiguous { query: String, candidates: Vec<String> },
+    #[error("Invalid model name '{0}', expected name[:tag] made of letters, digits, '.', '_', '-' and '/'")]
+    InvalidName(String),
+    #[error("Invalid manifest {}: {}", .0.display(), .1)]
+    Manifest(PathBuf, String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// A model's `name:tag`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ModelName {
+    pub name: String,
+    pub tag: String,
+}
+
+impl ModelName {
+    /// Parses `name[:tag]`, lowercased, with the tag defaulting to `latest`.
+    pub fn parse(text: &str) -> Result<Self, StoreError> {
+        let (name, tag) = split_tag(text.trim());
+        let name = Self {
+            name: name.to_ascii_lowercase(),
+            tag: tag.unwrap_or(DEFAULT_TAG).to_ascii_lowercase(),
+        };
+        if !name.name.split('/').all(valid_segment) || !valid_segment(&name.tag) {
+            return Err(StoreError::InvalidName(text.to_string()));
+        }
+        Ok(name)
+    }
+
+    /// A valid name or tag made from arbitrary text, such as a file name.
+    pub fn sanitize(text: &str) -> String {
+        let mapped: String = text
+            .to_ascii_lowercase()
+            .chars()
+            .map(|c| if valid_char(c) { c } else { '-' })
+            .collect();
+        let trimmed = mapped.trim_matches(|c: char| c == '-' || c == '.');
+        if trimmed.is_empty() {
+            "model".to_string()
+        } else {
+            trimmed.to_string()
+        }
+    }
+}
+
+impl fmt::Display for ModelName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.name, self.tag)
+    }
+}
+
+fn valid_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')
+}
+
+fn valid_segment(segment: &str) -> bool {
+    !segment.is_empty() && segment != "." && segment != ".." && segment.chars().all(valid_char)
+}
+
+/// Splits `name:tag`; a ':' before the last '/' is part of the name.
+fn split_tag(text: &str) -> (&str, Option<&str>) {
+    match text.rsplit_once(':') {
+        Some((name, tag)) if !tag.contains('/') => (name, Some(tag)),
+        _ => (text, None),
+    }
+}
+
+/// A model file's name without its `.rkllm` extension
+fn strip_rkllm(file: &str) -> &str {
+    file.strip_suffix(".rkllm").unwrap_or(file)
+}
+
+/// A file of a model, stored as a blob
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    /// `sha256:<hex>`
+    pub digest: String,
+    pub size: u64,
+    /// The name the file had before it was imported
+    pub file: String,
+}
+
+/// A conversation turn a model's chats start with, from a Modelfile `MESSAGE`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub model: Layer,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<Layer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vision_encoder: Option<Layer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adapters: Vec<Layer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    /// Request option defaults, keyed like `options`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub license: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Manifest {
+    pub fn new(model: Layer) -> Self {
+        Self {
+            model,
+            tokenizer: None,
+            vision_encoder: None,
+            adapters: Vec::new(),
+            template: None,
+            system: None,
+            parameters: BTreeMap::new(),
+            messages: Vec::new(),
+            license: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn layers(&self) -> impl Iterator<Item = &Layer> {
+        std::iter::once(&self.model)
+            .chain(self.tokenizer.iter())
+            .chain(self.vision_encoder.iter())
+            .chain(self.adapters.iter())
+    }
+
+    /// Bytes of all the model's files
+    pub fn size(&self) -> u64 {
+        self.layers().map(|layer| layer.size).sum()
+    }
+}
+
+/// A model found by `ModelStore::resolve`, with its files' paths
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedModel {
+    pub name: ModelName,
+    pub manifest: Manifest,
+    pub model_path: PathBuf,
+    pub tokenizer_path: Option<PathBuf>,
+    pub vision_encoder_path: Option<PathBuf>,
+}
+
+/// What `ModelStore::create` builds a model on
+#[derive(Debug, Clone)]
+pub enum ManifestBase {
+    /// A model file outside the store, or a directory holding one
+    File(PathBuf),
+    /// A model already in the store
+    Manifest(Manifest),
+}
+
+#[derive(Clone, Debug)]
+pub struct ModelStore {
+    root: PathBuf,
+    /// Imports and deletions move blobs around; one runs at a time.
+    lock: Arc<Mutex<()>>,
+}
+
+/// Entries of `dir` sorted by name, or none when it does not exist.
+fn entries(dir: &Path) -> io::Result<Vec<fs::DirEntry>> {
+    let mut entries = match fs::read_dir(dir) {
+        Ok(entries) => entries.collect::<io::Result<Vec<_>>>()?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+    Ok(entries)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+fn is_model_file(path: &Path) -> bool {
+    let name = file_name(path);
+    name.ends_with(".rkllm") && !pull_spec::is_lora(&name)
+}
+
+/// The tokenizer and vision encoder that go with `model` among the `files`
+/// of its directory: `<stem>.tokenizer.json` or `tokenizer.json`, and the
+/// directory's only `.onnx`/`.rknn` file.
+fn companions(model: &Path, files: &[PathBuf]) -> (Option<PathBuf>, Option<PathBuf>) {
+    let dir = model.parent().unwrap_or(Path::new(""));
+    let stem = model.file_stem().unwrap_or_default().to_string_lossy();
+    let tokenizer = [dir.join(format!("{}.tokenizer.json", stem)), dir.join("tokenizer.json")]
+        .into_iter()
+        .find(|path| files.contains(path));
+    let encoders: Vec<&PathBuf> = files
+        .iter()
+        .filter(|f| f.extension().is_some_and(|e| e == "onnx" || e == "rknn"))
+        .collect();
+    let encoder = match encoders.as_slice() {
+        [one] => Some((*one).clone()),
+        _ => None,
+    };
+    (tokenizer, encoder)
+}
+
+impl ModelStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// `sha256:<hex>` is stored as `blobs/sha256-<hex>`.
+    pub fn blob_path(&self, digest: &str) -> PathBuf {
+        self.root.join(BLOBS).join(digest.replacen(':', "-", 1))
+    }
+
+    fn manifest_path(&self, name: &ModelName) -> PathBuf {
+        let mut path = self.root.join(MANIFESTS);
+        path.extend(name.name.split('/'));
+        path.join(&name.tag)
+    }
+
+    pub fn read(&self, name: &ModelName) -> Result<Option<Manifest>, StoreError> {
+        let path = self.manifest_path(name);
+        match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| StoreError::Manifest(path, e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes through a temporary file, so readers never see half a manifest.
+    pub fn write(&self, name: &ModelName, manifest: &Manifest) -> Result<(), StoreError> {
+        let path = self.manifest_path(name);
+        let json = serde_json::to_vec_pretty(manifest)
+            .map_err(|e| StoreError::Manifest(path.clone(), e.to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_file_name(format!(".{}.tmp", name.tag));
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Every model in the store, sorted by name. Unreadable manifests are
+    /// skipped with a warning.
+    pub fn list(&self) -> Result<Vec<(ModelName, Manifest)>, StoreError> {
+        self.manifests(false)
+    }
+
+    fn manifests(&self, strict: bool) -> Result<Vec<(ModelName, Manifest)>, StoreError> {
+        let mut models = Vec::new();
+        self.collect_manifests(&self.root.join(MANIFESTS), &mut Vec::new(), strict, &mut models)?;
+        models.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(models)
+    }
+
+    fn collect_manifests(
+        &self,
+        dir: &Path,
+        prefix: &mut Vec<String>,
+        strict: bool,
+        models: &mut Vec<(ModelName, Manifest)>,
+    ) -> Result<(), StoreError> {
+        for entry in entries(dir)? {
+            let entry_name = entry.file_name().to_string_lossy().into_owned();
+            if entry_name.starts_with('.') {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                prefix.push(entry_name);
+                self.collect_manifests(&entry.path(), prefix, strict, models)?;
+                prefix.pop();
+            } else if !prefix.is_empty() {
+                let name = ModelName {
+                    name: prefix.join("/"),
+                    tag: entry_name,
+                };
+                match self.read(&name) {
+                    Ok(Some(manifest)) => models.push((name, manifest)),
+                    Ok(None) => {}
+                    Err(e) if !strict => tracing::warn!(error = %e, "skipping unreadable manifest"),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the model `query` names: by `name:tag`, by a name alone when it
+    /// has a single tag or a `latest` one, by its model file's name with or
+    /// without `.rkllm`, or else by part of a name that only one model
+    /// contains.
+    pub fn resolve(&self, query: &str) -> Result<ResolvedModel, StoreError> {
+        self.lookup(query, true)
+    }
+
+    /// Like `resolve`, without matching part of a name; for deleting and
+    /// copying, where a guess would be dangerous.
+    pub fn resolve_exact(&self, query: &str) -> Result<ResolvedModel, StoreError> {
+        self.lookup(query, false)
+    }
+
+    fn lookup(&self, query: &str, partial: bool) -> Result<ResolvedModel, StoreError> {
+        let models = self.list()?;
+        let (name, tag) = split_tag(query.trim());
+        let (name, tag) = (name.to_ascii_lowercase(), tag.map(str::to_ascii_lowercase));
+        let ambiguous = |matches: &[&(ModelName, Manifest)]| StoreError::Ambiguous {
+            query: query.to_string(),
+            candidates: matches.iter().map(|(name, _)| name.to_string()).collect(),
+        };
+
+        let exact: Vec<_> = models
+            .iter()
+            .filter(|(candidate, _)| {
+                candidate.name == name && tag.as_ref().map_or(true, |tag| &candidate.tag == tag)
+            })
+            .collect();
+        // Before names, models were known by their file names.
+        let file = strip_rkllm(&query.trim().to_ascii_lowercase()).to_string();
+        let by_file: Vec<_> = models
+            .iter()
+            .filter(|(_, manifest)| strip_rkllm(&manifest.model.file.to_ascii_lowercase()) == file)
+            .collect();
+        let exact = if exact.is_empty() { by_file } else { exact };
+
+        let found = match exact.as_slice() {
+            [one] => *one,
+            [] if partial => {
+                let needle = query.trim().to_ascii_lowercase();
+                let matches: Vec<_> = models
+                    .iter()
+                    .filter(|(candidate, _)| candidate.to_string().contains(&needle))
+                    .collect();
+                match matches.as_slice() {
+                    [one] => *one,
+                    [] => return Err(StoreError::NotFound(query.to_string())),
+                    several => return Err(ambiguous(several)),
+                }
+            }
+            [] => return Err(StoreError::NotFound(query.to_string())),
+            several => match several.iter().find(|(candidate, _)| candidate.tag == DEFAULT_TAG) {
+                Some(latest) => *latest,
+                None => return Err(ambiguous(several)),
+            },
+        };
+        Ok(self.resolved(found.0.clone(), found.1.clone()))
+    }
+
+    fn resolved(&self, name: ModelName, manifest: Manifest) -> ResolvedModel {
+        ResolvedModel {
+            model_path: self.blob_path(&manifest.model.digest),
+            tokenizer_path: manifest.tokenizer.as_ref().map(|l| self.blob_path(&l.digest)),
+            vision_encoder_path: manifest.vision_encoder.as_ref().map(|l| self.blob_path(&l.digest)),
+            name,
+            manifest,
+        }
+    }
+
+    /// Gives the model `source` names a second name, sharing its blobs.
+    pub fn copy(&self, source: &str, destination: &ModelName) -> Result<ModelName, StoreError> {
+        let source = self.resolve_exact(source)?;
+        let mut manifest = source.manifest;
+        manifest.created_at = Utc::now();
+        self.write(destination, &manifest)?;
+        Ok(source.name)
+    }
+
+    /// Deletes a model's manifest and the blobs no other model uses; returns
+    /// the bytes freed.
+    pub fn remove(&self, name: &ModelName) -> Result<u64, StoreError> {
+        let _guard = self.lock.lock().unwrap();
+        let path = self.manifest_path(name);
+        fs::remove_file(&path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => StoreError::NotFound(name.to_string()),
+            _ => e.into(),
+        })?;
+        // Directories of `owner/name` names go once empty.
+        let manifests = self.root.join(MANIFESTS);
+        let mut dir = path.parent();
+        while let Some(d) = dir.filter(|d| *d != manifests) {
+            if fs::remove_dir(d).is_err() {
+                break;
+            }
+            dir = d.parent();
+        }
+        // The model is gone either way; blobs left behind by a failed
+        // collection go with the next removal.
+        match self.collect_garbage() {
+            Ok(freed) => Ok(freed),
+            Err(e) => {
+                tracing::warn!(model = %name, error = %e, "removed model but kept its blobs");
+                Ok(0)
+            }
+        }
+    }
+
+    /// Deletes the blobs no manifest refers to.
+    fn collect_garbage(&self) -> Result<u64, StoreError> {
+        let referenced: HashSet<PathBuf> = self
+            .manifests(true)?
+            .iter()
+            .flat_map(|(_, manifest)| manifest.layers().map(|l| self.blob_path(&l.digest)).collect::<Vec<_>>())
+            .collect();
+        let mut freed = 0;
+        for entry in entries(&self.root.join(BLOBS))? {
+            let path = entry.path();
+            if entry.file_type()?.is_file() && !referenced.contains(&path) {
+                freed += entry.metadata()?.len();
+                fs::remove_file(&path)?;
+                tracing::info!(blob = %path.display(), "removed unused blob");
+            }
+        }
+        Ok(freed)
+    }
+
+    /// Moves `path` into the blobs, or deletes it when the blob is there already.
+    fn ingest(&self, path: &Path) -> Result<Layer, StoreError> {
+        let digest = sha256_file(path)?;
+        let size = fs::metadata(path)?.len();
+        let blob = self.blob_path(&digest);
+        if blob.is_file() {
+            fs::remove_file(path)?;
+        } else {
+            fs::create_dir_all(self.root.join(BLOBS))?;
+            hub::move_file(path, &blob)?;
+        }
+        Ok(Layer {
+            digest,
+            size,
+            file: file_name(path),
+        })
+    }
+
+    /// Puts a copy of the blob `digest` at `dest` without using more space,
+    /// so a pull need not fetch a file the store already has. False when
+    /// there is no such blob.
+    pub fn link_blob(&self, digest: &str, size: u64, dest: &Path) -> io::Result<bool> {
+        let blob = self.blob_path(digest);
+        if fs::metadata(&blob).map(|m| m.len()).ok() != Some(size) {
+            return Ok(false);
+        }
+        match fs::remove_file(dest) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        if fs::hard_link(&blob, dest).is_err() {
+            fs::copy(&blob, dest)?;
+        }
+        Ok(true)
+    }
+
+    /// Copies `path` into the blobs, leaving the original where it is.
+    pub fn add_file(&self, path: &Path) -> Result<Layer, StoreError> {
+        let _guard = self.lock.lock().unwrap();
+        self.copy_in(path)
+    }
+
+    fn copy_in(&self, path: &Path) -> Result<Layer, StoreError> {
+        let digest = sha256_file(path)?;
+        let size = fs::metadata(path)?.len();
+        let blob = self.blob_path(&digest);
+        if !blob.is_file() {
+            fs::create_dir_all(self.root.join(BLOBS))?;
+            let tmp = blob.with_extension("tmp");
+            fs::copy(path, &tmp)?;
+            fs::rename(&tmp, &blob)?;
+        }
+        Ok(Layer {
+            digest,
+            size,
+            file: file_name(path),
+        })
+    }
+
+    /// A manifest for a model file outside the store, or the only model file
+    /// in a directory, copying it in with its tokenizer and vision encoder.
+    pub fn manifest_for_file(&self, path: &Path) -> Result<Manifest, StoreError> {
+        let _guard = self.lock.lock().unwrap();
+        self.copy_in_model(path)
+    }
+
+    fn copy_in_model(&self, path: &Path) -> Result<Manifest, StoreError> {
+        let model = if path.is_dir() {
+            let models: Vec<PathBuf> = entries(path)?
+                .iter()
+                .map(|entry| entry.path())
+                .filter(|f| f.is_file() && is_model_file(f))
+                .collect();
+            match models.as_slice() {
+                [one] => one.clone(),
+                [] => return Err(StoreError::NotFound(path.display().to_string())),
+                several => {
+                    return Err(StoreError::Ambiguous {
+                        query: path.display().to_string(),
+                        candidates: several.iter().map(|f| file_name(f)).collect(),
+                    })
+                }
+            }
+        } else {
+            path.to_path_buf()
+        };
+        let dir = model.parent().unwrap_or(Path::new(""));
+        let files: Vec<PathBuf> = entries(dir)?.iter().map(|entry| entry.path()).collect();
+        let (tokenizer, encoder) = companions(&model, &files);
+
+        let mut manifest = Manifest::new(self.copy_in(&model)?);
+        manifest.tokenizer = tokenizer.map(|path| self.copy_in(&path)).transpose()?;
+        manifest.vision_encoder = encoder.map(|path| self.copy_in(&path)).transpose()?;
+        Ok(manifest)
+    }
+
+    /// Stores as `name` the manifest `edit` makes of `base`. The lock is held
+    /// from copying files in to writing the manifest, so a removal running
+    /// meanwhile cannot collect the new model's blobs.
+    pub fn create(
+        &self,
+        name: &ModelName,
+        base: ManifestBase,
+        edit: impl FnOnce(&mut Manifest),
+    ) -> Result<(), StoreError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut manifest = match base {
+            ManifestBase::File(path) => self.copy_in_model(&path)?,
+            ManifestBase::Manifest(manifest) => {
+                // The base model may have been removed since it was read.
+                if let Some(layer) = manifest.layers().find(|l| !self.blob_path(&l.digest).is_file()) {
+                    return Err(StoreError::NotFound(layer.file.clone()));
+                }
+                manifest
+            }
+        };
+        edit(&mut manifest);
+        self.write(name, &manifest)
+    }
+
+    /// Imports the `.rkllm` files under `models_path` outside the store and
+    /// hidden directories, with their companions. Directories with files
+    /// modified within `settle` are left for a later scan. Returns the names
+    /// of the imported models.
+    pub fn import_loose(&self, settle: Duration) -> Result<Vec<ModelName>, StoreError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut dirs = Vec::new();
+        self.loose_dirs(&self.root, &mut dirs)?;
+        let mut imported = Vec::new();
+        for dir in dirs {
+            imported.extend(self.import_files(&dir, None, settle)?);
+        }
+        Ok(imported)
+    }
+
+    /// Imports the models in `dir` with their companions, naming a lone
+    /// model `name` (replacing a model already called that).
+    pub fn import_dir(&self, dir: &Path, name: Option<ModelName>) -> Result<Vec<ModelName>, StoreError> {
+        let _guard = self.lock.lock().unwrap();
+        self.import_files(dir, name, Duration::ZERO)
+    }
+
+    fn loose_dirs(&self, dir: &Path, dirs: &mut Vec<PathBuf>) -> io::Result<()> {
+        let mut has_model = false;
+        for entry in entries(dir)? {
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if dir == self.root && (name == BLOBS || name == MANIFESTS) {
+                    continue;
+                }
+                self.loose_dirs(&entry.path(), dirs)?;
+            } else if file_type.is_file() && is_model_file(&entry.path()) {
+                has_model = true;
+            }
+        }
+        if has_model {
+            dirs.push(dir.to_path_buf());
+        }
+        Ok(())
+    }
+
+    fn import_files(
+        &self,
+        dir: &Path,
+        name: Option<ModelName>,
+        settle: Duration,
+    ) -> Result<Vec<ModelName>, StoreError> {
+        let mut files = Vec::new();
+        for entry in entries(dir)? {
+            if entry.file_type()?.is_file() {
+                let modified = entry.metadata()?.modified()?;
+                if modified.elapsed().unwrap_or_default() < settle {
+                    tracing::debug!(dir = %dir.display(), "files still changing, importing later");
+                    return Ok(Vec::new());
+                }
+                files.push(entry.path());
+            }
+        }
+        let models: Vec<&PathBuf> = files.iter().filter(|f| is_model_file(f)).collect();
+        let adapters: Vec<&PathBuf> = files
+            .iter()
+            .filter(|f| f.extension().is_some_and(|e| e == "rkllm") && !is_model_file(f))
+            .collect();
+
+        // Companions are ingested once, however many models share them.
+        let mut layers: HashMap<PathBuf, Layer> = HashMap::new();
+        let mut ingest = |path: &Path| -> Result<Layer, StoreError> {
+            if let Some(layer) = layers.get(path) {
+                return Ok(layer.clone());
+            }
+            let layer = self.ingest(path)?;
+            layers.insert(path.to_path_buf(), layer.clone());
+            Ok(layer)
+        };
+
+        let mut imported = Vec::new();
+        for model in &models {
+            let stem = model.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            let (tokenizer, encoder) = companions(model.as_path(), &files);
+
+            let mut manifest = Manifest::new(ingest(model.as_path())?);
+            manifest.tokenizer = tokenizer.map(|path| ingest(path.as_path())).transpose()?;
+            manifest.vision_encoder = encoder.map(|path| ingest(path.as_path())).transpose()?;
+            manifest.adapters = adapters.iter().map(|path| ingest(path.as_path())).collect::<Result<_, _>>()?;
+
+            let model_name = match (&name, models.len()) {
+                (Some(name), 1) => name.clone(),
+                _ => self.import_name(&stem, &manifest.model.digest)?,
+            };
+            self.write(&model_name, &manifest)?;
+            tracing::info!(model = %model_name, file = %model.display(), "imported model");
+            imported.push(model_name);
+        }
+        if dir != self.root {
+            let _ = fs::remove_dir(dir);
+        }
+        Ok(imported)
+    }
+
+    /// A loose file is named after itself, unless a different model already
+    /// has that name; then it is tagged with its digest.
+    fn import_name(&self, stem: &str, digest: &str) -> Result<ModelName, StoreError> {
+        let name = ModelName {
+            name: ModelName::sanitize(stem),
+            tag: DEFAULT_TAG.to_string(),
+        };
+        match self.read(&name)? {
+            Some(existing) if existing.model.digest != digest => Ok(ModelName {
+                tag: digest.trim_start_matches("sha256:").chars().take(12).collect(),
+                ..name
+            }),
+            _ => Ok(name),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "store_test.rs"]
+mod tests;
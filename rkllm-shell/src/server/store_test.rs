@@ -0,0 +1,258 @@
+//! Tests for the local model store
+
+use super::*;
+
+fn store() -> (tempfile::TempDir, ModelStore) {
+    let dir = tempfile::tempdir().unwrap();
+    let store = ModelStore::new(dir.path());
+    (dir, store)
+}
+
+fn drop_file(store: &ModelStore, path: &str, content: &[u8]) {
+    let path = store.root().join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn names(store: &ModelStore) -> Vec<String> {
+    store.list().unwrap().iter().map(|(name, _)| name.to_string()).collect()
+}
+
+#[test]
+fn test_parse_model_name() {
+    let name = ModelName::parse("Qwen2.5-1.5B").unwrap();
+    assert_eq!(name.to_string(), "qwen2.5-1.5b:latest");
+    let name = ModelName::parse("owner/repo:W8A8").unwrap();
+    assert_eq!((name.name.as_str(), name.tag.as_str()), ("owner/repo", "w8a8"));
+    for invalid in ["", "a b", "../x", "owner//repo", "name:", "name:a/b"] {
+        assert!(ModelName::parse(invalid).is_err(), "{}", invalid);
+    }
+    assert_eq!(ModelName::sanitize("Qwen 2 (W8A8)"), "qwen-2--w8a8");
+}
+
+#[test]
+fn test_import_moves_loose_files_into_blobs() {
+    let (_dir, store) = store();
+    drop_file(&store, "qwen/Qwen-W8A8.rkllm", b"model");
+    drop_file(&store, "qwen/tokenizer.json", b"{}");
+    drop_file(&store, "qwen/vision.onnx", b"encoder");
+    drop_file(&store, "qwen/qwen_lora.rkllm", b"adapter");
+    drop_file(&store, "qwen/README.md", b"readme");
+    drop_file(&store, "Other.rkllm", b"other");
+
+    let imported = store.import_loose(Duration::ZERO).unwrap();
+    assert_eq!(imported.len(), 2);
+    assert_eq!(names(&store), vec!["other:latest", "qwen-w8a8:latest"]);
+
+    let model = store.resolve("qwen-w8a8").unwrap();
+    assert_eq!(fs::read(&model.model_path).unwrap(), b"model");
+    assert_eq!(fs::read(model.tokenizer_path.unwrap()).unwrap(), b"{}");
+    assert_eq!(fs::read(model.vision_encoder_path.unwrap()).unwrap(), b"encoder");
+    assert_eq!(model.manifest.model.file, "Qwen-W8A8.rkllm");
+    assert_eq!(model.manifest.adapters.len(), 1);
+    assert_eq!(model.manifest.size(), 5 + 2 + 7 + 7);
+
+    assert!(!store.root().join("qwen/Qwen-W8A8.rkllm").exists());
+    assert!(store.root().join("qwen/README.md").exists());
+    assert!(!store.root().join("Other.rkllm").exists());
+    assert!(store.import_loose(Duration::ZERO).unwrap().is_empty());
+}
+
+#[test]
+fn test_import_waits_for_files_to_settle() {
+    let (_dir, store) = store();
+    drop_file(&store, "copying/model.rkllm", b"model");
+    assert!(store.import_loose(Duration::from_secs(3600)).unwrap().is_empty());
+    assert!(store.root().join("copying/model.rkllm").exists());
+}
+
+#[test]
+fn test_import_keeps_a_taken_name() {
+    let (_dir, store) = store();
+    drop_file(&store, "model.rkllm", b"one");
+    store.import_loose(Duration::ZERO).unwrap();
+    drop_file(&store, "model.rkllm", b"one");
+    drop_file(&store, "again/model.rkllm", b"two");
+    let imported = store.import_loose(Duration::ZERO).unwrap();
+
+    // A different file gets its digest as tag; the same one again keeps its name.
+    assert_eq!(imported[0].name, "model");
+    assert_eq!(imported[0].tag.len(), 12);
+    assert_eq!(imported[1].to_string(), "model:latest");
+    assert_eq!(fs::read_dir(store.root().join(BLOBS)).unwrap().count(), 2);
+}
+
+#[test]
+fn test_resolve_reports_ambiguity() {
+    let (_dir, store) = store();
+    drop_file(&store, "qwen-1.5b.rkllm", b"a");
+    drop_file(&store, "qwen-7b.rkllm", b"b");
+    drop_file(&store, "llama.rkllm", b"c");
+    store.import_loose(Duration::ZERO).unwrap();
+
+    assert_eq!(store.resolve("LLAMA").unwrap().name.to_string(), "llama:latest");
+    assert_eq!(store.resolve("7b").unwrap().name.to_string(), "qwen-7b:latest");
+    assert_eq!(store.resolve("qwen-7b:latest").unwrap().name.name, "qwen-7b");
+    let err = store.resolve("qwen").unwrap_err();
+    assert!(matches!(&err, StoreError::Ambiguous { candidates, .. } if candidates.len() == 2));
+    assert!(err.to_string().contains("qwen-1.5b:latest, qwen-7b:latest"));
+    assert!(matches!(store.resolve("mistral"), Err(StoreError::NotFound(_))));
+    assert!(matches!(store.resolve_exact("7b"), Err(StoreError::NotFound(_))));
+}
+
+#[test]
+fn test_resolve_by_file_name() {
+    let (_dir, store) = store();
+    drop_file(&store, ".pulls/owner--repo/Qwen2.5-3B_W8A8_RK3588.rkllm", b"model");
+    let name = ModelName::parse("owner/repo:w8a8").unwrap();
+    store.import_dir(&store.root().join(".pulls/owner--repo"), Some(name.clone())).unwrap();
+
+    for query in ["Qwen2.5-3B_W8A8_RK3588.rkllm", "qwen2.5-3b_w8a8_rk3588"] {
+        assert_eq!(store.resolve(query).unwrap().name, name);
+        assert_eq!(store.resolve_exact(query).unwrap().name, name);
+    }
+
+    // A copy shares the file; its `latest` tag is preferred.
+    store.copy("owner/repo:w8a8", &ModelName::parse("owner/repo:prod").unwrap()).unwrap();
+    assert!(matches!(
+        store.resolve("Qwen2.5-3B_W8A8_RK3588.rkllm"),
+        Err(StoreError::Ambiguous { .. })
+    ));
+    store.copy("owner/repo:w8a8", &ModelName::parse("owner/repo").unwrap()).unwrap();
+    assert_eq!(store.resolve("Qwen2.5-3B_W8A8_RK3588.rkllm").unwrap().name.tag, "latest");
+}
+
+#[test]
+fn test_name_without_tag() {
+    let (_dir, store) = store();
+    let manifest = |content: &str| Manifest::new(Layer {
+        digest: format!("sha256:{}", content),
+        size: 1,
+        file: "model.rkllm".to_string(),
+    });
+    store.write(&ModelName::parse("qwen:w8a8").unwrap(), &manifest("a")).unwrap();
+    assert_eq!(store.resolve_exact("qwen").unwrap().name.tag, "w8a8");
+
+    store.write(&ModelName::parse("qwen:w4a16").unwrap(), &manifest("b")).unwrap();
+    assert!(matches!(store.resolve_exact("qwen"), Err(StoreError::Ambiguous { .. })));
+    store.write(&ModelName::parse("qwen").unwrap(), &manifest("c")).unwrap();
+    assert_eq!(store.resolve_exact("qwen").unwrap().name.tag, "latest");
+}
+
+#[test]
+fn test_remove_collects_unused_blobs() {
+    let (_dir, store) = store();
+    drop_file(&store, "a/one.rkllm", b"one");
+    drop_file(&store, "a/tokenizer.json", b"{}");
+    drop_file(&store, "b/two.rkllm", b"two");
+    drop_file(&store, "b/tokenizer.json", b"{}");
+    store.import_loose(Duration::ZERO).unwrap();
+    let one = store.resolve("one").unwrap();
+    let two = store.resolve("two").unwrap();
+    assert_eq!(one.tokenizer_path, two.tokenizer_path);
+
+    assert_eq!(store.remove(&one.name).unwrap(), 3);
+    assert!(!one.model_path.exists());
+    assert!(two.tokenizer_path.unwrap().exists());
+    assert_eq!(names(&store), vec!["two:latest"]);
+    assert!(matches!(store.remove(&one.name), Err(StoreError::NotFound(_))));
+}
+
+#[test]
+fn test_remove_survives_unreadable_manifests() {
+    let (_dir, store) = store();
+    drop_file(&store, "one.rkllm", b"one");
+    store.import_loose(Duration::ZERO).unwrap();
+    let one = store.resolve("one").unwrap();
+    drop_file(&store, "manifests/broken/latest", b"not a manifest");
+
+    // Blobs are kept while a manifest that may refer to them is unreadable.
+    assert_eq!(store.remove(&one.name).unwrap(), 0);
+    assert!(one.model_path.exists());
+    assert!(matches!(store.remove(&one.name), Err(StoreError::NotFound(_))));
+
+    fs::remove_file(store.root().join("manifests/broken/latest")).unwrap();
+    drop_file(&store, "two.rkllm", b"two");
+    store.import_loose(Duration::ZERO).unwrap();
+    assert_eq!(store.remove(&ModelName::parse("two").unwrap()).unwrap(), 6);
+    assert!(!one.model_path.exists());
+}
+
+#[test]
+fn test_import_dir_names_a_pull() {
+    let (_dir, store) = store();
+    drop_file(&store, ".pulls/owner--repo/Model_W8A8_RK3588.rkllm", b"model");
+    let name = ModelName::parse("owner/repo:w8a8").unwrap();
+    let imported = store.import_dir(&store.root().join(".pulls/owner--repo"), Some(name.clone())).unwrap();
+    assert_eq!(imported, vec![name]);
+    assert_eq!(store.resolve("owner/repo").unwrap().manifest.model.file, "Model_W8A8_RK3588.rkllm");
+
+    let dest = store.root().join("again.rkllm");
+    let digest = store.resolve("owner/repo").unwrap().manifest.model.digest;
+    assert!(store.link_blob(&digest, 5, &dest).unwrap());
+    assert_eq!(fs::read(&dest).unwrap(), b"model");
+    assert!(!store.link_blob(&digest, 6, &dest).unwrap());
+}
+
+#[test]
+fn test_manifest_for_file_copies_it_in() {
+    let (_dir, store) = store();
+    let outside = tempfile::tempdir().unwrap();
+    fs::write(outside.path().join("Qwen.rkllm"), b"model").unwrap();
+    fs::write(outside.path().join("Qwen.tokenizer.json"), b"{}").unwrap();
+    fs::write(outside.path().join("vision.rknn"), b"encoder").unwrap();
+
+    let manifest = store.manifest_for_file(outside.path()).unwrap();
+    assert_eq!(manifest.model.file, "Qwen.rkllm");
+    assert_eq!(manifest.tokenizer.as_ref().unwrap().file, "Qwen.tokenizer.json");
+    assert_eq!(manifest.vision_encoder.as_ref().unwrap().size, 7);
+    assert_eq!(fs::read(store.blob_path(&manifest.model.digest)).unwrap(), b"model");
+    assert!(outside.path().join("Qwen.rkllm").exists());
+
+    fs::write(outside.path().join("Other.rkllm"), b"other").unwrap();
+    assert!(matches!(store.manifest_for_file(outside.path()), Err(StoreError::Ambiguous { .. })));
+    let adapter = store.add_file(&outside.path().join("Other.rkllm")).unwrap();
+    assert_eq!(adapter.size, 5);
+    assert!(store.blob_path(&adapter.digest).is_file());
+}
+
+#[test]
+fn test_copy_shares_blobs() {
+    let (_dir, store) = store();
+    drop_file(&store, "qwen.rkllm", b"model");
+    store.import_loose(Duration::ZERO).unwrap();
+
+    let prod = ModelName::parse("qwen:prod").unwrap();
+    assert_eq!(store.copy("qwen", &prod).unwrap().to_string(), "qwen:latest");
+    assert_eq!(names(&store), vec!["qwen:latest", "qwen:prod"]);
+    assert_eq!(fs::read_dir(store.root().join(BLOBS)).unwrap().count(), 1);
+    assert!(matches!(store.copy("qw", &prod), Err(StoreError::NotFound(_))));
+
+    // The blob stays while the copy refers to it.
+    assert_eq!(store.remove(&ModelName::parse("qwen").unwrap()).unwrap(), 0);
+    assert_eq!(fs::read(store.resolve("qwen:prod").unwrap().model_path).unwrap(), b"model");
+}
+
+#[test]
+fn test_create_checks_the_base_blobs() {
+    let (_dir, store) = store();
+    drop_file(&store, "qwen.rkllm", b"model");
+    store.import_loose(Duration::ZERO).unwrap();
+    let base = store.resolve("qwen").unwrap().manifest;
+
+    let terse = ModelName::parse("terse").unwrap();
+    store
+        .create(&terse, ManifestBase::Manifest(base.clone()), |m| m.system = Some("Be terse.".to_string()))
+        .unwrap();
+    assert_eq!(store.resolve("terse").unwrap().manifest.system.as_deref(), Some("Be terse."));
+
+    // A base removed after it was read is not written with missing blobs.
+    store.remove(&ModelName::parse("qwen").unwrap()).unwrap();
+    store.remove(&terse).unwrap();
+    let again = ModelName::parse("again").unwrap();
+    assert!(matches!(
+        store.create(&again, ManifestBase::Manifest(base), |_| {}),
+        Err(StoreError::NotFound(_))
+    ));
+    assert!(store.read(&again).unwrap().is_none());
+}
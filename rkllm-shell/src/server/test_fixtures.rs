@@ -17,14 +17,16 @@ pub fn simple_chat_request(model: &str) -> ChatCompletionRequest {
             ChatCompletionRequestMessage {
                 role: Role::System,
                 content: "You are a helpful assistant".into(),
-                thunking: None,
+                thinking: None,
                 images: None,
+                videos: None,
             },
             ChatCompletionRequestMessage {
                 role: Role::User,
                 content: "Hello".into(),
-                thunking: None,
+                thinking: None,
                 images: None,
+                videos: None,
             },
         ],
         stream: false,
@@ -32,6 +34,7 @@ pub fn simple_chat_request(model: &str) -> ChatCompletionRequest {
         top_p: 0.9,
         max_tokens: None,
         keep_alive: Duration::from_secs(300),
+        think: false,
         options: default_model_options(),
     }
 }
@@ -43,14 +46,16 @@ pub fn chat_request_with_image(model: &str, image_base64: &str) -> ChatCompletio
         messages: vec![ChatCompletionRequestMessage {
             role: Role::User,
             content: "Describe this image".into(),
-            thunking: None,
+            thinking: None,
             images: Some(vec![image_base64.into()]),
+            videos: None,
         }],
         stream: false,
         temperature: 0.8,
         top_p: 0.9,
         max_tokens: None,
         keep_alive: Duration::from_secs(300),
+        think: false,
         options: default_model_options(),
     }
 }
@@ -75,6 +80,7 @@ pub fn generate_request(model: &str, prompt: &str) -> GenerateRequest {
         top_p: 0.9,
         max_tokens: None,
         keep_alive: Duration::from_secs(300),
+        think: false,
         options: default_model_options(),
     }
 }
@@ -94,17 +100,20 @@ pub fn openai_chat_request(model: &str) -> OpenAiChatRequest {
             OpenAiMessage {
                 role: "system".into(),
                 content: OpenAiContent::Text("You are a helpful assistant".into()),
+                reasoning_content: None,
             },
             OpenAiMessage {
                 role: "user".into(),
                 content: OpenAiContent::Text("Hello".into()),
+                reasoning_content: None,
             },
         ],
         stream: false,
-        temperature: 0.8,
-        top_p: 0.9,
+        temperature: None,
+        top_p: None,
         max_tokens: None,
         keep_alive: Duration::from_secs(300),
+        reasoning_effort: None,
     }
 }
 
@@ -122,12 +131,14 @@ pub fn openai_chat_request_with_image(model: &str, image_base64: &str) -> OpenAi
         messages: vec![OpenAiMessage {
             role: "user".into(),
             content: OpenAiContent::Text("Describe this image".into()),
+            reasoning_content: None,
         }],
         stream: false,
-        temperature: 0.8,
-        top_p: 0.9,
+        temperature: None,
+        top_p: None,
         max_tokens: None,
         keep_alive: Duration::from_secs(300),
+        reasoning_effort: None,
     }
 }
 
@@ -197,8 +208,9 @@ pub mod scenarios {
         req.messages.push(ChatCompletionRequestMessage {
             role: Role::User,
             content: "x".repeat(10000),
-            thunking: None,
+            thinking: None,
             images: None,
+            videos: None,
         });
         req
     }
@@ -26,6 +26,7 @@ impl ChatRequestBuilder {
                 top_p: 0.9,
                 max_tokens: None,
                 keep_alive: Duration::from_secs(300),
+                think: false,
                 options: default_model_options(),
             },
         }
@@ -35,8 +36,9 @@ impl ChatRequestBuilder {
         self.request.messages.push(ChatCompletionRequestMessage {
             role,
             content: content.into(),
-            thunking: None,
+            thinking: None,
             images: None,
+            videos: None,
         });
         self
     }
@@ -99,6 +101,7 @@ impl GenerateRequestBuilder {
                 top_p: 0.9,
                 max_tokens: None,
                 keep_alive: Duration::from_secs(300),
+                think: false,
                 options: default_model_options(),
             },
         }
@@ -141,10 +144,11 @@ impl OpenAiChatRequestBuilder {
                 model: model.into(),
                 messages: vec![],
                 stream: false,
-                temperature: 0.8,
-                top_p: 0.9,
+                temperature: None,
+                top_p: None,
                 max_tokens: None,
                 keep_alive: Duration::from_secs(300),
+                reasoning_effort: None,
             },
         }
     }
@@ -153,6 +157,7 @@ impl OpenAiChatRequestBuilder {
         self.request.messages.push(OpenAiMessage {
             role: role.into(),
             content: OpenAiContent::Text(content.into()),
+            reasoning_content: None,
         });
         self
     }
@@ -175,7 +180,7 @@ impl OpenAiChatRequestBuilder {
     }
 
     pub fn temperature(mut self, temp: f32) -> Self {
-        self.request.temperature = temp;
+        self.request.temperature = Some(temp);
         self
     }
 
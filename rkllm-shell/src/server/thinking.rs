@@ -0,0 +1,134 @@
+//! Separating reasoning from answers
+//!
+//! Reasoning models (DeepSeek-R1, Qwen3) wrap their chain of thought in
+//! `<think>...</think>`. The splitter routes that text to the `thinking` /
+//! `reasoning_content` fields instead of the answer, also when a tag arrives
+//! split across several tokens.
+
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+/// Accepted `reasoning_effort` values on `/v1/chat/completions`
+pub const REASONING_EFFORTS: &[&str] = &["none", "minimal", "low", "medium", "high"];
+
+/// Whether an OpenAI `reasoning_effort` turns thinking on
+pub fn reasoning_effort_enables_thinking(effort: Option<&str>) -> bool {
+    matches!(effort, Some(effort) if effort != "none")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Thinking(String),
+    Content(String),
+}
+
+#[derive(Debug, Default)]
+pub struct ThinkingSplitter {
+    in_thinking: bool,
+    /// Text that may be the start of a tag, held back until it is decided
+    pending: String,
+    /// Set after a tag, so the newlines models put around tags are dropped
+    trim_start: bool,
+}
+
+impl ThinkingSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, token: &str) -> Vec<Segment> {
+        self.pending.push_str(token);
+        let mut segments = Vec::new();
+        loop {
+            let tag = if self.in_thinking { CLOSE_TAG } else { OPEN_TAG };
+            if let Some(at) = self.pending.find(tag) {
+                let text = self.pending[..at].to_string();
+                self.pending.drain(..at + tag.len());
+                self.emit(text, &mut segments);
+                self.in_thinking = !self.in_thinking;
+                self.trim_start = true;
+                continue;
+            }
+            // Hold back the longest tail that could still grow into the tag.
+            let held = (1..tag.len())
+                .rev()
+                .find(|&n| self.pending.ends_with(&tag[..n]))
+                .unwrap_or(0);
+            let text = self.pending.drain(..self.pending.len() - held).collect();
+            self.emit(text, &mut segments);
+            return segments;
+        }
+    }
+
+    /// Flushes text held back as a possible tag.
+    pub fn finish(&mut self) -> Vec<Segment> {
+        let text = std::mem::take(&mut self.pending);
+        let mut segments = Vec::new();
+        self.emit(text, &mut segments);
+        segments
+    }
+
+    fn emit(&mut self, text: String, segments: &mut Vec<Segment>) {
+        let text = if self.trim_start {
+            let trimmed = text.trim_start();
+            if trimmed.is_empty() {
+                return;
+            }
+            self.trim_start = false;
+            trimmed.to_string()
+        } else {
+            text
+        };
+        if text.is_empty() {
+            return;
+        }
+        segments.push(if self.in_thinking {
+            Segment::Thinking(text)
+        } else {
+            Segment::Content(text)
+        });
+    }
+}
+
+/// Splits a complete response into its thinking and its answer.
+pub fn split(text: &str) -> (String, String) {
+    let mut splitter = ThinkingSplitter::new();
+    let mut thinking = String::new();
+    let mut content = String::new();
+    let mut segments = splitter.feed(text);
+    segments.extend(splitter.finish());
+    for segment in segments {
+        match segment {
+            Segment::Thinking(text) => thinking.push_str(&text),
+            Segment::Content(text) => content.push_str(&text),
+        }
+    }
+    (thinking.trim_end().to_string(), content)
+}
+
+/// Splits a token stream into segments. Thinking is dropped unless `think` is set.
+pub fn split_stream(mut rx: UnboundedReceiver<String>, think: bool) -> UnboundedReceiver<Segment> {
+    let (tx, out) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut splitter = ThinkingSplitter::new();
+        let send = |segments: Vec<Segment>| {
+            segments
+                .into_iter()
+                .filter(|s| think || matches!(s, Segment::Content(_)))
+                .all(|s| tx.send(s).is_ok())
+        };
+        while let Some(token) = rx.recv().await {
+            if !send(splitter.feed(&token)) {
+                return;
+            }
+        }
+        send(splitter.finish());
+    });
+    out
+}
+
+#[cfg(test)]
+#[path = "thinking_test.rs"]
+mod tests;
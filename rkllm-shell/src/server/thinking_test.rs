@@ -0,0 +1,80 @@
+//! Tests for splitting thinking from answers
+
+use super::*;
+
+fn feed_all(tokens: &[&str]) -> Vec<Segment> {
+    let mut splitter = ThinkingSplitter::new();
+    let mut segments: Vec<Segment> = tokens.iter().flat_map(|t| splitter.feed(t)).collect();
+    segments.extend(splitter.finish());
+    segments
+}
+
+#[test]
+fn test_split_complete_response() {
+    let (thinking, content) = split("<think>\nThe user greets me.\n</think>\n\nHello!");
+    assert_eq!(thinking, "The user greets me.");
+    assert_eq!(content, "Hello!");
+}
+
+#[test]
+fn test_plain_response_is_all_content() {
+    assert_eq!(split("Just an answer."), (String::new(), "Just an answer.".to_string()));
+}
+
+#[test]
+fn test_empty_think_block_is_dropped() {
+    // Qwen3 with thinking disabled still emits an empty block.
+    assert_eq!(feed_all(&["<think>\n\n</think>\n\n", "Hi"]), vec![Segment::Content("Hi".into())]);
+}
+
+#[test]
+fn test_tags_split_across_tokens() {
+    let segments = feed_all(&["<th", "ink>", "hmm", "</thi", "nk>", "ok <", "b>"]);
+    assert_eq!(
+        segments,
+        vec![
+            Segment::Thinking("hmm".into()),
+            Segment::Content("ok ".into()),
+            Segment::Content("<b>".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_non_ascii_before_tag() {
+    let segments = feed_all(&["<think>é</think>ü"]);
+    assert_eq!(
+        segments,
+        vec![Segment::Thinking("é".into()), Segment::Content("ü".into())]
+    );
+}
+
+#[test]
+fn test_reasoning_effort() {
+    assert!(!reasoning_effort_enables_thinking(None));
+    assert!(!reasoning_effort_enables_thinking(Some("none")));
+    assert!(reasoning_effort_enables_thinking(Some("low")));
+}
+
+#[tokio::test]
+async fn test_split_stream_drops_thinking_unless_requested() {
+    let collect = |think: bool| async move {
+        let (tx, rx) = mpsc::unbounded_channel();
+        for token in ["<think>", "why", "</think>", "because"] {
+            tx.send(token.to_string()).unwrap();
+        }
+        drop(tx);
+        let mut out = split_stream(rx, think);
+        let mut segments = Vec::new();
+        while let Some(segment) = out.recv().await {
+            segments.push(segment);
+        }
+        segments
+    };
+
+    assert_eq!(collect(false).await, vec![Segment::Content("because".into())]);
+    assert_eq!(
+        collect(true).await,
+        vec![Segment::Thinking("why".into()), Segment::Content("because".into())]
+    );
+}
@@ -0,0 +1,120 @@
+//! Prompt token counting
+//!
+//! The RKLLM runtime does not expose its tokenizer, so counts come from a
+//! Hugging Face `tokenizer.json` stored with the model, or shipped next to
+//! the `.rkllm` file as `<model>.tokenizer.json` or `tokenizer.json`.
+//! Without one, counts are estimated from the text length and reported as such.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::server::runtime_trait::{RuntimeError, Tokenize};
+
+/// Characters per token assumed when no tokenizer file is available. Kept
+/// low on purpose: overestimating is safer than overflowing the context.
+const ESTIMATED_CHARS_PER_TOKEN: usize = 3;
+
+pub enum ModelTokenizer {
+    HuggingFace(tokenizers::Tokenizer),
+    Estimate,
+}
+
+impl ModelTokenizer {
+    /// Candidate tokenizer files for the model at `model_path`, most specific
+    /// first, starting with the one stored with it.
+    pub fn candidates(model_path: &Path, stored: Option<&Path>) -> Vec<PathBuf> {
+        let mut candidates: Vec<PathBuf> = stored.map(Path::to_path_buf).into_iter().collect();
+        if let Some(stem) = model_path.file_stem().and_then(|s| s.to_str()) {
+            candidates.push(model_path.with_file_name(format!("{}.tokenizer.json", stem)));
+        }
+        if let Some(dir) = model_path.parent() {
+            candidates.push(dir.join("tokenizer.json"));
+        }
+        candidates
+    }
+
+    pub fn load(model_path: &Path, stored: Option<&Path>) -> Self {
+        for candidate in Self::candidates(model_path, stored) {
+            if !candidate.is_file() {
+                continue;
+            }
+            match tokenizers::Tokenizer::from_file(&candidate) {
+                Ok(tokenizer) => {
+                    tracing::debug!(path = %candidate.display(), "loaded tokenizer");
+                    return ModelTokenizer::HuggingFace(tokenizer);
+                }
+                Err(e) => {
+                    tracing::warn!(path = %candidate.display(), error = %e, "failed to load tokenizer");
+                }
+            }
+        }
+        ModelTokenizer::Estimate
+    }
+
+    fn exact(&self) -> Result<&tokenizers::Tokenizer, RuntimeError> {
+        match self {
+            ModelTokenizer::HuggingFace(tokenizer) => Ok(tokenizer),
+            ModelTokenizer::Estimate => Err(RuntimeError::TokenizerUnavailable(
+                "no tokenizer.json found next to the model, only estimated counts are available"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+impl Tokenize for ModelTokenizer {
+    fn encode(&self, text: &str) -> Result<Vec<u32>, RuntimeError> {
+        let encoding = self
+            .exact()?
+            .encode(text, false)
+            .map_err(|e| RuntimeError::Internal(format!("tokenization failed: {}", e)))?;
+        Ok(encoding.get_ids().to_vec())
+    }
+
+    fn decode(&self, ids: &[u32]) -> Result<String, RuntimeError> {
+        self.exact()?
+            .decode(ids, false)
+            .map_err(|e| RuntimeError::Internal(format!("detokenization failed: {}", e)))
+    }
+
+    fn count(&self, text: &str) -> usize {
+        match self.encode(text) {
+            Ok(ids) => ids.len(),
+            Err(_) => estimate(text),
+        }
+    }
+
+    /// False when counts are estimated rather than produced by the model's tokenizer.
+    fn is_exact(&self) -> bool {
+        matches!(self, ModelTokenizer::HuggingFace(_))
+    }
+}
+
+fn estimate(text: &str) -> usize {
+    text.chars().count().div_ceil(ESTIMATED_CHARS_PER_TOKEN)
+}
+
+/// Tokenizers by model path, loaded on first use
+#[derive(Clone, Default)]
+pub struct TokenizerCache(Arc<Mutex<HashMap<PathBuf, Arc<ModelTokenizer>>>>);
+
+impl TokenizerCache {
+    pub async fn get(&self, model_path: &Path, stored: Option<&Path>) -> Arc<ModelTokenizer> {
+        if let Some(tokenizer) = self.0.lock().unwrap().get(model_path) {
+            return tokenizer.clone();
+        }
+        // tokenizer.json files run to several MB, so parse off the executor.
+        let path = model_path.to_path_buf();
+        let stored = stored.map(Path::to_path_buf);
+        let tokenizer = tokio::task::spawn_blocking(move || ModelTokenizer::load(&path, stored.as_deref()))
+            .await
+            .unwrap_or(ModelTokenizer::Estimate);
+        self.0
+            .lock()
+            .unwrap()
+            .entry(model_path.to_path_buf())
+            .or_insert_with(|| Arc::new(tokenizer))
+            .clone()
+    }
+}
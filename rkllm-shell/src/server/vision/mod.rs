@@ -7,6 +7,22 @@ use anyhow::{Context, Result};
 use base64::{engine::general_purpose, Engine as _};
 use image::DynamicImage;
 use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::{VisionEncoderKind, VisionModelConfig};
+
+mod onnx;
+pub mod preprocess;
+pub mod video;
+
+pub use onnx::OnnxVisionEncoder;
+pub use preprocess::PreprocessProfile;
+
+/// Marks where an image's embeddings go in a multimodal prompt
+pub const IMAGE_PLACEHOLDER: &str = "<image>";
+
+/// Marks where a video's frame embeddings go in a multimodal prompt
+pub const VIDEO_PLACEHOLDER: &str = "<video>";
 
 // ---------------------------------------------------------------------------
 // Image Processing Utilities
@@ -85,37 +101,6 @@ fn detect_image_format(bytes: &[u8]) -> Option<VisionVisionImageFormat> {
     None
 }
 
-pub fn preprocess_image(
-    img: DynamicImage,
-    target_size: (u32, u32),
-) -> Result<Vec<f32>> {
-    let resized = img.resize_exact(
-        target_size.0,
-        target_size.1,
-        image::imageops::FilterType::Triangle,
-    );
-
-    let rgb = resized.to_rgb8();
-
-    let mean = [0.48145466_f32, 0.4578275, 0.40821073];
-    let std = [0.26862954_f32, 0.26130258, 0.27577711];
-
-    let mut output = Vec::with_capacity((target_size.0 * target_size.1 * 3) as usize);
-
-    for y in 0..target_size.1 {
-        for x in 0..target_size.0 {
-            let pixel = rgb.get_pixel(x, y);
-            for c in 0..3 {
-                let val = pixel[c] as f32 / 255.0;
-                let normalized = (val - mean[c]) / std[c];
-                output.push(normalized);
-            }
-        }
-    }
-
-    Ok(output)
-}
-
 // ---------------------------------------------------------------------------
 // Vision Encoder Trait
 // ---------------------------------------------------------------------------
@@ -193,78 +178,240 @@ impl VisionEncoder for StubVisionEncoder {
     }
 }
 
+/// The preprocessing profile of a `vision` config entry, with its
+/// `input_size` override applied.
+pub fn profile(config: &VisionModelConfig) -> PreprocessProfile {
+    let mut profile = config.profile.resolve();
+    if let Some(input_size) = config.input_size {
+        profile.input_size = input_size;
+    }
+    profile
+}
+
+/// Loads the encoder a `vision` config entry describes.
+pub fn load_encoder(config: &VisionModelConfig) -> Result<Arc<dyn VisionEncoder>> {
+    let [width, height] = profile(config).input_size;
+    let input_size = (width, height);
+    match config.encoder {
+        VisionEncoderKind::Onnx => {
+            let path = config
+                .path
+                .as_ref()
+                .with_context(|| format!("No encoder path configured for {}", config.model))?;
+            let encoder = OnnxVisionEncoder::load(path, input_size)?;
+            for (name, configured, actual) in [
+                ("embed_dim", config.embed_dim, encoder.embed_dim()),
+                ("num_image_tokens", config.num_image_tokens, encoder.num_image_tokens()),
+            ] {
+                if let Some(configured) = configured.filter(|&n| n != actual) {
+                    anyhow::bail!(
+                        "{} of {} is {} but the encoder produces {}",
+                        name,
+                        config.model,
+                        configured,
+                        actual
+                    );
+                }
+            }
+            Ok(Arc::new(encoder))
+        }
+        VisionEncoderKind::Stub => {
+            let defaults = VisionEncoderConfig::default();
+            Ok(Arc::new(StubVisionEncoder::new(VisionEncoderConfig {
+                model_path: String::new(),
+                input_size,
+                embed_dim: config.embed_dim.unwrap_or(defaults.embed_dim),
+                num_image_tokens: config.num_image_tokens.unwrap_or(defaults.num_image_tokens),
+            })))
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // High-level Multimodal Input Builder
 // ---------------------------------------------------------------------------
 
+use std::ffi::CString;
+
 use rkllm_api_sys::{
     RKLLMInput, RKLLMInputType_RKLLM_INPUT_MULTIMODAL, RKLLMMultiModalInput,
 };
 
+/// An `RKLLMInput` together with the buffers its pointers refer to.
+///
+/// The prompt, the image and video markers, the embeddings and the
+/// preprocessed images are owned by the guard, so they stay alive for as long as the guard
+/// does and are freed when it is dropped. Keep it alive until `rkllm_run` has
+/// returned.
+pub struct MultimodalInputGuard {
+    input: RKLLMInput,
+    _prompt: CString,
+    _markers: Vec<CString>,
+    _embeddings: Vec<f32>,
+    _video_embeddings: Vec<f32>,
+    _preprocessed_images: Vec<Vec<f32>>,
+}
+
+impl MultimodalInputGuard {
+    /// The input to pass to `rkllm_run`. Only valid while the guard lives.
+    pub fn input_mut(&mut self) -> &mut RKLLMInput {
+        &mut self.input
+    }
+
+    pub fn set_enable_thinking(&mut self, enable_thinking: bool) {
+        self.input.enable_thinking = enable_thinking;
+    }
+}
+
+/// Repeats the placeholder of the `i`-th image `tiles[i]` times, as the
+/// runtime expects one placeholder per tile.
+pub fn expand_placeholders(prompt: &str, tiles: &[usize]) -> String {
+    let mut parts = prompt.split(IMAGE_PLACEHOLDER);
+    let mut expanded = parts.next().unwrap_or_default().to_string();
+    for (i, part) in parts.enumerate() {
+        let count = tiles.get(i).copied().unwrap_or(1);
+        expanded.push_str(&vec![IMAGE_PLACEHOLDER; count].join("\n"));
+        expanded.push_str(part);
+    }
+    expanded
+}
+
+/// Embeddings for `inputs`, or none without calling the encoder.
+fn encode(vision_encoder: &dyn VisionEncoder, inputs: &[Vec<f32>]) -> Result<Vec<f32>> {
+    if inputs.is_empty() {
+        return Ok(Vec::new());
+    }
+    vision_encoder.encode(inputs)
+}
+
+/// Pointer to `embeddings` for the runtime, null when there are none.
+fn embed_ptr(embeddings: &mut [f32]) -> *mut f32 {
+    if embeddings.is_empty() {
+        std::ptr::null_mut()
+    } else {
+        embeddings.as_mut_ptr()
+    }
+}
+
+/// Builds the runtime input for a prompt with images and videos. Videos are
+/// given as their frames and cut to the frame count of the shortest one, as
+/// the runtime takes the same number of frames from every video.
 pub fn build_multimodal_input(
     prompt: &str,
     images_base64: &[String],
+    videos: &[Vec<DynamicImage>],
     vision_encoder: &dyn VisionEncoder,
-) -> Result<RKLLMInput> {
-    if images_base64.is_empty() {
-        let c_prompt = std::ffi::CString::new(prompt)?;
-        return Ok(RKLLMInput {
+    profile: &PreprocessProfile,
+) -> Result<MultimodalInputGuard> {
+    if images_base64.is_empty() && videos.is_empty() {
+        let c_prompt = CString::new(prompt)?;
+        // Moving a CString does not move its heap buffer, so the pointer
+        // taken here stays valid once the CString is inside the guard.
+        let input = RKLLMInput {
             role: std::ptr::null(),
             enable_thinking: false,
             input_type: rkllm_api_sys::RKLLMInputType_RKLLM_INPUT_PROMPT,
             __bindgen_anon_1: rkllm_api_sys::RKLLMInput__bindgen_ty_1 {
-                prompt_input: c_prompt.into_raw(),
+                prompt_input: c_prompt.as_ptr(),
             },
+        };
+        return Ok(MultimodalInputGuard {
+            input,
+            _prompt: c_prompt,
+            _markers: Vec::new(),
+            _embeddings: Vec::new(),
+            _video_embeddings: Vec::new(),
+            _preprocessed_images: Vec::new(),
         });
     }
 
+    // Tiled images reach the runtime as one image per tile.
     let mut preprocessed_images = Vec::new();
+    let mut tiles = Vec::new();
     for img_b64 in images_base64 {
         let (img, _format) = decode_base64_image(img_b64)?;
-        let preprocessed = preprocess_image(img, vision_encoder.input_size())?;
-        preprocessed_images.push(preprocessed);
+        let preprocessed = profile.preprocess(&img, vision_encoder.input_size());
+        tiles.push(preprocessed.len());
+        preprocessed_images.extend(preprocessed);
     }
 
-    let embeddings = vision_encoder.encode(&preprocessed_images)?;
+    let mut embeddings = encode(vision_encoder, &preprocessed_images)?;
+
+    let size = vision_encoder.input_size();
+    let n_frames = videos.iter().map(Vec::len).min().unwrap_or(0);
+    let mut preprocessed_frames = Vec::new();
+    for frames in videos {
+        for frame in video::sample_evenly(frames.iter().collect(), n_frames) {
+            preprocessed_frames.push(profile.preprocess_frame(frame, size));
+        }
+    }
+    let mut video_embeddings = encode(vision_encoder, &preprocessed_frames)?;
 
-    let c_prompt = std::ffi::CString::new(prompt)?;
-    let n_images = images_base64.len();
+    let c_prompt = CString::new(expand_placeholders(prompt, &tiles))?;
+    let n_images = preprocessed_images.len();
     let n_tokens = vision_encoder.num_image_tokens();
 
+    let mut markers = Vec::new();
+    let mut marker = |token: &Option<String>| -> Result<*const std::os::raw::c_char> {
+        Ok(match token {
+            Some(token) => {
+                let token = CString::new(token.as_str())?;
+                let ptr = token.as_ptr();
+                markers.push(token);
+                ptr
+            }
+            None => std::ptr::null(),
+        })
+    };
+    let image_start = marker(&profile.image_start)?;
+    let image_end = marker(&profile.image_end)?;
+    let image_content = marker(&profile.image_content)?;
+    let video_start = marker(&profile.video_start)?;
+    let video_end = marker(&profile.video_end)?;
+    let video_content = marker(&profile.video_content)?;
+
     let input = RKLLMInput {
         role: std::ptr::null(),
         enable_thinking: false,
         input_type: RKLLMInputType_RKLLM_INPUT_MULTIMODAL,
         __bindgen_anon_1: rkllm_api_sys::RKLLMInput__bindgen_ty_1 {
             multimodal_input: RKLLMMultiModalInput {
-                prompt: c_prompt.into_raw(),
+                prompt: c_prompt.as_ptr() as *mut _,
                 image: rkllm_api_sys::RKLLMMultiModalInput__bindgen_ty_1 {
-                    image_embed: embeddings.as_ptr() as *mut f32,
+                    image_embed: embed_ptr(&mut embeddings),
                     n_image_tokens: n_tokens,
                     n_image: n_images,
-                    image_start: std::ptr::null(),
-                    image_end: std::ptr::null(),
-                    image_content: std::ptr::null(),
-                    image_width: vision_encoder.input_size().0 as usize,
-                    image_height: vision_encoder.input_size().1 as usize,
+                    image_start,
+                    image_end,
+                    image_content,
+                    image_width: size.0 as usize,
+                    image_height: size.1 as usize,
                 },
                 video: rkllm_api_sys::RKLLMMultiModalInput__bindgen_ty_2 {
-                    video_embed: std::ptr::null_mut(),
-                    n_frame_tokens: 0,
-                    n_frame_per_video: 0,
-                    n_video: 0,
-                    video_start: std::ptr::null(),
-                    video_end: std::ptr::null(),
-                    video_content: std::ptr::null(),
-                    frame_width: 0,
-                    frame_height: 0,
+                    video_embed: embed_ptr(&mut video_embeddings),
+                    n_frame_tokens: n_tokens,
+                    n_frame_per_video: n_frames,
+                    n_video: videos.len(),
+                    video_start,
+                    video_end,
+                    video_content,
+                    frame_width: size.0 as usize,
+                    frame_height: size.1 as usize,
                 },
             },
         },
     };
 
-    std::mem::forget(embeddings);
-    std::mem::forget(preprocessed_images);
-
-    Ok(input)
+    Ok(MultimodalInputGuard {
+        input,
+        _prompt: c_prompt,
+        _markers: markers,
+        _embeddings: embeddings,
+        _video_embeddings: video_embeddings,
+        _preprocessed_images: preprocessed_images,
+    })
 }
+
+#[cfg(test)]
+#[path = "vision_test.rs"]
+mod tests;
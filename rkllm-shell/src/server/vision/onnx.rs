@@ -0,0 +1,94 @@
+//! CPU vision encoder running an ONNX export of the vision tower
+//!
+//! The model takes one normalised `[1, 3, height, width]` image and returns
+//! its embeddings as `[1, tokens, dim]` or `[tokens, dim]`. Inference runs on
+//! tract, so no native runtime is needed.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use tract_onnx::prelude::*;
+
+use super::VisionEncoder;
+
+pub struct OnnxVisionEncoder {
+    plan: TypedRunnableModel<TypedModel>,
+    input_size: (u32, u32),
+    embed_dim: usize,
+    num_image_tokens: usize,
+}
+
+impl OnnxVisionEncoder {
+    /// Loads and optimises the model at `path` for images of `input_size`.
+    pub fn load(path: &Path, input_size: (u32, u32)) -> Result<Self> {
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .with_context(|| format!("Failed to read vision encoder {}", path.display()))?;
+        Self::from_model(model, input_size)
+    }
+
+    pub fn from_model(model: InferenceModel, input_size: (u32, u32)) -> Result<Self> {
+        let (width, height) = input_size;
+        let plan = model
+            .with_input_fact(0, f32::fact([1, 3, height as usize, width as usize]).into())?
+            .into_optimized()?
+            .into_runnable()?;
+
+        let output = plan.model().output_fact(0)?;
+        let Some(shape) = output.shape.as_concrete() else {
+            bail!("Vision encoder output shape {:?} is not fixed", output.shape);
+        };
+        let (num_image_tokens, embed_dim) = match *shape {
+            [1, tokens, dim] | [tokens, dim] => (tokens, dim),
+            _ => bail!("Vision encoder output shape {:?} is not [1, tokens, dim]", shape),
+        };
+
+        Ok(Self {
+            plan,
+            input_size,
+            embed_dim,
+            num_image_tokens,
+        })
+    }
+
+    /// `PreprocessProfile::preprocess` lays pixels out as HWC; ONNX vision models take CHW.
+    fn to_tensor(&self, image: &[f32]) -> Result<Tensor> {
+        let (width, height) = (self.input_size.0 as usize, self.input_size.1 as usize);
+        if image.len() != width * height * 3 {
+            bail!("Expected a {}x{} RGB image, got {} values", width, height, image.len());
+        }
+        let chw = tract_ndarray::Array4::from_shape_fn((1, 3, height, width), |(_, c, y, x)| {
+            image[(y * width + x) * 3 + c]
+        });
+        Ok(chw.into())
+    }
+}
+
+impl VisionEncoder for OnnxVisionEncoder {
+    fn input_size(&self) -> (u32, u32) {
+        self.input_size
+    }
+
+    fn embed_dim(&self) -> usize {
+        self.embed_dim
+    }
+
+    fn num_image_tokens(&self) -> usize {
+        self.num_image_tokens
+    }
+
+    fn encode(&self, images: &[Vec<f32>]) -> Result<Vec<f32>> {
+        let mut embeddings = Vec::with_capacity(images.len() * self.num_image_tokens * self.embed_dim);
+        for image in images {
+            let input = self.to_tensor(image)?;
+            let outputs = self.plan.run(tvec!(input.into()))?;
+            let output = outputs[0].to_array_view::<f32>()?;
+            embeddings.extend(output.iter().copied());
+        }
+        Ok(embeddings)
+    }
+}
+
+#[cfg(test)]
+#[path = "onnx_test.rs"]
+mod tests;
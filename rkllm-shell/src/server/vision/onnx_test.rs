@@ -0,0 +1,89 @@
+//! Tests for the ONNX vision encoder
+
+use tract_onnx::pb;
+
+use super::*;
+
+/// A model that reshapes a 4x4 image into 4 tokens of 12 values, so each
+/// embedding is a known slice of the CHW pixels.
+fn reshape_model() -> InferenceModel {
+    let tensor_type = |name: &str| pb::ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(pb::TypeProto {
+            value: Some(pb::type_proto::Value::TensorType(pb::type_proto::Tensor {
+                elem_type: pb::tensor_proto::DataType::Float as i32,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let graph = pb::GraphProto {
+        name: "reshape".to_string(),
+        node: vec![pb::NodeProto {
+            op_type: "Reshape".to_string(),
+            input: vec!["pixel_values".to_string(), "shape".to_string()],
+            output: vec!["image_embeds".to_string()],
+            ..Default::default()
+        }],
+        initializer: vec![pb::TensorProto {
+            name: "shape".to_string(),
+            dims: vec![3],
+            data_type: pb::tensor_proto::DataType::Int64 as i32,
+            int64_data: vec![1, 4, 12],
+            ..Default::default()
+        }],
+        input: vec![tensor_type("pixel_values")],
+        output: vec![tensor_type("image_embeds")],
+        ..Default::default()
+    };
+    let proto = pb::ModelProto {
+        ir_version: 7,
+        opset_import: vec![pb::OperatorSetIdProto {
+            domain: String::new(),
+            version: 13,
+        }],
+        graph: Some(graph),
+        ..Default::default()
+    };
+    tract_onnx::onnx().model_for_proto_model(&proto).unwrap()
+}
+
+/// A 4x4 HWC image whose values encode their channel and position.
+fn hwc_image() -> Vec<f32> {
+    (0..4 * 4)
+        .flat_map(|pixel| (0..3).map(move |c| (c * 100 + pixel) as f32))
+        .collect()
+}
+
+#[test]
+fn test_shape_read_from_model() {
+    let encoder = OnnxVisionEncoder::from_model(reshape_model(), (4, 4)).unwrap();
+    assert_eq!(encoder.input_size(), (4, 4));
+    assert_eq!(encoder.num_image_tokens(), 4);
+    assert_eq!(encoder.embed_dim(), 12);
+}
+
+#[test]
+fn test_encode_feeds_chw_and_concatenates_images() {
+    let encoder = OnnxVisionEncoder::from_model(reshape_model(), (4, 4)).unwrap();
+    let embeddings = encoder.encode(&[hwc_image(), hwc_image()]).unwrap();
+    assert_eq!(embeddings.len(), 2 * 4 * 12);
+
+    // Channel planes come one after another: all red, then green, then blue.
+    let expected: Vec<f32> = (0..3)
+        .flat_map(|c| (0..16).map(move |pixel| (c * 100 + pixel) as f32))
+        .collect();
+    assert_eq!(&embeddings[..48], &expected[..]);
+    assert_eq!(&embeddings[48..], &expected[..]);
+
+    // Same input, same output.
+    assert_eq!(encoder.encode(&[hwc_image()]).unwrap(), &embeddings[..48]);
+}
+
+#[test]
+fn test_wrong_image_size_is_rejected() {
+    let encoder = OnnxVisionEncoder::from_model(reshape_model(), (4, 4)).unwrap();
+    let err = encoder.encode(&[vec![0.0; 10]]).unwrap_err();
+    assert!(err.to_string().contains("4x4"));
+}
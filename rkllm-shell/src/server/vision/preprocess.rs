@@ -0,0 +1,328 @@
+//! Image preprocessing profiles
+//!
+//! Vision towers differ in how they want images: CLIP stretches to a square,
+//! Qwen2-VL pads to one, InternVL and MiniCPM-V cut large images into tiles
+//! plus a thumbnail. A profile captures those choices together with the
+//! normalisation and the marker tokens around each image in the prompt.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgb, RgbImage};
+use serde::Deserialize;
+
+const CLIP_MEAN: [f32; 3] = [0.48145466, 0.4578275, 0.40821073];
+const CLIP_STD: [f32; 3] = [0.26862954, 0.26130258, 0.27577711];
+const IMAGENET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const IMAGENET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+/// How an image is fitted to the encoder's input size
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizeMode {
+    /// Resize to the input size, ignoring the aspect ratio
+    #[default]
+    Stretch,
+    /// Keep the aspect ratio and pad the rest with `pad_color`
+    Letterbox,
+    /// Keep the aspect ratio, fill the input and crop the overflow evenly
+    CenterCrop,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizeFilter {
+    Nearest,
+    #[default]
+    Triangle,
+    CatmullRom,
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Where the downscaled whole image goes when an image is tiled
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Thumbnail {
+    #[default]
+    None,
+    First,
+    Last,
+}
+
+/// Cutting large images into input-sized tiles. With `max_tiles` at 1 images
+/// are never tiled.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TileGrid {
+    pub min_tiles: usize,
+    pub max_tiles: usize,
+    /// Added only when an image is cut into more than one tile
+    pub thumbnail: Thumbnail,
+}
+
+impl Default for TileGrid {
+    fn default() -> Self {
+        Self {
+            min_tiles: 1,
+            max_tiles: 1,
+            thumbnail: Thumbnail::None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PreprocessProfile {
+    /// Per-channel mean and standard deviation, on the 0..1 scale
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+    /// Encoder input width and height
+    pub input_size: [u32; 2],
+    pub resize: ResizeMode,
+    pub filter: ResizeFilter,
+    /// RGB colour letterboxing pads with
+    pub pad_color: [u8; 3],
+    pub tiles: TileGrid,
+    /// Tokens the runtime puts before and after each image's embeddings, and
+    /// the token it stands in for each embedding with
+    pub image_start: Option<String>,
+    pub image_end: Option<String>,
+    pub image_content: Option<String>,
+    /// The same for video frames
+    pub video_start: Option<String>,
+    pub video_end: Option<String>,
+    pub video_content: Option<String>,
+}
+
+impl Default for PreprocessProfile {
+    fn default() -> Self {
+        Self::clip()
+    }
+}
+
+impl PreprocessProfile {
+    /// CLIP ViT-L/14: stretched to 224x224
+    pub fn clip() -> Self {
+        Self {
+            mean: CLIP_MEAN,
+            std: CLIP_STD,
+            input_size: [224, 224],
+            resize: ResizeMode::Stretch,
+            filter: ResizeFilter::Triangle,
+            pad_color: [0, 0, 0],
+            tiles: TileGrid::default(),
+            image_start: None,
+            image_end: None,
+            image_content: None,
+            video_start: None,
+            video_end: None,
+            video_content: None,
+        }
+    }
+
+    /// Qwen2-VL: padded to a 392x392 square
+    pub fn qwen2_vl() -> Self {
+        Self {
+            input_size: [392, 392],
+            resize: ResizeMode::Letterbox,
+            filter: ResizeFilter::CatmullRom,
+            pad_color: [127, 127, 127],
+            image_start: Some("<|vision_start|>".to_string()),
+            image_end: Some("<|vision_end|>".to_string()),
+            image_content: Some("<|image_pad|>".to_string()),
+            video_start: Some("<|vision_start|>".to_string()),
+            video_end: Some("<|vision_end|>".to_string()),
+            video_content: Some("<|video_pad|>".to_string()),
+            ..Self::clip()
+        }
+    }
+
+    /// MiniCPM-V: up to 9 slices of 448x448, after the whole image
+    pub fn minicpm_v() -> Self {
+        Self {
+            mean: [0.5, 0.5, 0.5],
+            std: [0.5, 0.5, 0.5],
+            input_size: [448, 448],
+            resize: ResizeMode::Stretch,
+            filter: ResizeFilter::CatmullRom,
+            pad_color: [0, 0, 0],
+            tiles: TileGrid {
+                min_tiles: 1,
+                max_tiles: 9,
+                thumbnail: Thumbnail::First,
+            },
+            image_start: Some("<image>".to_string()),
+            image_end: Some("</image>".to_string()),
+            image_content: Some("<unk>".to_string()),
+            video_start: None,
+            video_end: None,
+            video_content: None,
+        }
+    }
+
+    /// InternVL: up to 12 tiles of 448x448, followed by the whole image
+    pub fn internvl() -> Self {
+        Self {
+            mean: IMAGENET_MEAN,
+            std: IMAGENET_STD,
+            input_size: [448, 448],
+            resize: ResizeMode::Stretch,
+            filter: ResizeFilter::CatmullRom,
+            pad_color: [0, 0, 0],
+            tiles: TileGrid {
+                min_tiles: 1,
+                max_tiles: 12,
+                thumbnail: Thumbnail::Last,
+            },
+            image_start: Some("<img>".to_string()),
+            image_end: Some("</img>".to_string()),
+            image_content: Some("<IMG_CONTEXT>".to_string()),
+            video_start: None,
+            video_end: None,
+            video_content: None,
+        }
+    }
+
+    /// Normalised HWC tensors for `image`, one per tile, each `size` large.
+    pub fn preprocess(&self, image: &DynamicImage, size: (u32, u32)) -> Vec<Vec<f32>> {
+        let (cols, rows) = self.tile_grid(image.width(), image.height(), size);
+        if cols * rows == 1 {
+            return vec![self.preprocess_frame(image, size)];
+        }
+
+        let (width, height) = size;
+        let grid = image
+            .resize_exact(cols * width, rows * height, self.filter.into())
+            .to_rgb8();
+        let mut tiles: Vec<Vec<f32>> = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (col, row)))
+            .map(|(col, row)| {
+                let tile = imageops::crop_imm(&grid, col * width, row * height, width, height);
+                self.normalize(&tile.to_image())
+            })
+            .collect();
+        match self.tiles.thumbnail {
+            Thumbnail::None => {}
+            Thumbnail::First => tiles.insert(0, self.preprocess_frame(image, size)),
+            Thumbnail::Last => tiles.push(self.preprocess_frame(image, size)),
+        }
+        tiles
+    }
+
+    /// The normalised HWC tensor for a single untiled image, such as a video frame.
+    pub fn preprocess_frame(&self, frame: &DynamicImage, size: (u32, u32)) -> Vec<f32> {
+        self.normalize(&self.fit(frame, size))
+    }
+
+    /// Columns and rows of tiles for an image, picking the grid whose aspect
+    /// ratio is closest to the image's. On a tie the larger grid wins if the
+    /// image has enough pixels to fill half of it.
+    pub fn tile_grid(&self, width: u32, height: u32, size: (u32, u32)) -> (u32, u32) {
+        if self.tiles.max_tiles <= 1 {
+            return (1, 1);
+        }
+        let aspect = width as f32 / height as f32;
+        let area = width as f32 * height as f32;
+        let mut best = (1, 1);
+        let mut best_diff = f32::INFINITY;
+        for n in self.tiles.min_tiles.max(1)..=self.tiles.max_tiles {
+            for cols in (1..=n).filter(|cols| n % cols == 0) {
+                let rows = n / cols;
+                let diff = (aspect - cols as f32 / rows as f32).abs();
+                let grid_area = (n as u32 * size.0 * size.1) as f32;
+                if diff < best_diff || (diff == best_diff && area > 0.5 * grid_area) {
+                    best = (cols as u32, rows as u32);
+                    best_diff = diff;
+                }
+            }
+        }
+        best
+    }
+
+    fn fit(&self, image: &DynamicImage, (width, height): (u32, u32)) -> RgbImage {
+        let filter = self.filter.into();
+        let scale = |fit: fn(f32, f32) -> f32| {
+            let scale = fit(width as f32 / image.width() as f32, height as f32 / image.height() as f32);
+            (
+                ((image.width() as f32 * scale).round() as u32).max(1),
+                ((image.height() as f32 * scale).round() as u32).max(1),
+            )
+        };
+        match self.resize {
+            ResizeMode::Stretch => image.resize_exact(width, height, filter).to_rgb8(),
+            ResizeMode::Letterbox => {
+                let (w, h) = scale(f32::min);
+                let (w, h) = (w.min(width), h.min(height));
+                let resized = image.resize_exact(w, h, filter).to_rgb8();
+                let mut canvas = RgbImage::from_pixel(width, height, Rgb(self.pad_color));
+                let (x, y) = ((width - w) / 2, (height - h) / 2);
+                imageops::overlay(&mut canvas, &resized, x as i64, y as i64);
+                canvas
+            }
+            ResizeMode::CenterCrop => {
+                let (w, h) = scale(f32::max);
+                let (w, h) = (w.max(width), h.max(height));
+                let resized = image.resize_exact(w, h, filter).to_rgb8();
+                let (x, y) = ((w - width) / 2, (h - height) / 2);
+                imageops::crop_imm(&resized, x, y, width, height).to_image()
+            }
+        }
+    }
+
+    fn normalize(&self, image: &RgbImage) -> Vec<f32> {
+        image
+            .pixels()
+            .flat_map(|pixel| {
+                (0..3).map(move |c| (pixel[c] as f32 / 255.0 - self.mean[c]) / self.std[c])
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BuiltinProfile {
+    Clip,
+    Qwen2Vl,
+    MinicpmV,
+    Internvl,
+}
+
+/// A built-in profile by name, or a profile spelled out in the config
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ProfileSetting {
+    Builtin(BuiltinProfile),
+    Custom(PreprocessProfile),
+}
+
+impl Default for ProfileSetting {
+    fn default() -> Self {
+        Self::Builtin(BuiltinProfile::Clip)
+    }
+}
+
+impl ProfileSetting {
+    pub fn resolve(&self) -> PreprocessProfile {
+        match self {
+            Self::Builtin(BuiltinProfile::Clip) => PreprocessProfile::clip(),
+            Self::Builtin(BuiltinProfile::Qwen2Vl) => PreprocessProfile::qwen2_vl(),
+            Self::Builtin(BuiltinProfile::MinicpmV) => PreprocessProfile::minicpm_v(),
+            Self::Builtin(BuiltinProfile::Internvl) => PreprocessProfile::internvl(),
+            Self::Custom(profile) => profile.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "preprocess_test.rs"]
+mod tests;
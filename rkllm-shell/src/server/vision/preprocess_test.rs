@@ -0,0 +1,135 @@
+//! Golden-tensor tests for the preprocessing profiles
+//!
+//! Images and input sizes are tiny so the expected tensors can be written
+//! out; the values are the profiles' normalisations of black, white, grey
+//! and red pixels.
+
+use super::*;
+
+const BLACK: [u8; 3] = [0, 0, 0];
+const WHITE: [u8; 3] = [255, 255, 255];
+
+/// An image whose pixel colours come from `color(x, y)`.
+fn image(width: u32, height: u32, color: impl Fn(u32, u32) -> [u8; 3]) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| Rgb(color(x, y))))
+}
+
+/// `pixels` normalised pixels, laid out HWC.
+fn tensor(pixels: &[[f32; 3]]) -> Vec<f32> {
+    pixels.iter().flatten().copied().collect()
+}
+
+fn assert_tensors(actual: &[Vec<f32>], expected: &[Vec<f32>]) {
+    assert_eq!(actual.len(), expected.len(), "number of tiles");
+    for (tile, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+        assert_eq!(actual.len(), expected.len(), "size of tile {}", tile);
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!((a - e).abs() < 1e-5, "tile {} value {}: {} != {}", tile, i, a, e);
+        }
+    }
+}
+
+#[test]
+fn test_clip_golden() {
+    let red = [1.930336, -1.752097, -1.48022];
+    let tiles = PreprocessProfile::clip().preprocess(&image(1, 1, |_, _| [255, 0, 0]), (2, 2));
+    assert_tensors(&tiles, &[tensor(&[red; 4])]);
+}
+
+#[test]
+fn test_qwen2_vl_golden() {
+    // A wide white image is letterboxed between grey bars.
+    let grey = [0.061738, 0.153889, 0.325729];
+    let white = [1.930336, 2.074884, 2.145897];
+    let tiles = PreprocessProfile::qwen2_vl().preprocess(&image(4, 2, |_, _| WHITE), (4, 4));
+    let expected: Vec<[f32; 3]> = [grey, white, white, grey]
+        .into_iter()
+        .flat_map(|row| [row; 4])
+        .collect();
+    assert_tensors(&tiles, &[tensor(&expected)]);
+}
+
+#[test]
+fn test_minicpm_v_golden() {
+    // A tall image, black above white: thumbnail first, then the two slices.
+    let profile = PreprocessProfile {
+        filter: ResizeFilter::Nearest,
+        ..PreprocessProfile::minicpm_v()
+    };
+    let tiles = profile.preprocess(&image(2, 4, |_, y| if y < 2 { BLACK } else { WHITE }), (2, 2));
+    let (black, white) = ([-1.0; 3], [1.0; 3]);
+    assert_tensors(
+        &tiles,
+        &[
+            tensor(&[black, black, white, white]),
+            tensor(&[black; 4]),
+            tensor(&[white; 4]),
+        ],
+    );
+}
+
+#[test]
+fn test_internvl_golden() {
+    // A wide image, black left of white: the two tiles, then the thumbnail.
+    let profile = PreprocessProfile {
+        filter: ResizeFilter::Nearest,
+        ..PreprocessProfile::internvl()
+    };
+    let tiles = profile.preprocess(&image(4, 2, |x, _| if x < 2 { BLACK } else { WHITE }), (2, 2));
+    let black = [-2.117904, -2.035714, -1.804444];
+    let white = [2.248908, 2.428571, 2.64];
+    assert_tensors(
+        &tiles,
+        &[
+            tensor(&[black; 4]),
+            tensor(&[white; 4]),
+            tensor(&[black, white, black, white]),
+        ],
+    );
+}
+
+#[test]
+fn test_center_crop_keeps_the_middle() {
+    let profile = PreprocessProfile {
+        mean: [0.0; 3],
+        std: [1.0; 3],
+        resize: ResizeMode::CenterCrop,
+        filter: ResizeFilter::Nearest,
+        ..PreprocessProfile::clip()
+    };
+    // Columns 1 and 2 of 4 are white; cropping to 2x2 keeps only them.
+    let tiles = profile.preprocess(&image(4, 2, |x, _| if x == 1 || x == 2 { WHITE } else { BLACK }), (2, 2));
+    assert_tensors(&tiles, &[tensor(&[[1.0; 3]; 4])]);
+}
+
+#[test]
+fn test_tile_grid_follows_aspect_ratio() {
+    let internvl = PreprocessProfile::internvl();
+    assert_eq!(internvl.tile_grid(1344, 448, (448, 448)), (3, 1));
+    assert_eq!(internvl.tile_grid(448, 896, (448, 448)), (1, 2));
+    // Small images stay on the smallest matching grid...
+    assert_eq!(internvl.tile_grid(200, 200, (448, 448)), (1, 1));
+    // ...large ones take the larger grid with the same shape.
+    assert_eq!(internvl.tile_grid(1792, 1792, (448, 448)), (3, 3));
+    assert_eq!(PreprocessProfile::clip().tile_grid(1344, 448, (448, 448)), (1, 1));
+}
+
+#[test]
+fn test_profile_setting_by_name_or_inline() {
+    let named: ProfileSetting = serde_json::from_str(r#""qwen2_vl""#).unwrap();
+    assert_eq!(named.resolve(), PreprocessProfile::qwen2_vl());
+
+    let custom: ProfileSetting =
+        serde_json::from_str(r#"{"resize": "letterbox", "image_start": "<img>"}"#).unwrap();
+    let custom = custom.resolve();
+    assert_eq!(custom.resize, ResizeMode::Letterbox);
+    assert_eq!(custom.image_start.as_deref(), Some("<img>"));
+    assert_eq!(custom.mean, PreprocessProfile::clip().mean);
+}
+
+#[test]
+fn test_profile_setting_rejects_unknown_fields() {
+    // A misspelt key must not silently fall back to the CLIP defaults.
+    assert!(serde_json::from_str::<ProfileSetting>(r#"{"image_strat": "<img>"}"#).is_err());
+    assert!(serde_json::from_str::<ProfileSetting>(r#"{"tiles": {"max_tile": 4}}"#).is_err());
+}
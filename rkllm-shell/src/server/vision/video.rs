@@ -0,0 +1,183 @@
+//! Turning video input into frames for the vision encoder
+//!
+//! MP4 and WebM videos are decoded with `ffmpeg`, sampled at `video.fps` and
+//! cut off after `video.max_frames`. Frames sent as an array of images are
+//! thinned out evenly to the same maximum before they are decoded. Decoding
+//! runs on the blocking thread pool.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine as _};
+use image::DynamicImage;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::VideoConfig;
+use crate::server::api_models::VideoInput;
+use crate::server::apis::error::ApiError;
+
+use super::decode_base64_image;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const WEBM_MAGIC: &[u8] = &[0x1A, 0x45, 0xDF, 0xA3];
+
+/// The frames of one video, at most `config.max_frames` of them.
+pub async fn load_video(input: &VideoInput, config: &VideoConfig) -> Result<Vec<DynamicImage>, ApiError> {
+    let frames = match input {
+        VideoInput::Frames(frames) => {
+            // Thin out before decoding so frames past the limit cost nothing.
+            let frames: Vec<String> = sample_evenly(frames.iter().collect(), config.max_frames)
+                .into_iter()
+                .cloned()
+                .collect();
+            blocking(move || {
+                frames
+                    .iter()
+                    .map(|frame| {
+                        decode_base64_image(frame).map(|(image, _)| image).map_err(|e| {
+                            ApiError::InvalidRequest(format!("videos: invalid frame: {:#}", e))
+                        })
+                    })
+                    .collect()
+            })
+            .await?
+        }
+        VideoInput::Encoded(data) => {
+            let data = data.clone();
+            let bytes = blocking(move || {
+                let data = data.split_once(',').map_or(data.as_str(), |(_, data)| data);
+                general_purpose::STANDARD
+                    .decode(data)
+                    .map_err(|e| ApiError::InvalidRequest(format!("videos: invalid base64: {}", e)))
+            })
+            .await?;
+            decode_video(&bytes, config).await?
+        }
+    };
+    if frames.is_empty() {
+        return Err(ApiError::InvalidRequest("videos: video has no frames".to_string()));
+    }
+    Ok(sample_evenly(frames, config.max_frames))
+}
+
+/// Runs CPU-bound decoding off the async worker threads.
+async fn blocking<T, F>(f: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, ApiError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("videos: decoding panicked: {}", e)))?
+}
+
+/// `n` items spread evenly over `items`, first and last included.
+pub fn sample_evenly<T>(items: Vec<T>, n: usize) -> Vec<T> {
+    let len = items.len();
+    if len <= n {
+        return items;
+    }
+    if n <= 1 {
+        return items.into_iter().take(n).collect();
+    }
+    let picks: Vec<usize> = (0..n).map(|i| i * (len - 1) / (n - 1)).collect();
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| picks.contains(i))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+fn is_video(bytes: &[u8]) -> bool {
+    let mp4 = bytes.len() >= 8 && &bytes[4..8] == b"ftyp";
+    mp4 || bytes.starts_with(WEBM_MAGIC)
+}
+
+async fn decode_video(bytes: &[u8], config: &VideoConfig) -> Result<Vec<DynamicImage>, ApiError> {
+    if !is_video(bytes) {
+        return Err(ApiError::InvalidRequest("videos: not an MP4 or WebM video".to_string()));
+    }
+    // MP4s may keep their index at the end, so ffmpeg needs a seekable file.
+    let file = tempfile::NamedTempFile::new()
+        .map_err(|e| ApiError::InternalError(format!("videos: {}", e)))?;
+    let mut writer = tokio::fs::File::from_std(
+        file.reopen()
+            .map_err(|e| ApiError::InternalError(format!("videos: {}", e)))?,
+    );
+    writer
+        .write_all(bytes)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("videos: {}", e)))?;
+    drop(writer);
+
+    let mut command = Command::new(&config.ffmpeg);
+    command
+        .args(["-nostdin", "-loglevel", "error", "-i"])
+        .arg(file.path())
+        .args(["-vf", &format!("fps={}", config.fps)])
+        .args(["-frames:v", &config.max_frames.to_string()])
+        .args(["-f", "image2pipe", "-c:v", "png", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let child = command.spawn().map_err(|e| {
+        ApiError::InternalError(format!("videos: failed to run {}: {}", config.ffmpeg.display(), e))
+    })?;
+    let output = tokio::time::timeout(Duration::from_secs(config.timeout_secs), child.wait_with_output())
+        .await
+        .map_err(|_| {
+            ApiError::InvalidRequest(format!("videos: decoding took longer than {}s", config.timeout_secs))
+        })?
+        .map_err(|e| ApiError::InternalError(format!("videos: {}", e)))?;
+    if !output.status.success() {
+        return Err(ApiError::InvalidRequest(format!(
+            "videos: failed to decode video: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    blocking(move || {
+        split_png_stream(&output.stdout)?
+            .into_iter()
+            .map(|png| {
+                image::load_from_memory(png)
+                    .map_err(|e| ApiError::InternalError(format!("videos: invalid frame from ffmpeg: {}", e)))
+            })
+            .collect()
+    })
+    .await
+}
+
+/// Splits concatenated PNG files, as ffmpeg's `image2pipe` writes them.
+pub fn split_png_stream(mut stream: &[u8]) -> Result<Vec<&[u8]>, ApiError> {
+    let invalid = || ApiError::InternalError("videos: truncated frame from ffmpeg".to_string());
+    let mut pngs = Vec::new();
+    while !stream.is_empty() {
+        if !stream.starts_with(PNG_SIGNATURE) {
+            return Err(invalid());
+        }
+        // Walk the chunks (length, type, data, CRC) up to IEND.
+        let mut end = PNG_SIGNATURE.len();
+        loop {
+            let header = stream.get(end..end + 8).ok_or_else(invalid)?;
+            let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            end += 12 + length;
+            if end > stream.len() {
+                return Err(invalid());
+            }
+            if &header[4..8] == b"IEND" {
+                break;
+            }
+        }
+        pngs.push(&stream[..end]);
+        stream = &stream[end..];
+    }
+    Ok(pngs)
+}
+
+#[cfg(test)]
+#[path = "video_test.rs"]
+mod tests;
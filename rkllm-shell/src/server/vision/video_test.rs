@@ -0,0 +1,67 @@
+//! Tests for video frame loading
+
+use super::*;
+use crate::server::test_helpers::test_images::TINY_PNG;
+
+fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::RgbImage::new(width, height)
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+    png.into_inner()
+}
+
+#[test]
+fn test_sample_evenly() {
+    let items: Vec<u32> = (0..10).collect();
+    assert_eq!(sample_evenly(items.clone(), 4), vec![0, 3, 6, 9]);
+    assert_eq!(sample_evenly(items.clone(), 1), vec![0]);
+    assert_eq!(sample_evenly(items.clone(), 20), items);
+}
+
+#[test]
+fn test_split_png_stream() {
+    let (first, second) = (png_bytes(1, 1), png_bytes(2, 3));
+    let stream = [first.as_slice(), second.as_slice()].concat();
+    let pngs = split_png_stream(&stream).unwrap();
+    assert_eq!(pngs, vec![first.as_slice(), second.as_slice()]);
+    assert_eq!(image::load_from_memory(pngs[1]).unwrap().height(), 3);
+
+    assert!(split_png_stream(&stream[..stream.len() - 4]).is_err());
+    assert!(split_png_stream(b"not a png").is_err());
+}
+
+#[tokio::test]
+async fn test_frame_arrays_are_thinned_out() {
+    let config = VideoConfig {
+        max_frames: 2,
+        ..VideoConfig::default()
+    };
+    let frames = VideoInput::Frames(vec![TINY_PNG.to_string(); 5]);
+    assert_eq!(load_video(&frames, &config).await.unwrap().len(), 2);
+
+    // Only the first and last frames are kept, so the middle one is never decoded.
+    let mut frames = vec![TINY_PNG.to_string(); 5];
+    frames[2] = "not-an-image".to_string();
+    assert_eq!(load_video(&VideoInput::Frames(frames), &config).await.unwrap().len(), 2);
+
+    let err = load_video(&VideoInput::Frames(Vec::new()), &config).await.unwrap_err();
+    assert!(matches!(err, ApiError::InvalidRequest(msg) if msg.contains("no frames")));
+}
+
+#[tokio::test]
+async fn test_only_mp4_and_webm_are_decoded() {
+    let config = VideoConfig {
+        ffmpeg: "/nonexistent/ffmpeg".into(),
+        ..VideoConfig::default()
+    };
+    let png = VideoInput::Encoded(general_purpose::STANDARD.encode(png_bytes(1, 1)));
+    let err = load_video(&png, &config).await.unwrap_err();
+    assert!(matches!(err, ApiError::InvalidRequest(msg) if msg.contains("not an MP4 or WebM")));
+
+    // An MP4 header gets as far as running the decoder.
+    let mp4 = b"\x00\x00\x00\x18ftypmp42\x00\x00\x00\x00";
+    let mp4 = VideoInput::Encoded(format!("data:video/mp4;base64,{}", general_purpose::STANDARD.encode(mp4)));
+    let err = load_video(&mp4, &config).await.unwrap_err();
+    assert!(matches!(err, ApiError::InternalError(msg) if msg.contains("failed to run")));
+}
@@ -0,0 +1,129 @@
+//! Tests for building multimodal inputs
+
+use super::*;
+use crate::server::test_helpers::test_images::TINY_PNG;
+
+fn stub_encoder() -> StubVisionEncoder {
+    StubVisionEncoder::new(VisionEncoderConfig {
+        input_size: (32, 32),
+        embed_dim: 64,
+        num_image_tokens: 16,
+        ..VisionEncoderConfig::default()
+    })
+}
+
+/// Builds an input with the CLIP profile and no videos.
+fn build(prompt: &str, images: &[String], encoder: &StubVisionEncoder) -> Result<MultimodalInputGuard> {
+    build_multimodal_input(prompt, images, &[], encoder, &PreprocessProfile::clip())
+}
+
+#[test]
+fn test_guard_points_into_owned_buffers() {
+    let encoder = stub_encoder();
+    let mut guard = build("<image>\nWhat is this?", &[TINY_PNG.to_string()], &encoder).unwrap();
+    let input = guard.input_mut();
+    assert_eq!(input.input_type, RKLLMInputType_RKLLM_INPUT_MULTIMODAL);
+
+    let multimodal = unsafe { input.__bindgen_anon_1.multimodal_input };
+    let prompt = unsafe { std::ffi::CStr::from_ptr(multimodal.prompt) };
+    assert_eq!(prompt.to_str().unwrap(), "<image>\nWhat is this?");
+    assert_eq!(multimodal.image.n_image, 1);
+    assert_eq!(multimodal.image.n_image_tokens, 16);
+
+    let embeddings = unsafe { std::slice::from_raw_parts(multimodal.image.image_embed, 16 * 64) };
+    assert!(embeddings.iter().all(|v| v.is_finite()));
+}
+
+fn vision_config(encoder: VisionEncoderKind) -> VisionModelConfig {
+    VisionModelConfig {
+        model: "qwen2-vl".to_string(),
+        encoder,
+        path: None,
+        profile: Default::default(),
+        input_size: Some([32, 32]),
+        embed_dim: Some(64),
+        num_image_tokens: None,
+    }
+}
+
+#[test]
+fn test_stub_encoder_only_when_configured() {
+    let encoder = load_encoder(&vision_config(VisionEncoderKind::Stub)).unwrap();
+    assert_eq!(encoder.input_size(), (32, 32));
+    assert_eq!(encoder.embed_dim(), 64);
+    assert_eq!(encoder.num_image_tokens(), VisionEncoderConfig::default().num_image_tokens);
+
+    let err = load_encoder(&vision_config(VisionEncoderKind::Onnx)).err().unwrap();
+    assert!(err.to_string().contains("No encoder path configured for qwen2-vl"));
+}
+
+#[test]
+fn test_placeholders_expand_per_tile() {
+    assert_eq!(
+        expand_placeholders("<image>\nA <image> B", &[3, 1]),
+        "<image>\n<image>\n<image>\nA <image> B"
+    );
+    assert_eq!(expand_placeholders("no images", &[2]), "no images");
+}
+
+#[test]
+fn test_tiles_and_markers_reach_the_input() {
+    let encoder = stub_encoder();
+    let profile = PreprocessProfile {
+        tiles: preprocess::TileGrid {
+            min_tiles: 1,
+            max_tiles: 4,
+            thumbnail: preprocess::Thumbnail::Last,
+        },
+        image_start: Some("<img>".to_string()),
+        image_end: Some("</img>".to_string()),
+        ..PreprocessProfile::clip()
+    };
+    // 64x32 fills two 32x32 tiles side by side, plus the thumbnail.
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::RgbImage::new(64, 32)
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+    let wide = general_purpose::STANDARD.encode(png.into_inner());
+
+    let mut guard = build_multimodal_input("<image>\nDescribe.", &[wide], &[], &encoder, &profile).unwrap();
+    let multimodal = unsafe { guard.input_mut().__bindgen_anon_1.multimodal_input };
+    assert_eq!(multimodal.image.n_image, 3);
+    let text = |ptr: *const std::os::raw::c_char| {
+        unsafe { std::ffi::CStr::from_ptr(ptr) }.to_str().unwrap().to_string()
+    };
+    assert_eq!(text(multimodal.prompt as *const _), "<image>\n<image>\n<image>\nDescribe.");
+    assert_eq!(text(multimodal.image.image_start), "<img>");
+    assert_eq!(text(multimodal.image.image_end), "</img>");
+    assert!(multimodal.image.image_content.is_null());
+}
+
+#[test]
+fn test_videos_reach_the_input() {
+    let encoder = stub_encoder();
+    let frame = || DynamicImage::ImageRgb8(image::RgbImage::new(8, 8));
+    // Both videos are cut to the shorter one's two frames.
+    let videos = vec![vec![frame(), frame(), frame()], vec![frame(), frame()]];
+    let mut guard = build_multimodal_input(
+        "<video>\n<video>\nWhat happens?",
+        &[],
+        &videos,
+        &encoder,
+        &PreprocessProfile::qwen2_vl(),
+    )
+    .unwrap();
+    let input = guard.input_mut();
+    assert_eq!(input.input_type, RKLLMInputType_RKLLM_INPUT_MULTIMODAL);
+
+    let multimodal = unsafe { input.__bindgen_anon_1.multimodal_input };
+    assert_eq!(multimodal.image.n_image, 0);
+    assert!(multimodal.image.image_embed.is_null());
+    assert_eq!(multimodal.video.n_video, 2);
+    assert_eq!(multimodal.video.n_frame_per_video, 2);
+    assert_eq!(multimodal.video.n_frame_tokens, 16);
+    assert_eq!((multimodal.video.frame_width, multimodal.video.frame_height), (32, 32));
+    let embeddings = unsafe { std::slice::from_raw_parts(multimodal.video.video_embed, 2 * 2 * 16 * 64) };
+    assert!(embeddings.iter().all(|v| v.is_finite()));
+    let content = unsafe { std::ffi::CStr::from_ptr(multimodal.video.video_content) };
+    assert_eq!(content.to_str().unwrap(), "<|video_pad|>");
+}
@@ -0,0 +1,100 @@
+//! Leak tests for multimodal inputs
+//!
+//! These live in their own test binary because they install a counting
+//! global allocator, which would otherwise apply to every unit test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use anyhow::Result;
+use rkllm_shell::server::vision::{
+    build_multimodal_input, MultimodalInputGuard, PreprocessProfile, StubVisionEncoder,
+    VisionEncoderConfig,
+};
+
+/// 1x1 pixel PNG (base64 encoded)
+const TINY_PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==";
+
+/// 1x1 pixel JPEG (base64 encoded)
+const TINY_JPEG: &str = "/9j/4AAQSkZJRgABAQEASABIAAD/2wBDAAgGBgcGBQgHBwcJCQgKDBQNDAsLDBkSEw8UHRofHh0aHBwgJC4iLBwoOzs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozs7Ozv/wAALCAABAAEBAREA/8QAFAABAAAAAAAAAAAAAAAAAAAAAP/aAAgBAQAAPwDSzyD/2Q==";
+
+/// Counts the bytes each thread has allocated and not yet freed, so a test
+/// can check its own thread for leaks while other tests run in parallel.
+struct CountingAllocator;
+
+thread_local! {
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let _ = LIVE_BYTES.try_with(|n| n.set(n.get() + layout.size() as isize));
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        let _ = LIVE_BYTES.try_with(|n| n.set(n.get() - layout.size() as isize));
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn live_bytes() -> isize {
+    LIVE_BYTES.with(|n| n.get())
+}
+
+fn stub_encoder() -> StubVisionEncoder {
+    StubVisionEncoder::new(VisionEncoderConfig {
+        input_size: (32, 32),
+        embed_dim: 64,
+        num_image_tokens: 16,
+        ..VisionEncoderConfig::default()
+    })
+}
+
+/// Builds an input with the CLIP profile and no videos.
+fn build(prompt: &str, images: &[String], encoder: &StubVisionEncoder) -> Result<MultimodalInputGuard> {
+    build_multimodal_input(prompt, images, &[], encoder, &PreprocessProfile::clip())
+}
+
+/// Bytes still allocated after building and dropping `runs` inputs, measured
+/// after a warm-up run so one-off allocations are not counted.
+fn leaked_bytes(prompt: &str, images: &[String], runs: usize) -> isize {
+    let encoder = stub_encoder();
+    drop(build(prompt, images, &encoder).unwrap());
+    let before = live_bytes();
+    for _ in 0..runs {
+        let mut guard = build(prompt, images, &encoder).unwrap();
+        guard.set_enable_thinking(true);
+        drop(guard);
+    }
+    live_bytes() - before
+}
+
+#[test]
+fn test_text_only_input_does_not_leak() {
+    assert_eq!(leaked_bytes("Describe nothing.", &[], 100), 0);
+}
+
+#[test]
+fn test_multimodal_input_does_not_leak() {
+    let images = vec![TINY_PNG.to_string(), TINY_JPEG.to_string()];
+    assert_eq!(leaked_bytes("<image>\n<image>\nCompare these.", &images, 50), 0);
+}
+
+#[test]
+fn test_invalid_image_frees_prompt() {
+    let encoder = stub_encoder();
+    let images = vec!["not-an-image".to_string()];
+    assert!(build("<image>", &images, &encoder).is_err());
+    let before = live_bytes();
+    for _ in 0..10 {
+        assert!(build("<image>", &images, &encoder).is_err());
+    }
+    assert_eq!(live_bytes() - before, 0);
+}